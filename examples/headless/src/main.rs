@@ -5,6 +5,11 @@ fn main() {
 
     // Create a headless graphics context
     let context = HeadlessContext::new().unwrap();
+    println!(
+        "Using {:?} backend and {} renderer",
+        context.backend(),
+        context.renderer()
+    );
 
     // Create a camera
     let camera = Camera::new_perspective(
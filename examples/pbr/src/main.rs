@@ -137,6 +137,9 @@ pub async fn run() {
                     } else {
                         None
                     },
+                    lightmap_texture: None,
+                    lightmap_intensity: 1.0,
+                    lightmap_encoding: LightmapEncoding::Linear,
                     render_states: model.material.render_states,
                     is_transparent: model.material.is_transparent,
                     lighting_model: LightingModel::Cook(
@@ -47,6 +47,93 @@ pub fn main() {
             ..Default::default()
         },
     );
+    let outline = ShapeStyle {
+        fill_color: Srgba::new(255, 200, 0, 255),
+        stroke_color: Srgba::BLACK,
+        stroke_width: 3.0 * scale_factor,
+    };
+    let shapes = Shapes2D::new(
+        &context,
+        &[
+            (
+                Shape2D::Rectangle {
+                    center: (vec2(900.0, 550.0) * scale_factor).into(),
+                    width: 200.0 * scale_factor,
+                    height: 100.0 * scale_factor,
+                    rotation: degrees(-10.0).into(),
+                    corner_radius: 20.0 * scale_factor,
+                },
+                outline,
+            ),
+            (
+                Shape2D::Ellipse {
+                    center: (vec2(1100.0, 400.0) * scale_factor).into(),
+                    radius_x: 100.0 * scale_factor,
+                    radius_y: 50.0 * scale_factor,
+                    rotation: degrees(30.0).into(),
+                },
+                outline,
+            ),
+            (
+                Shape2D::Arc {
+                    center: (vec2(900.0, 250.0) * scale_factor).into(),
+                    radius: 80.0 * scale_factor,
+                    start_angle: degrees(0.0).into(),
+                    end_angle: degrees(270.0).into(),
+                    thickness: 20.0 * scale_factor,
+                },
+                outline,
+            ),
+            (
+                Shape2D::Polygon {
+                    points: vec![
+                        (vec2(1050.0, 100.0) * scale_factor).into(),
+                        (vec2(1200.0, 100.0) * scale_factor).into(),
+                        (vec2(1125.0, 250.0) * scale_factor).into(),
+                    ],
+                },
+                outline,
+            ),
+            (
+                Shape2D::Capsule {
+                    start: (vec2(700.0, 100.0) * scale_factor).into(),
+                    end: (vec2(800.0, 400.0) * scale_factor).into(),
+                    radius: 15.0 * scale_factor,
+                },
+                ShapeStyle {
+                    fill_color: Srgba::new(0, 0, 0, 0),
+                    ..outline
+                },
+            ),
+        ],
+    );
+    let path = Path::new()
+        .move_to(vec2(100.0, 600.0) * scale_factor)
+        .cubic_to(
+            vec2(200.0, 700.0) * scale_factor,
+            vec2(300.0, 500.0) * scale_factor,
+            vec2(400.0, 600.0) * scale_factor,
+        )
+        .line_to(vec2(400.0, 450.0) * scale_factor);
+    let curve = Gm::new(
+        Mesh::new(
+            &context,
+            &path.stroke(
+                &StrokeStyle {
+                    width: 8.0 * scale_factor,
+                    join: LineJoin::Round,
+                    cap: LineCap::Round,
+                    dash_pattern: vec![30.0 * scale_factor, 15.0 * scale_factor],
+                    ..Default::default()
+                },
+                0.25,
+            ),
+        ),
+        ColorMaterial {
+            color: Srgba::BLACK,
+            ..Default::default()
+        },
+    );
 
     window.render_loop(move |frame_input| {
         for event in frame_input.events.iter() {
@@ -78,7 +165,11 @@ pub fn main() {
             .clear(ClearState::color_and_depth(0.8, 0.8, 0.8, 1.0, 1.0))
             .render(
                 &Camera::new_2d(frame_input.viewport),
-                line.into_iter().chain(&rectangle).chain(&circle),
+                line.into_iter()
+                    .chain(&rectangle)
+                    .chain(&circle)
+                    .chain(&curve)
+                    .chain(&shapes),
                 &[],
             );
 
@@ -32,6 +32,19 @@ mod scissor_box;
 #[doc(inline)]
 pub use scissor_box::*;
 
+mod occlusion_query;
+#[doc(inline)]
+pub use occlusion_query::*;
+
+#[cfg(all(feature = "image-export", not(target_arch = "wasm32")))]
+#[cfg_attr(docsrs, doc(feature = "image-export"))]
+mod image_export;
+#[cfg(all(feature = "image-export", not(target_arch = "wasm32")))]
+pub(crate) use image_export::srgb_pixels;
+#[cfg(all(feature = "image-export", not(target_arch = "wasm32")))]
+#[doc(inline)]
+pub use image_export::*;
+
 pub mod prelude {
 
     //!
@@ -110,13 +123,56 @@ fn from_byte_slice<T: DataType>(data: &[u8]) -> &[T] {
     }
 }
 
-fn format_from_data_type<T: DataType>() -> u32 {
-    match T::size() {
-        1 => crate::context::RED,
-        2 => crate::context::RG,
-        3 => crate::context::RGB,
-        4 => crate::context::RGBA,
+// The texture data types always have between one and four channels, and the data type cannot be implemented outside of this crate
+fn format_from_data_type<T: TextureDataType>() -> u32 {
+    if T::data_type() == crate::context::UNSIGNED_INT {
+        match T::size() {
+            1 => crate::context::RED_INTEGER,
+            2 => crate::context::RG_INTEGER,
+            3 => crate::context::RGB_INTEGER,
+            4 => crate::context::RGBA_INTEGER,
+            _ => unreachable!(),
+        }
+    } else {
+        match T::size() {
+            1 => crate::context::RED,
+            2 => crate::context::RG,
+            3 => crate::context::RGB,
+            4 => crate::context::RGBA,
+            _ => unreachable!(),
+        }
+    }
+}
+
+///
+/// Creates a [CpuTexture] from pixels read from a render target, ie. the first pixel is the top left pixel.
+///
+pub(crate) fn cpu_texture_from_pixels<T: CpuTextureDataType>(
+    pixels: Vec<T>,
+    width: u32,
+    height: u32,
+) -> CpuTexture {
+    let bytes = to_byte_slice(&pixels);
+    let data = match (T::data_type(), T::size()) {
+        (crate::context::UNSIGNED_BYTE, 1) => TextureData::RU8(from_byte_slice(bytes).to_vec()),
+        (crate::context::UNSIGNED_BYTE, 2) => TextureData::RgU8(from_byte_slice(bytes).to_vec()),
+        (crate::context::UNSIGNED_BYTE, 3) => TextureData::RgbU8(from_byte_slice(bytes).to_vec()),
+        (crate::context::UNSIGNED_BYTE, 4) => TextureData::RgbaU8(from_byte_slice(bytes).to_vec()),
+        (crate::context::HALF_FLOAT, 1) => TextureData::RF16(from_byte_slice(bytes).to_vec()),
+        (crate::context::HALF_FLOAT, 2) => TextureData::RgF16(from_byte_slice(bytes).to_vec()),
+        (crate::context::HALF_FLOAT, 3) => TextureData::RgbF16(from_byte_slice(bytes).to_vec()),
+        (crate::context::HALF_FLOAT, 4) => TextureData::RgbaF16(from_byte_slice(bytes).to_vec()),
+        (crate::context::FLOAT, 1) => TextureData::RF32(from_byte_slice(bytes).to_vec()),
+        (crate::context::FLOAT, 2) => TextureData::RgF32(from_byte_slice(bytes).to_vec()),
+        (crate::context::FLOAT, 3) => TextureData::RgbF32(from_byte_slice(bytes).to_vec()),
+        (crate::context::FLOAT, 4) => TextureData::RgbaF32(from_byte_slice(bytes).to_vec()),
         _ => unreachable!(),
+    };
+    CpuTexture {
+        data,
+        width,
+        height,
+        ..Default::default()
     }
 }
 
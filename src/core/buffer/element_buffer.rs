@@ -102,6 +102,18 @@ impl ElementBuffer {
     pub(crate) fn data_type(&self) -> u32 {
         self.data_type
     }
+
+    ///
+    /// The offset in bytes to the index with the given position in the buffer.
+    ///
+    pub(crate) fn byte_offset(&self, index: u32) -> i32 {
+        let size = match self.data_type {
+            crate::context::UNSIGNED_BYTE => 1,
+            crate::context::UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+        (index * size) as i32
+    }
 }
 
 impl Drop for ElementBuffer {
@@ -17,8 +17,20 @@ pub struct Context {
     pub(super) vao: crate::context::VertexArray,
     /// A cache of programs to avoid recompiling a [Program] every frame.
     pub programs: Arc<RwLock<HashMap<Vec<u8>, Program>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) multi_draw_elements: Option<MultiDrawElements>,
 }
 
+// The signature of glMultiDrawElements, which is not exposed by the low-level graphics context.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) type MultiDrawElements = unsafe extern "system" fn(
+    mode: u32,
+    count: *const i32,
+    element_type: u32,
+    indices: *const *const std::ffi::c_void,
+    draw_count: i32,
+);
+
 impl Context {
     ///
     /// Creates a new mid-level context, used in this [core](crate::core) module, from a low-level OpenGL/WebGL context from the [context](crate::context) module.
@@ -44,11 +56,44 @@ impl Context {
                 context,
                 vao,
                 programs: Arc::new(RwLock::new(HashMap::new())),
+                #[cfg(not(target_arch = "wasm32"))]
+                multi_draw_elements: None,
             }
         };
         Ok(c)
     }
 
+    ///
+    /// Loads the OpenGL functions which are not available through the low-level graphics context, using the given function which returns the address of the OpenGL function with the given name.
+    /// Currently, this is only `glMultiDrawElements` which is used to draw several parts of an [ElementBuffer] in one call, see [Program::draw_subsets_of_elements].
+    /// This is done when creating a context using the [window](crate::window) module, otherwise it should be called after [Context::from_gl_context]
+    /// with the same function as used to create the low-level context, or a slower fallback is used.
+    ///
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_functions(
+        &mut self,
+        get_proc_address: impl FnMut(&str) -> *const std::ffi::c_void,
+    ) {
+        let name = if !self.version().is_embedded {
+            Some("glMultiDrawElements")
+        } else if self
+            .supported_extensions()
+            .contains("GL_EXT_multi_draw_arrays")
+        {
+            Some("glMultiDrawElementsEXT")
+        } else if self.supported_extensions().contains("GL_ANGLE_multi_draw") {
+            Some("glMultiDrawElementsANGLE")
+        } else {
+            None
+        };
+        self.multi_draw_elements = name
+            .map(get_proc_address)
+            .filter(|address| !address.is_null())
+            .map(|address| unsafe {
+                std::mem::transmute::<*const std::ffi::c_void, MultiDrawElements>(address)
+            });
+    }
+
     ///
     /// Set the scissor test for this context (see [ScissorBox]).
     ///
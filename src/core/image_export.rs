@@ -0,0 +1,246 @@
+use crate::core::*;
+use std::path::Path;
+use thiserror::Error;
+
+///
+/// Error when encoding or saving an image.
+///
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ImageExportError {
+    #[error("failed encoding the image: {0}")]
+    Encoding(#[from] image::ImageError),
+    #[error("failed writing the image to disk: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the image format of {0} is not supported, use png, jpeg, hdr or exr")]
+    UnsupportedFormat(String),
+    #[error("the size of the image ({0}x{1}) does not match the number of pixels ({2})")]
+    InvalidSize(u32, u32, usize),
+}
+
+///
+/// The file formats supported by [encode_image] and [save_image].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 8-bit PNG with an alpha channel if the texture has one. Colors are stored in sRGB color space.
+    Png,
+    /// 8-bit JPEG without alpha. Colors are stored in sRGB color space.
+    Jpeg,
+    /// Radiance HDR (RGBE) without alpha. Colors are stored in linear color space.
+    Hdr,
+    /// 32-bit float OpenEXR with an alpha channel if the texture has one. Colors are stored in linear color space.
+    OpenExr,
+}
+
+impl ImageFormat {
+    ///
+    /// Returns the format that matches the given file extension, if any.
+    ///
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "hdr" => Some(Self::Hdr),
+            "exr" => Some(Self::OpenExr),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Saves the texture as an image at the given path, where the file format is deduced from the file extension, see [ImageFormat].
+/// The texture is expected to be in the same orientation as images loaded from disk, ie. the first pixel is the top left pixel,
+/// which is the case for textures returned by for example [RenderTarget::read_color_cpu_texture].
+///
+/// Texture data with `u8` channels is assumed to be in sRGB color space and texture data with float channels is assumed to be in linear color space.
+/// The colors are converted if needed by the file format, so rendering into a float texture with no color mapping and saving it as an `.exr` or `.hdr` file
+/// keeps the high dynamic range while saving it as a `.png` or `.jpg` file maps the colors to sRGB.
+/// Textures with one channel are saved as grayscale and textures with two channels use the two channels as red and green.
+///
+pub fn save_image(texture: &CpuTexture, path: impl AsRef<Path>) -> Result<(), ImageExportError> {
+    let path = path.as_ref();
+    let format = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(ImageFormat::from_extension)
+        .ok_or_else(|| ImageExportError::UnsupportedFormat(path.display().to_string()))?;
+    std::fs::write(path, encode_image(texture, format)?)?;
+    Ok(())
+}
+
+///
+/// Encodes the texture as an image in the given format, see [save_image] for more information.
+///
+pub fn encode_image(
+    texture: &CpuTexture,
+    format: ImageFormat,
+) -> Result<Vec<u8>, ImageExportError> {
+    let (width, height) = (texture.width, texture.height);
+    let pixel_count = match &texture.data {
+        TextureData::RU8(data) => data.len(),
+        TextureData::RgU8(data) => data.len(),
+        TextureData::RgbU8(data) => data.len(),
+        TextureData::RgbaU8(data) => data.len(),
+        TextureData::RF16(data) => data.len(),
+        TextureData::RgF16(data) => data.len(),
+        TextureData::RgbF16(data) => data.len(),
+        TextureData::RgbaF16(data) => data.len(),
+        TextureData::RF32(data) => data.len(),
+        TextureData::RgF32(data) => data.len(),
+        TextureData::RgbF32(data) => data.len(),
+        TextureData::RgbaF32(data) => data.len(),
+    };
+    if pixel_count != width as usize * height as usize {
+        Err(ImageExportError::InvalidSize(width, height, pixel_count))?;
+    }
+    let has_alpha = matches!(
+        texture.data,
+        TextureData::RgbaU8(_) | TextureData::RgbaF16(_) | TextureData::RgbaF32(_)
+    );
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Png | ImageFormat::Jpeg => {
+            let pixels = srgb_pixels(&texture.data);
+            let image = image::DynamicImage::ImageRgba8(
+                image::RgbaImage::from_raw(width, height, pixels.into_iter().flatten().collect())
+                    .unwrap(),
+            );
+            if format == ImageFormat::Png && has_alpha {
+                image.write_to(&mut bytes, image::ImageOutputFormat::Png)?;
+            } else if format == ImageFormat::Png {
+                image::DynamicImage::ImageRgb8(image.to_rgb8())
+                    .write_to(&mut bytes, image::ImageOutputFormat::Png)?;
+            } else {
+                image::DynamicImage::ImageRgb8(image.to_rgb8())
+                    .write_to(&mut bytes, image::ImageOutputFormat::Jpeg(95))?;
+            }
+        }
+        ImageFormat::Hdr => {
+            let pixels = linear_pixels(&texture.data)
+                .into_iter()
+                .map(|p| image::Rgb([p[0], p[1], p[2]]))
+                .collect::<Vec<_>>();
+            image::codecs::hdr::HdrEncoder::new(&mut bytes).encode(
+                &pixels,
+                width as usize,
+                height as usize,
+            )?;
+        }
+        ImageFormat::OpenExr => {
+            let pixels = linear_pixels(&texture.data);
+            let image = if has_alpha {
+                image::DynamicImage::ImageRgba32F(
+                    image::ImageBuffer::from_raw(
+                        width,
+                        height,
+                        pixels.into_iter().flatten().collect(),
+                    )
+                    .unwrap(),
+                )
+            } else {
+                image::DynamicImage::ImageRgb32F(
+                    image::ImageBuffer::from_raw(
+                        width,
+                        height,
+                        pixels
+                            .into_iter()
+                            .flat_map(|p| [p[0], p[1], p[2]])
+                            .collect(),
+                    )
+                    .unwrap(),
+                )
+            };
+            image.write_to(&mut bytes, image::ImageOutputFormat::OpenExr)?;
+        }
+    }
+    Ok(bytes.into_inner())
+}
+
+///
+/// Returns the pixels as RGBA in sRGB color space, converting float data from linear color space.
+///
+pub(crate) fn srgb_pixels(data: &TextureData) -> Vec<[u8; 4]> {
+    let from_linear = |pixels: Vec<[f32; 4]>| {
+        pixels
+            .into_iter()
+            .map(|p| {
+                [
+                    linear_to_srgb(p[0]),
+                    linear_to_srgb(p[1]),
+                    linear_to_srgb(p[2]),
+                    (p[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+                ]
+            })
+            .collect()
+    };
+    match data {
+        TextureData::RU8(data) => data.iter().map(|v| [*v, *v, *v, 255]).collect(),
+        TextureData::RgU8(data) => data.iter().map(|v| [v[0], v[1], 0, 255]).collect(),
+        TextureData::RgbU8(data) => data.iter().map(|v| [v[0], v[1], v[2], 255]).collect(),
+        TextureData::RgbaU8(data) => data.clone(),
+        _ => from_linear(linear_pixels(data)),
+    }
+}
+
+///
+/// Returns the pixels as RGBA in linear color space, converting `u8` data from sRGB color space.
+///
+fn linear_pixels(data: &TextureData) -> Vec<[f32; 4]> {
+    let from_srgb = |pixels: Vec<[u8; 4]>| {
+        pixels
+            .into_iter()
+            .map(|p| {
+                [
+                    srgb_to_linear(p[0]),
+                    srgb_to_linear(p[1]),
+                    srgb_to_linear(p[2]),
+                    p[3] as f32 / 255.0,
+                ]
+            })
+            .collect()
+    };
+    match data {
+        TextureData::RF16(data) => data
+            .iter()
+            .map(|v| [v.to_f32(), v.to_f32(), v.to_f32(), 1.0])
+            .collect(),
+        TextureData::RgF16(data) => data
+            .iter()
+            .map(|v| [v[0].to_f32(), v[1].to_f32(), 0.0, 1.0])
+            .collect(),
+        TextureData::RgbF16(data) => data
+            .iter()
+            .map(|v| [v[0].to_f32(), v[1].to_f32(), v[2].to_f32(), 1.0])
+            .collect(),
+        TextureData::RgbaF16(data) => data
+            .iter()
+            .map(|v| [v[0].to_f32(), v[1].to_f32(), v[2].to_f32(), v[3].to_f32()])
+            .collect(),
+        TextureData::RF32(data) => data.iter().map(|v| [*v, *v, *v, 1.0]).collect(),
+        TextureData::RgF32(data) => data.iter().map(|v| [v[0], v[1], 0.0, 1.0]).collect(),
+        TextureData::RgbF32(data) => data.iter().map(|v| [v[0], v[1], v[2], 1.0]).collect(),
+        TextureData::RgbaF32(data) => data.clone(),
+        _ => from_srgb(srgb_pixels(data)),
+    }
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c < 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c < 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
@@ -0,0 +1,86 @@
+use crate::core::*;
+
+///
+/// A query which can be used to test whether any samples pass the depth test when drawing, for example to find out if an object is occluded by other objects.
+/// The result is computed asynchronously on the GPU, so it is typically read one or more frames after it was issued to avoid waiting.
+///
+pub struct OcclusionQuery {
+    context: Context,
+    id: crate::context::Query,
+    pending: bool,
+}
+
+impl OcclusionQuery {
+    ///
+    /// Creates a new occlusion query.
+    ///
+    pub fn new(context: &Context) -> Self {
+        let id = unsafe { context.create_query().expect("Failed creating query") };
+        Self {
+            context: context.clone(),
+            id,
+            pending: false,
+        }
+    }
+
+    ///
+    /// Begins the query. All draw calls until [OcclusionQuery::end] is called are included in the query.
+    /// Only one occlusion query can be active at a time.
+    ///
+    pub fn begin(&mut self) {
+        unsafe {
+            self.context
+                .begin_query(crate::context::ANY_SAMPLES_PASSED, self.id);
+        }
+        self.pending = true;
+    }
+
+    ///
+    /// Ends the query.
+    ///
+    pub fn end(&self) {
+        unsafe {
+            self.context.end_query(crate::context::ANY_SAMPLES_PASSED);
+        }
+    }
+
+    ///
+    /// Returns whether or not a query has been issued for which the result has not yet been read using [OcclusionQuery::result].
+    ///
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    ///
+    /// Returns whether any samples passed the depth test in the draw calls included in the last issued query,
+    /// or `None` if no query is pending or the result is not yet available. This never waits for the GPU.
+    ///
+    pub fn result(&mut self) -> Option<bool> {
+        if !self.pending {
+            return None;
+        }
+        unsafe {
+            if self
+                .context
+                .get_query_parameter_u32(self.id, crate::context::QUERY_RESULT_AVAILABLE)
+                == 0
+            {
+                return None;
+            }
+            self.pending = false;
+            Some(
+                self.context
+                    .get_query_parameter_u32(self.id, crate::context::QUERY_RESULT)
+                    != 0,
+            )
+        }
+    }
+}
+
+impl Drop for OcclusionQuery {
+    fn drop(&mut self) {
+        unsafe {
+            self.context.delete_query(self.id);
+        }
+    }
+}
@@ -502,9 +502,12 @@ impl Program {
 
     ///
     /// Draws a subset of the triangles defined by the given [ElementBuffer] with the given render states and viewport using this shader program.
+    /// The subset starts at the element with index `first` and contains `count` elements.
     /// Requires that all attributes and uniforms have been defined using the use_attribute and use_uniform methods.
     /// If you do not want to use an [ElementBuffer], see [Program::draw_arrays].
     ///
+    /// **Note:** `first` is the index of the first element and not a byte offset into the element buffer, which it was in earlier versions.
+    ///
     pub fn draw_subset_of_elements(
         &self,
         render_states: RenderStates,
@@ -522,7 +525,7 @@ impl Program {
                 crate::context::TRIANGLES,
                 count as i32,
                 element_buffer.data_type(),
-                first as i32,
+                element_buffer.byte_offset(first),
             );
             self.context
                 .bind_buffer(crate::context::ELEMENT_ARRAY_BUFFER, None);
@@ -540,6 +543,50 @@ impl Program {
             .expect("Unexpected rendering error occured")
     }
 
+    ///
+    /// Draws several subsets of the triangles defined by the given [ElementBuffer] with the given render states and viewport using this shader program.
+    /// Each subset is given by the index of the first element and the number of elements.
+    /// The subsets are drawn in one call using `glMultiDrawElements` if it is loaded (see [Context::load_functions]), otherwise they are drawn one at a time.
+    /// Requires that all attributes and uniforms have been defined using the use_attribute and use_uniform methods.
+    ///
+    pub fn draw_subsets_of_elements(
+        &self,
+        render_states: RenderStates,
+        viewport: Viewport,
+        element_buffer: &ElementBuffer,
+        subsets: &[(u32, u32)],
+    ) {
+        self.context.set_viewport(viewport);
+        self.context.set_render_states(render_states);
+        self.use_program();
+        element_buffer.bind();
+        unsafe {
+            if !self.multi_draw_elements(element_buffer, subsets) {
+                for (first, count) in subsets {
+                    self.context.draw_elements(
+                        crate::context::TRIANGLES,
+                        *count as i32,
+                        element_buffer.data_type(),
+                        element_buffer.byte_offset(*first),
+                    );
+                }
+            }
+            self.context
+                .bind_buffer(crate::context::ELEMENT_ARRAY_BUFFER, None);
+
+            for location in self.attributes.values() {
+                self.context.disable_vertex_attrib_array(*location);
+            }
+            self.context.bind_vertex_array(None);
+        }
+        self.unuse_program();
+
+        #[cfg(debug_assertions)]
+        self.context
+            .error_check()
+            .expect("Unexpected rendering error occured")
+    }
+
     ///
     /// Same as [Program::draw_elements] except it renders 'instance_count' instances of the same set of triangles.
     /// Use the [Program::use_instance_attribute] method to send unique data for each instance to the shader.
@@ -583,7 +630,7 @@ impl Program {
                 crate::context::TRIANGLES,
                 count as i32,
                 element_buffer.data_type(),
-                first as i32,
+                element_buffer.byte_offset(first),
                 instance_count as i32,
             );
             self.context
@@ -615,6 +662,41 @@ impl Program {
         self.attributes.contains_key(name)
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn multi_draw_elements(&self, element_buffer: &ElementBuffer, subsets: &[(u32, u32)]) -> bool {
+        let Some(multi_draw_elements) = self.context.multi_draw_elements else {
+            return false;
+        };
+        let counts = subsets
+            .iter()
+            .map(|(_, count)| *count as i32)
+            .collect::<Vec<_>>();
+        let offsets = subsets
+            .iter()
+            .map(|(first, _)| element_buffer.byte_offset(*first) as usize as *const _)
+            .collect::<Vec<_>>();
+        unsafe {
+            multi_draw_elements(
+                crate::context::TRIANGLES,
+                counts.as_ptr(),
+                element_buffer.data_type(),
+                offsets.as_ptr(),
+                subsets.len() as i32,
+            );
+        }
+        true
+    }
+
+    // glMultiDrawElements is not available in WebGL2 without an extension which is not exposed by the low-level graphics context.
+    #[cfg(target_arch = "wasm32")]
+    fn multi_draw_elements(
+        &self,
+        _element_buffer: &ElementBuffer,
+        _subsets: &[(u32, u32)],
+    ) -> bool {
+        false
+    }
+
     fn location(&self, name: &str) -> u32 {
         self.use_program();
         *self.attributes.get(name).unwrap_or_else(|| {
@@ -141,6 +141,7 @@ impl<'a> RenderTarget<'a> {
     /// The generic parameter `T` is limited to:
     /// - Unsigned byte RGBA (Specify `T` as either `Vec4<u8>` or `[u8; 4]`) which works with any render target using `u8` as its base type.
     /// - 32-bit float RGBA (Specify `T` as either `Vec4<f32>` or `[f32; 4]`) which works with any render target using `f16` or `f32` as its base type.
+    /// - 32-bit unsigned integer RGBA (Specify `T` as either `Vec4<u32>` or `[u32; 4]`) which works with any render target using `u32` as its base type.
     ///
     pub fn read_color<T: TextureDataType>(&self) -> Vec<T> {
         self.read_color_partially(self.scissor_box())
@@ -157,6 +158,7 @@ impl<'a> RenderTarget<'a> {
     /// The generic parameter `T` is limited to:
     /// - Unsigned byte RGBA (Specify `T` as either `Vec4<u8>` or `[u8; 4]`) which works with any render target using `u8` as its base type.
     /// - 32-bit float RGBA (Specify `T` as either `Vec4<f32>` or `[f32; 4]`) which works with any render target using `f16` or `f32` as its base type.
+    /// - 32-bit unsigned integer RGBA (Specify `T` as either `Vec4<u32>` or `[u32; 4]`) which works with any render target using `u32` as its base type.
     ///
     pub fn read_color_partially<T: TextureDataType>(&self, scissor_box: ScissorBox) -> Vec<T> {
         if self.id.is_some() && self.color.is_none() {
@@ -167,10 +169,11 @@ impl<'a> RenderTarget<'a> {
 
         // On web, the read format needs to be RGBA and f16 is not supported (see https://webglfundamentals.org/webgl/lessons/webgl-readpixels.html).
         #[cfg(target_arch = "wasm32")]
-        if format != crate::context::RGBA
-            || !(data_type == crate::context::UNSIGNED_BYTE || data_type == crate::context::FLOAT)
+        if !(format == crate::context::RGBA
+            && (data_type == crate::context::UNSIGNED_BYTE || data_type == crate::context::FLOAT)
+            || format == crate::context::RGBA_INTEGER && data_type == crate::context::UNSIGNED_INT)
         {
-            panic!("Only the texture data types `Vec4<T>` and `[T; 4]` where `T` is either `u8` or `f32` are supported when reading color from a render target on web.");
+            panic!("Only the texture data types `Vec4<T>` and `[T; 4]` where `T` is either `u8`, `f32` or `u32` are supported when reading color from a render target on web.");
         }
 
         self.bind(crate::context::DRAW_FRAMEBUFFER);
@@ -198,6 +201,23 @@ impl<'a> RenderTarget<'a> {
         pixels
     }
 
+    ///
+    /// Returns the colors of the pixels in this render target as a [CpuTexture], for example to save it as an image using `save_image` which requires the `image-export` feature.
+    /// The first pixel in the returned texture is the top left pixel, as for images loaded from disk.
+    /// The number of channels per pixel and the data format for each channel (`u8`, `f16` or `f32`) is specified by the generic parameter `T`, see [RenderTarget::read_color] for limitations.
+    ///
+    /// The colors are returned as they are stored in the render target, which means that the colors are in sRGB color space if the
+    /// [ColorMapping](crate::renderer::ColorMapping) of the camera maps to sRGB, which is the default, and otherwise in linear color space.
+    ///
+    pub fn read_color_cpu_texture<T: CpuTextureDataType>(&self) -> CpuTexture {
+        let scissor_box = self.scissor_box();
+        cpu_texture_from_pixels(
+            self.read_color_partially::<T>(scissor_box),
+            scissor_box.width,
+            scissor_box.height,
+        )
+    }
+
     ///
     /// Returns the depth values in this render target.
     ///
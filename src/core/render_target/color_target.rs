@@ -125,6 +125,7 @@ impl<'a> ColorTarget<'a> {
     /// The generic parameter `T` is limited to:
     /// - Unsigned byte RGBA (Specify `T` as either `Vec4<u8>` or `[u8; 4]`) which works with any color target using `u8` as its base type.
     /// - 32-bit float RGBA (Specify `T` as either `Vec4<f32>` or `[f32; 4]`) which works with any color target using `f16` or `f32` as its base type.
+    /// - 32-bit unsigned integer RGBA (Specify `T` as either `Vec4<u32>` or `[u32; 4]`) which works with any color target using `u32` as its base type.
     ///
     pub fn read<T: TextureDataType>(&self) -> Vec<T> {
         self.read_partially(self.scissor_box())
@@ -141,11 +142,21 @@ impl<'a> ColorTarget<'a> {
     /// The generic parameter `T` is limited to:
     /// - Unsigned byte RGBA (Specify `T` as either `Vec4<u8>` or `[u8; 4]`) which works with any color target using `u8` as its base type.
     /// - 32-bit float RGBA (Specify `T` as either `Vec4<f32>` or `[f32; 4]`) which works with any color target using `f16` or `f32` as its base type.
+    /// - 32-bit unsigned integer RGBA (Specify `T` as either `Vec4<u32>` or `[u32; 4]`) which works with any color target using `u32` as its base type.
     ///
     pub fn read_partially<T: TextureDataType>(&self, scissor_box: ScissorBox) -> Vec<T> {
         self.as_render_target().read_color_partially(scissor_box)
     }
 
+    ///
+    /// Returns the colors of the pixels in this color target as a [CpuTexture], for example to save it as an image using `save_image` which requires the `image-export` feature.
+    /// The first pixel in the returned texture is the top left pixel, as for images loaded from disk.
+    /// The number of channels per pixel and the data format for each channel (`u8`, `f16` or `f32`) is specified by the generic parameter `T`, see [ColorTarget::read] for limitations.
+    ///
+    pub fn read_cpu_texture<T: CpuTextureDataType>(&self) -> CpuTexture {
+        self.as_render_target().read_color_cpu_texture::<T>()
+    }
+
     ///
     /// Returns the width of the color target in texels.
     /// If using the zero mip level of the underlying texture, then this is simply the width of that texture, otherwise it is the width of the given mip level.
@@ -47,6 +47,7 @@ pub trait TextureDataType: DataType {}
 impl TextureDataType for u8 {}
 impl TextureDataType for f16 {}
 impl TextureDataType for f32 {}
+impl TextureDataType for u32 {}
 
 impl<T: TextureDataType + PrimitiveDataType> TextureDataType for Vector2<T> {}
 impl<T: TextureDataType + PrimitiveDataType> TextureDataType for Vector3<T> {}
@@ -59,6 +60,26 @@ impl TextureDataType for Quat {}
 
 impl<T: TextureDataType + ?Sized> TextureDataType for &T {}
 
+///
+/// The basic data type used for each channel of each pixel in a texture which can be stored in a [CpuTexture],
+/// ie. all of the [TextureDataType]s except those with `u32` channels.
+///
+pub trait CpuTextureDataType: TextureDataType {}
+impl CpuTextureDataType for u8 {}
+impl CpuTextureDataType for f16 {}
+impl CpuTextureDataType for f32 {}
+
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for Vector2<T> {}
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for Vector3<T> {}
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for Vector4<T> {}
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for [T; 2] {}
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for [T; 3] {}
+impl<T: CpuTextureDataType + PrimitiveDataType> CpuTextureDataType for [T; 4] {}
+
+impl CpuTextureDataType for Quat {}
+
+impl<T: CpuTextureDataType> CpuTextureDataType for &T {}
+
 /// The basic data type used for each pixel in a depth texture.
 pub trait DepthTextureDataType: DepthDataType {}
 
@@ -150,6 +150,16 @@ impl Texture2D {
         ColorTarget::new_texture2d(&self.context, self, mip_level)
     }
 
+    ///
+    /// Returns the pixels of this texture as a [CpuTexture], for example to save it as an image using `save_image` which requires the `image-export` feature.
+    /// The first pixel in the returned texture is the top left pixel, ie. the pixel at the uv coordinates `(0, 1)`, as for images loaded from disk.
+    /// The number of channels per pixel and the data format for each channel (`u8`, `f16` or `f32`) is specified by the generic parameter `T`,
+    /// and the base type of `T` must match the base type of this texture, see [ColorTarget::read] for more information.
+    ///
+    pub fn read_cpu_texture<T: CpuTextureDataType>(&self) -> CpuTexture {
+        ColorTarget::new_texture2d(&self.context, self, None).read_cpu_texture::<T>()
+    }
+
     /// The width of this texture.
     pub fn width(&self) -> u32 {
         self.width
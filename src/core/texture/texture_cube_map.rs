@@ -369,74 +369,98 @@ impl TextureCubeMap {
         front_data: &[T],
         back_data: &[T],
     ) {
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            right_data.len(),
-        );
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            left_data.len(),
-        );
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            top_data.len(),
-        );
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            bottom_data.len(),
-        );
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            front_data.len(),
+        self.fill_mip_level(
+            0,
+            right_data,
+            left_data,
+            top_data,
+            bottom_data,
+            front_data,
+            back_data,
         );
-        check_data_length::<T>(
-            self.width,
-            self.height,
-            1,
-            self.data_byte_size,
-            back_data.len(),
+        self.generate_mip_maps();
+    }
+
+    ///
+    /// Fills the given mip level of the cube map texture with the given pixel data for the 6 images.
+    /// In contrast to [TextureCubeMap::fill], the other mip levels are not generated from the given data,
+    /// which makes it possible to fill each mip level with precomputed data, for example a prefiltered environment map.
+    ///
+    /// # Panic
+    /// Will panic if the length of the data for all 6 images does not correspond to the size of the mip level and the format specified at construction
+    /// or if the mip level does not exist.
+    ///
+    pub fn fill_mip_level<T: TextureDataType>(
+        &mut self,
+        mip_level: u32,
+        right_data: &[T],
+        left_data: &[T],
+        top_data: &[T],
+        bottom_data: &[T],
+        front_data: &[T],
+        back_data: &[T],
+    ) {
+        assert!(
+            mip_level < self.number_of_mip_maps,
+            "the mip level {} does not exist, the texture has {} mip levels",
+            mip_level,
+            self.number_of_mip_maps
         );
+        let width = (self.width >> mip_level).max(1);
+        let height = (self.height >> mip_level).max(1);
+        let sides = [
+            right_data,
+            left_data,
+            top_data,
+            bottom_data,
+            front_data,
+            back_data,
+        ];
+        for data in sides {
+            check_data_length::<T>(width, height, 1, self.data_byte_size, data.len());
+        }
         self.bind();
-        for i in 0..6 {
-            let data = match i {
-                0 => right_data,
-                1 => left_data,
-                2 => top_data,
-                3 => bottom_data,
-                4 => front_data,
-                5 => back_data,
-                _ => unreachable!(),
-            };
+        for (i, data) in sides.into_iter().enumerate() {
             unsafe {
                 self.context.tex_sub_image_2d(
                     crate::context::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+                    mip_level as i32,
                     0,
                     0,
-                    0,
-                    self.width as i32,
-                    self.height as i32,
+                    width as i32,
+                    height as i32,
                     format_from_data_type::<T>(),
                     T::data_type(),
                     crate::context::PixelUnpackData::Slice(to_byte_slice(data)),
                 );
             }
         }
-        self.generate_mip_maps();
+    }
+
+    ///
+    /// Returns the colors of the pixels in the given side and mip level of this texture as a [CpuTexture], where the first pixel is the top left pixel
+    /// when looking in the [CubeMapSide::direction] of the side with [CubeMapSide::up] as the up direction.
+    /// The number of channels per pixel and the data format for each channel (`u8`, `f16` or `f32`) is specified by the generic parameter `T`,
+    /// and the base type of `T` must match the base type of this texture, see [ColorTarget::read] for more information.
+    ///
+    pub fn read_cpu_texture<T: CpuTextureDataType>(
+        &self,
+        side: CubeMapSide,
+        mip_level: u32,
+    ) -> CpuTexture {
+        ColorTarget::new_texture_cube_map(&self.context, self, &[side], Some(mip_level))
+            .read_cpu_texture::<T>()
+    }
+
+    pub(crate) fn data_byte_size(&self) -> usize {
+        self.data_byte_size
+    }
+
+    ///
+    /// Returns the number of mip levels of this texture.
+    ///
+    pub fn number_of_mip_levels(&self) -> u32 {
+        self.number_of_mip_maps
     }
 
     ///
@@ -503,6 +527,60 @@ impl TextureCubeMap {
         texture
     }
 
+    ///
+    /// Converts this cube map into an equirectangular texture with the given width and half the width as height, where the first pixel is the top left pixel.
+    /// This is the inverse of [TextureCubeMap::new_from_equirectangular] and can for example be used to save a 360° panorama to disk using `save_image` which requires the `image-export` feature.
+    /// The number of channels per pixel and the data format for each channel (`u8`, `f16` or `f32`) is specified by the generic parameter `T`, see [ColorTarget::read] for limitations.
+    ///
+    pub fn to_equirectangular<T: CpuTextureDataType>(&self, width: u32) -> CpuTexture {
+        let height = (width / 2).max(1);
+        let mut texture = Texture2D::new_empty::<T>(
+            &self.context,
+            width,
+            height,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let fragment_shader_source = "
+            uniform samplerCube cubeMap;
+
+            in vec2 uvs;
+
+            layout (location = 0) out vec4 outColor;
+
+            void main()
+            {
+                float phi = (uvs.x - 0.5) * 6.2832;
+                float theta = (uvs.y - 0.5) * 3.1416;
+                vec3 dir = vec3(cos(theta) * cos(phi), sin(theta), cos(theta) * sin(phi));
+                outColor = texture(cubeMap, dir);
+            }";
+        let program = Program::from_source(
+            &self.context,
+            full_screen_vertex_shader_source(),
+            fragment_shader_source,
+        )
+        .expect("Failed compiling shader");
+        let color_target = texture.as_color_target(None);
+        color_target
+            .clear(ClearState::default())
+            .write::<CoreError>(|| {
+                program.use_texture_cube("cubeMap", self);
+                full_screen_draw(
+                    &self.context,
+                    &program,
+                    RenderStates::default(),
+                    Viewport::new_at_origo(width, height),
+                );
+                Ok(())
+            })
+            .unwrap();
+        color_target.read_cpu_texture::<T>()
+    }
+
     ///
     /// Returns a [ColorTarget] which can be used to clear, write to and read from the given side and mip level of this texture.
     /// Combine this together with a [DepthTarget] with [RenderTarget::new] to be able to write to both a depth and color target at the same time.
@@ -0,0 +1,314 @@
+//!
+//! Support for golden image tests, ie. tests that render a scene and compare the result against a stored reference image.
+//! This is useful for catching unintended changes to the output of shaders, materials, lights and effects.
+//!
+//! Use a [GoldenImageTest] to render a scene with a [HeadlessContext](crate::HeadlessContext) and compare it against the reference image with the same name.
+//! When the images differ more than the [GoldenTolerance] allows, the rendered image and an image highlighting the differences are written to
+//! [GoldenImageTest::output_dir] and an error is returned.
+//! Set the `THREE_D_BLESS` environment variable to write the rendered images as new reference images instead of comparing them.
+//!
+//! Each [GoldenImageTest] creates its own [HeadlessContext](crate::HeadlessContext), so the tests need one of the EGL backends, which are available on Linux and other unix platforms.
+//! The hidden window backend, which is used on for example macOS and Windows, can only create one context per process,
+//! so creating a second [GoldenImageTest] in the same process returns [HeadlessError::EventLoopAlreadyCreated](crate::HeadlessError::EventLoopAlreadyCreated).
+//!
+
+use crate::core::*;
+use crate::{HeadlessContext, HeadlessError};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+///
+/// Error in a golden image test.
+///
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum GoldenError {
+    #[error("failed creating a headless context: {0}")]
+    Headless(#[from] HeadlessError),
+    #[error("failed saving an image: {0}")]
+    ImageExport(#[from] ImageExportError),
+    #[error("failed loading the reference image {0}: {1}")]
+    ReferenceLoading(PathBuf, image::ImageError),
+    #[error("the reference image {0} does not exist, run with the THREE_D_BLESS environment variable set to create it")]
+    MissingReference(PathBuf),
+    #[error("the rendered image is {0}x{1} but the reference image is {2}x{3}")]
+    SizeMismatch(u32, u32, u32, u32),
+    #[error(
+        "the rendered image {0} differs from the reference image: {1}, see the differences in {2}"
+    )]
+    Mismatch(String, GoldenComparison, PathBuf),
+}
+
+///
+/// Specifies how much a rendered image is allowed to differ from the reference image.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoldenTolerance {
+    /// The largest difference in any of the color channels, in the range `[0, 1]`, for which two pixels are considered equal.
+    /// The default is `0.02`.
+    pub pixel_tolerance: f32,
+    /// The largest fraction of pixels, in the range `[0, 1]`, which are allowed to differ by more than the pixel tolerance.
+    /// The default is `0.001`.
+    pub max_differing_pixels: f32,
+    /// The smallest allowed structural similarity index (SSIM) of the two images, where `1.0` means that the images are identical.
+    /// The default is `0.99`.
+    pub min_ssim: f32,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        Self {
+            pixel_tolerance: 0.02,
+            max_differing_pixels: 0.001,
+            min_ssim: 0.99,
+        }
+    }
+}
+
+///
+/// The result of comparing a rendered image against a reference image.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoldenComparison {
+    /// The number of pixels which differ by more than the pixel tolerance.
+    pub differing_pixels: usize,
+    /// The total number of pixels.
+    pub pixel_count: usize,
+    /// The largest difference in any of the color channels of any pixel, in the range `[0, 1]`.
+    pub max_difference: f32,
+    /// The structural similarity index (SSIM) of the luminance of the two images, where `1.0` means that the images are identical.
+    pub ssim: f32,
+}
+
+impl GoldenComparison {
+    ///
+    /// Returns whether the comparison is within the given tolerance.
+    ///
+    pub fn is_within(&self, tolerance: &GoldenTolerance) -> bool {
+        self.differing_pixels as f32 <= tolerance.max_differing_pixels * self.pixel_count as f32
+            && self.ssim >= tolerance.min_ssim
+    }
+}
+
+impl std::fmt::Display for GoldenComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} pixels differ, the largest difference is {:.3} and the SSIM is {:.4}",
+            self.differing_pixels, self.pixel_count, self.max_difference, self.ssim
+        )
+    }
+}
+
+///
+/// Renders scenes of a fixed size with a [HeadlessContext] and compares them against stored reference images, see the [module](crate::golden) documentation.
+///
+pub struct GoldenImageTest {
+    /// The tolerance used when comparing a rendered image against the reference image.
+    pub tolerance: GoldenTolerance,
+    /// The directory where the rendered image and the image highlighting the differences are written when a comparison fails.
+    /// The default is the `three-d-golden` directory in the temporary directory of the system.
+    pub output_dir: PathBuf,
+    context: HeadlessContext,
+    reference_dir: PathBuf,
+    width: u32,
+    height: u32,
+}
+
+impl GoldenImageTest {
+    ///
+    /// Creates a new golden image test which renders images of the given size and compares them against the reference images in the given directory.
+    ///
+    pub fn new(
+        reference_dir: impl AsRef<Path>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, GoldenError> {
+        Ok(Self {
+            tolerance: GoldenTolerance::default(),
+            output_dir: std::env::temp_dir().join("three-d-golden"),
+            context: HeadlessContext::new()?,
+            reference_dir: reference_dir.as_ref().to_path_buf(),
+            width,
+            height,
+        })
+    }
+
+    ///
+    /// Returns the graphics context used for rendering.
+    ///
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    ///
+    /// Returns the viewport of the rendered images, which should be used when creating a [Camera](crate::renderer::Camera).
+    ///
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new_at_origo(self.width, self.height)
+    }
+
+    ///
+    /// Renders into a render target with an 8-bit RGBA color texture and a depth texture and compares the result against the reference image with the given name.
+    /// The render target is not cleared before calling the `render` closure.
+    ///
+    pub fn render(
+        &self,
+        name: &str,
+        render: impl FnOnce(&RenderTarget),
+    ) -> Result<GoldenComparison, GoldenError> {
+        let mut color_texture = Texture2D::new_empty::<[u8; 4]>(
+            &self.context,
+            self.width,
+            self.height,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let mut depth_texture = DepthTexture2D::new::<f32>(
+            &self.context,
+            self.width,
+            self.height,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let render_target = RenderTarget::new(
+            color_texture.as_color_target(None),
+            depth_texture.as_depth_target(),
+        );
+        render(&render_target);
+        self.compare(name, &render_target.read_color_cpu_texture::<[u8; 4]>())
+    }
+
+    ///
+    /// Compares the given image against the reference image with the given name.
+    /// If the `THREE_D_BLESS` environment variable is set, the image is instead saved as the new reference image.
+    ///
+    pub fn compare(&self, name: &str, image: &CpuTexture) -> Result<GoldenComparison, GoldenError> {
+        let reference_path = self.reference_dir.join(format!("{}.png", name));
+        if std::env::var_os("THREE_D_BLESS").is_some() {
+            std::fs::create_dir_all(&self.reference_dir).map_err(ImageExportError::from)?;
+            save_image(image, &reference_path)?;
+            return Ok(GoldenComparison {
+                differing_pixels: 0,
+                pixel_count: (image.width * image.height) as usize,
+                max_difference: 0.0,
+                ssim: 1.0,
+            });
+        }
+        if !reference_path.exists() {
+            Err(GoldenError::MissingReference(reference_path.clone()))?;
+        }
+        let reference = image::open(&reference_path)
+            .map_err(|e| GoldenError::ReferenceLoading(reference_path.clone(), e))?
+            .to_rgba8();
+        if reference.width() != image.width || reference.height() != image.height {
+            Err(GoldenError::SizeMismatch(
+                image.width,
+                image.height,
+                reference.width(),
+                reference.height(),
+            ))?;
+        }
+        let actual = crate::core::srgb_pixels(&image.data);
+        let reference = reference.pixels().map(|p| p.0).collect::<Vec<_>>();
+
+        let mut differing_pixels = 0;
+        let mut max_difference = 0.0f32;
+        let diff = actual
+            .iter()
+            .zip(reference.iter())
+            .map(|(a, r)| {
+                let difference = (0..4)
+                    .map(|i| (a[i] as f32 - r[i] as f32).abs() / 255.0)
+                    .fold(0.0, f32::max);
+                max_difference = max_difference.max(difference);
+                if difference > self.tolerance.pixel_tolerance {
+                    differing_pixels += 1;
+                    [255, (255.0 * (1.0 - difference)) as u8, 0, 255]
+                } else {
+                    let l = (0.3 * luminance(r)) as u8;
+                    [l, l, l, 255]
+                }
+            })
+            .collect::<Vec<_>>();
+        let comparison = GoldenComparison {
+            differing_pixels,
+            pixel_count: actual.len(),
+            max_difference,
+            ssim: ssim(
+                &actual,
+                &reference,
+                image.width as usize,
+                image.height as usize,
+            ),
+        };
+
+        if !comparison.is_within(&self.tolerance) {
+            std::fs::create_dir_all(&self.output_dir).map_err(ImageExportError::from)?;
+            save_image(image, self.output_dir.join(format!("{}.actual.png", name)))?;
+            let diff_path = self.output_dir.join(format!("{}.diff.png", name));
+            save_image(
+                &CpuTexture {
+                    data: TextureData::RgbaU8(diff),
+                    width: image.width,
+                    height: image.height,
+                    ..Default::default()
+                },
+                &diff_path,
+            )?;
+            Err(GoldenError::Mismatch(
+                name.to_owned(),
+                comparison,
+                diff_path,
+            ))?;
+        }
+        Ok(comparison)
+    }
+}
+
+fn luminance(color: &[u8; 4]) -> f32 {
+    0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32
+}
+
+///
+/// Computes the mean structural similarity index of the luminance of the two images using 8x8 windows which overlap by half a window.
+///
+fn ssim(a: &[[u8; 4]], b: &[[u8; 4]], width: usize, height: usize) -> f32 {
+    const C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+    let window_width = width.min(8);
+    let window_height = height.min(8);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y0 in (0..=height - window_height).step_by((window_height / 2).max(1)) {
+        for x0 in (0..=width - window_width).step_by((window_width / 2).max(1)) {
+            let pixels = (y0..y0 + window_height)
+                .flat_map(|y| (x0..x0 + window_width).map(move |x| y * width + x))
+                .map(|i| (luminance(&a[i]), luminance(&b[i])))
+                .collect::<Vec<_>>();
+            let n = pixels.len() as f32;
+            let mean_a = pixels.iter().map(|p| p.0).sum::<f32>() / n;
+            let mean_b = pixels.iter().map(|p| p.1).sum::<f32>() / n;
+            let (mut variance_a, mut variance_b, mut covariance) = (0.0, 0.0, 0.0);
+            for (la, lb) in pixels {
+                variance_a += (la - mean_a) * (la - mean_a);
+                variance_b += (lb - mean_b) * (lb - mean_b);
+                covariance += (la - mean_a) * (lb - mean_b);
+            }
+            variance_a /= n;
+            variance_b /= n;
+            covariance /= n;
+            sum += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            count += 1;
+        }
+    }
+    if count == 0 {
+        1.0
+    } else {
+        sum / count as f32
+    }
+}
@@ -23,3 +23,7 @@ pub use window::*;
 mod gui;
 #[allow(unused_imports)]
 pub use gui::*;
+
+#[cfg(all(feature = "golden-test", not(target_arch = "wasm32")))]
+#[cfg_attr(docsrs, doc(feature = "golden-test"))]
+pub mod golden;
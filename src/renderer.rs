@@ -29,6 +29,14 @@ pub enum RendererError {
     InvalidBufferLength(String, usize, usize),
     #[error("the material {0} is required by the geometry {1} but could not be found")]
     MissingMaterial(String, String),
+    #[error("failed loading environment: {0}")]
+    InvalidEnvironmentData(String),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("failed reading or writing the environment file {0}: {1}")]
+    EnvironmentIo(std::path::PathBuf, std::io::Error),
+    #[cfg(feature = "text")]
+    #[error("failed parsing font: {0}")]
+    FontParsing(String),
 }
 
 mod camera;
@@ -52,6 +60,27 @@ pub use object::*;
 pub mod control;
 pub use control::*;
 
+mod picking;
+pub use picking::*;
+
+mod occlusion_culling;
+pub use occlusion_culling::*;
+
+mod tiled_rendering;
+pub use tiled_rendering::*;
+
+mod cube_map_capture;
+pub use cube_map_capture::*;
+
+mod lightmap_baker;
+pub use lightmap_baker::*;
+
+#[cfg(feature = "text")]
+#[cfg_attr(docsrs, doc(cfg(feature = "text")))]
+pub mod text;
+#[cfg(feature = "text")]
+pub use text::*;
+
 macro_rules! impl_render_target_extensions_body {
     () => {
         ///
@@ -151,6 +180,57 @@ macro_rules! impl_render_target_extensions_body {
             self
         }
 
+        ///
+        /// Render the objects using the given camera and lights into this render target like [Self::render],
+        /// except that objects which were hidden behind other objects in the previous frame are not rendered, see [OcclusionCulling].
+        ///
+        pub fn render_with_occlusion_culling(
+            &self,
+            camera: &Camera,
+            objects: impl IntoIterator<Item = impl Object>,
+            lights: &[&dyn Light],
+            occlusion_culling: &mut OcclusionCulling,
+        ) -> &Self {
+            self.render_partially_with_occlusion_culling(
+                self.scissor_box(),
+                camera,
+                objects,
+                lights,
+                occlusion_culling,
+            )
+        }
+
+        ///
+        /// Render the objects using the given camera and lights into the part of this render target defined by the scissor box like [Self::render_partially],
+        /// except that objects which were hidden behind other objects in the previous frame are not rendered, see [OcclusionCulling].
+        ///
+        pub fn render_partially_with_occlusion_culling(
+            &self,
+            scissor_box: ScissorBox,
+            camera: &Camera,
+            objects: impl IntoIterator<Item = impl Object>,
+            lights: &[&dyn Light],
+            occlusion_culling: &mut OcclusionCulling,
+        ) -> &Self {
+            let objects = objects.into_iter().collect::<Vec<_>>();
+            let visibility = occlusion_culling.visibility(camera, &objects);
+            self.render_partially(
+                scissor_box,
+                camera,
+                objects
+                    .iter()
+                    .zip(visibility)
+                    .filter_map(|(object, visible)| visible.then_some(object)),
+                lights,
+            );
+            self.write_partially::<RendererError>(scissor_box, || {
+                occlusion_culling.test(camera, &objects);
+                Ok(())
+            })
+            .unwrap();
+            self
+        }
+
         ///
         /// Render the geometries with the given [Material] using the given camera and lights into this render target.
         /// Use an empty array for the `lights` argument, if the material does not require lights to be rendered.
@@ -12,6 +12,7 @@ use crate::core::*;
 #[derive(Clone, Debug)]
 pub struct Camera {
     camera: three_d_asset::Camera,
+    tile_projection: Option<Mat4>,
     /// This tone mapping is applied to the final color of renders using this camera.
     pub tone_mapping: ToneMapping,
     /// This color mapping is applied to the final color of renders using this camera.
@@ -35,6 +36,7 @@ impl Camera {
             camera: three_d_asset::Camera::new_orthographic(
                 viewport, position, target, up, height, z_near, z_far,
             ),
+            tile_projection: None,
             tone_mapping: ToneMapping::default(),
             color_mapping: ColorMapping::default(),
         }
@@ -62,6 +64,7 @@ impl Camera {
                 z_near,
                 z_far,
             ),
+            tile_projection: None,
             tone_mapping: ToneMapping::default(),
             color_mapping: ColorMapping::default(),
         }
@@ -108,6 +111,73 @@ impl Camera {
         self.tone_mapping = ToneMapping::default();
         self.color_mapping = ColorMapping::default();
     }
+
+    ///
+    /// Returns a camera which renders the given region of the viewport of this camera into a viewport of the same size as the region placed at origo.
+    /// The region is specified in pixels relative to the bottom left corner of the viewport of this camera and is allowed to extend outside the viewport.
+    /// This is done by using an off-center part of the projection of this camera, so rendering all regions of the viewport with the returned cameras
+    /// gives the same result as rendering the entire viewport with this camera, which is for example used by [TiledRenderer](crate::renderer::TiledRenderer) to render images larger than the maximum texture size.
+    ///
+    /// The returned camera is intended for rendering only, changing the projection or viewport of the returned camera has no effect on its projection
+    /// and the functions which relate pixels to positions, for example [position_at_pixel](three_d_asset::Camera::position_at_pixel), do not take the region into account.
+    ///
+    pub fn tile(&self, region: Viewport) -> Self {
+        let viewport = self.viewport();
+        let (width, height) = (viewport.width as f32, viewport.height as f32);
+        let (region_width, region_height) = (region.width as f32, region.height as f32);
+        let window =
+            Mat4::from_translation(vec3(
+                (width - 2.0 * region.x as f32 - region_width) / region_width,
+                (height - 2.0 * region.y as f32 - region_height) / region_height,
+                0.0,
+            )) * Mat4::from_nonuniform_scale(width / region_width, height / region_height, 1.0);
+        let mut camera = self.clone();
+        camera.tile_projection = Some(window * self.projection());
+        camera
+            .camera
+            .set_viewport(Viewport::new_at_origo(region.width, region.height));
+        camera
+    }
+
+    ///
+    /// Returns the projection matrix, which is an off-center part of the projection of the original camera if this camera is created using [Camera::tile].
+    ///
+    pub fn projection(&self) -> &Mat4 {
+        self.tile_projection
+            .as_ref()
+            .unwrap_or_else(|| self.camera.projection())
+    }
+
+    ///
+    /// Returns whether or not the given bounding box is within the camera frustum.
+    /// It returns false if it is fully outside and true if it is inside or intersects.
+    ///
+    pub fn in_frustum(&self, aabb: &AxisAlignedBoundingBox) -> bool {
+        if self.tile_projection.is_none() || aabb.is_infinite() {
+            return self.camera.in_frustum(aabb);
+        }
+        let m = self.projection() * self.view();
+        let rows = [0, 1, 2, 3].map(|i| vec4(m.x[i], m.y[i], m.z[i], m.w[i]));
+        let planes = [
+            rows[3] + rows[0],
+            rows[3] - rows[0],
+            rows[3] + rows[1],
+            rows[3] - rows[1],
+            rows[3] + rows[2],
+            rows[3] - rows[2],
+        ];
+        let (min, max) = (aabb.min(), aabb.max());
+        // The box is outside if the corner furthest along the normal of one of the planes is outside that plane
+        planes.iter().all(|plane| {
+            let corner = vec4(
+                if plane.x > 0.0 { max.x } else { min.x },
+                if plane.y > 0.0 { max.y } else { min.y },
+                if plane.z > 0.0 { max.z } else { min.z },
+                1.0,
+            );
+            plane.dot(corner) >= 0.0
+        })
+    }
 }
 
 use std::ops::Deref;
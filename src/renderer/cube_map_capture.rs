@@ -0,0 +1,115 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// Captures the scene as seen from a point in all directions into a [TextureCubeMap].
+/// Each side of the cube map is rendered with a camera with a 90° field of view looking in the [CubeMapSide::direction] of that side.
+///
+/// The captured cube map can be used as the environment map in for example [Skybox::new_with_texture], [Environment::new] and [AmbientLight::new_with_environment],
+/// which makes it possible to create dynamic reflection maps by capturing the scene every frame or whenever it changes.
+/// Use [TextureCubeMap::to_equirectangular] to convert the captured cube map into a 360° panorama, for example for VR previews.
+///
+/// The colors are captured in linear color space without tone mapping into a 16-bit float texture, so the high dynamic range of the scene is preserved.
+///
+pub struct CubeMapCapture {
+    /// The distance to the near plane of the cameras used for capturing.
+    pub z_near: f32,
+    /// The distance to the far plane of the cameras used for capturing.
+    pub z_far: f32,
+    texture: TextureCubeMap,
+    depth_texture: DepthTexture2D,
+}
+
+impl CubeMapCapture {
+    ///
+    /// Creates a new cube map capture where each side of the cube map has the given size in pixels.
+    ///
+    pub fn new(context: &Context, size: u32) -> Self {
+        Self {
+            z_near: 0.01,
+            z_far: 1000.0,
+            texture: TextureCubeMap::new_empty::<[f16; 4]>(
+                context,
+                size,
+                size,
+                Interpolation::Linear,
+                Interpolation::Linear,
+                Some(Interpolation::Linear),
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+            depth_texture: DepthTexture2D::new::<f32>(
+                context,
+                size,
+                size,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+        }
+    }
+
+    ///
+    /// Returns the camera used for capturing the given side of the cube map from the given position.
+    ///
+    pub fn camera(&self, position: Vec3, side: CubeMapSide) -> Camera {
+        let mut camera = Camera::new_perspective(
+            Viewport::new_at_origo(self.texture.width(), self.texture.height()),
+            position,
+            position + side.direction(),
+            side.up(),
+            degrees(90.0),
+            self.z_near,
+            self.z_far,
+        );
+        camera.disable_tone_and_color_mapping();
+        camera
+    }
+
+    ///
+    /// Renders the objects using the given lights into all sides of the cube map as seen from the given position.
+    /// Each side is cleared using the given clear state before rendering.
+    ///
+    pub fn render(
+        &mut self,
+        position: Vec3,
+        clear_state: ClearState,
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        lights: &[&dyn Light],
+    ) -> &TextureCubeMap {
+        for side in CubeMapSide::iter() {
+            let camera = self.camera(position, side);
+            let sides = [side];
+            RenderTarget::new(
+                self.texture.as_color_target(&sides, None),
+                self.depth_texture.as_depth_target(),
+            )
+            .clear(clear_state)
+            .render(&camera, objects.clone(), lights);
+        }
+        &self.texture
+    }
+
+    ///
+    /// Returns the colors of the pixels of the given side of the last capture, where the first pixel is the top left pixel
+    /// when looking in the [CubeMapSide::direction] of the side with [CubeMapSide::up] as the up direction.
+    /// The number of channels per pixel and the data format for each channel is specified by the generic parameter `T`, see [ColorTarget::read] for limitations.
+    ///
+    pub fn read_side<T: TextureDataType>(&mut self, side: CubeMapSide) -> Vec<T> {
+        self.texture.as_color_target(&[side], Some(0)).read()
+    }
+
+    ///
+    /// Returns the cube map containing the last capture.
+    ///
+    pub fn texture(&self) -> &TextureCubeMap {
+        &self.texture
+    }
+
+    ///
+    /// Returns the cube map containing the last capture and consumes the capture.
+    ///
+    pub fn into_texture(self) -> TextureCubeMap {
+        self.texture
+    }
+}
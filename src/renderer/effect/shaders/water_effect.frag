@@ -12,6 +12,12 @@ uniform vec4 environmentColor;
 uniform float metallic;
 uniform float roughness;
 
+#ifdef USE_FOAM
+uniform vec4 foamColor;
+uniform float foamShorelineDepth;
+uniform float foamCrestThreshold;
+#endif
+
 in vec3 pos;
 in vec3 nor;
 in vec2 uvs;
@@ -61,6 +67,19 @@ vec3 water(vec3 col, vec3 p1, vec3 p2)
     return colorChange * col + (1. - colorChange) * equilibriumColorAtInfinity;
 }
 
+#ifdef USE_FOAM
+float foam(vec3 waterBottomPos)
+{
+    // The unnormalized normal from the vertex shader is compressed in the y-direction where the waves are steep
+    float compression = clamp(1.0 - nor.y, 0.0, 1.0);
+    float crest = clamp((compression - foamCrestThreshold) / max(1.0 - foamCrestThreshold, 0.001), 0.0, 1.0);
+
+    float waterDepth = max(pos.y - waterBottomPos.y, 0.0);
+    float shoreline = 1.0 - smoothstep(0.0, max(foamShorelineDepth, 0.001), waterDepth);
+    return max(crest, shoreline);
+}
+#endif
+
 void main()
 {
     vec2 screen_uv = gl_FragCoord.xy/screenSize;
@@ -87,6 +106,12 @@ void main()
     // Mix refraction and reflection
     outColor.rgb = mix(refractColor, reflectColor, fresnel);
 
+#ifdef USE_FOAM
+    vec2 bottom_uv = gl_FragCoord.xy/screenSize;
+    vec3 bottomPos = world_pos_from_depth(viewProjectionInverse, sample_depth(bottom_uv), bottom_uv);
+    outColor.rgb = mix(outColor.rgb, foamColor.rgb, foamColor.a * foam(bottomPos));
+#endif
+
     outColor.rgb = calculate_lighting(cameraPosition, outColor.rgb, pos, normal, metallic, roughness, 1.0);
     outColor.rgb = tone_mapping(outColor.rgb);
     outColor.rgb = color_mapping(outColor.rgb);
@@ -17,6 +17,29 @@ impl Default for Background {
     }
 }
 
+///
+/// Parameters that control the foam added to the water surface by the [WaterEffect].
+///
+#[derive(Clone, Copy, Debug)]
+pub struct FoamParameters {
+    /// The color of the foam.
+    pub color: Srgba,
+    /// Foam is added where the water is shallower than this depth, for example along the shoreline.
+    pub shoreline_depth: f32,
+    /// A value in the range `[0..1]` specifying how steep the wave crests must be before foam is added, where `0` adds foam to all crests and `1` adds no crest foam.
+    pub crest_threshold: f32,
+}
+
+impl Default for FoamParameters {
+    fn default() -> Self {
+        Self {
+            color: Srgba::WHITE,
+            shoreline_depth: 0.5,
+            crest_threshold: 0.5,
+        }
+    }
+}
+
 ///
 /// An effect that simulates a water surface and should therefore only be applied to a water surface geometry.
 /// This effect needs the rendered scene (without the water surface) in a color and depth texture to be able to add reflections and refractions.
@@ -31,6 +54,8 @@ pub struct WaterEffect {
     pub roughness: f32,
     /// The lighting model used when rendering this effect
     pub lighting_model: LightingModel,
+    /// Adds foam on steep wave crests and in shallow water if specified.
+    pub foam: Option<FoamParameters>,
 }
 
 impl Effect for WaterEffect {
@@ -41,11 +66,16 @@ impl Effect for WaterEffect {
         depth_texture: Option<DepthTexture>,
     ) -> String {
         format!(
-            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
             match &self.background {
                 Background::Color(_) => "",
                 Background::Texture(_) => "#define USE_BACKGROUND_TEXTURE",
             },
+            if self.foam.is_some() {
+                "#define USE_FOAM"
+            } else {
+                ""
+            },
             color_texture
                 .expect("Must supply a color texture to apply a water effect")
                 .fragment_shader_source(),
@@ -63,6 +93,7 @@ impl Effect for WaterEffect {
         0b1u16 << 14
             | 0b1u16 << 12
             | 0b1u16 << 11
+            | if self.foam.is_some() { 0b1u16 << 10 } else { 0 }
             | color_texture
                 .expect("Must supply a color texture to apply a water effect")
                 .id()
@@ -127,6 +158,11 @@ impl Effect for WaterEffect {
             }
             Background::Texture(tex) => program.use_texture_cube("environmentMap", tex),
         }
+        if let Some(foam) = &self.foam {
+            program.use_uniform("foamColor", foam.color.to_linear_srgb());
+            program.use_uniform("foamShorelineDepth", foam.shoreline_depth);
+            program.use_uniform("foamCrestThreshold", foam.crest_threshold);
+        }
     }
 }
 
@@ -137,6 +173,7 @@ impl Default for WaterEffect {
             metallic: 0.0,
             roughness: 1.0,
             lighting_model: LightingModel::Blinn,
+            foam: None,
         }
     }
 }
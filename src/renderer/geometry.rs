@@ -85,6 +85,30 @@ mod circle;
 #[doc(inline)]
 pub use circle::*;
 
+mod path;
+#[doc(inline)]
+pub use path::*;
+
+mod lines_3d;
+#[doc(inline)]
+pub use lines_3d::*;
+
+mod bvh;
+#[doc(inline)]
+pub use bvh::*;
+
+mod lod_mesh;
+#[doc(inline)]
+pub use lod_mesh::*;
+
+mod instanced_lod_mesh;
+#[doc(inline)]
+pub use instanced_lod_mesh::*;
+
+mod mesh_batch;
+#[doc(inline)]
+pub use mesh_batch::*;
+
 use crate::core::*;
 use crate::renderer::*;
 
@@ -263,6 +287,7 @@ struct BaseMesh {
     normals: Option<VertexBuffer>,
     tangents: Option<VertexBuffer>,
     uvs: Option<VertexBuffer>,
+    uvs2: Option<VertexBuffer>,
     colors: Option<VertexBuffer>,
 }
 
@@ -287,15 +312,11 @@ impl BaseMesh {
                 .tangents
                 .as_ref()
                 .map(|data| VertexBuffer::new_with_data(context, data)),
-            uvs: cpu_mesh.uvs.as_ref().map(|data| {
-                VertexBuffer::new_with_data(
-                    context,
-                    &data
-                        .iter()
-                        .map(|uv| vec2(uv.x, 1.0 - uv.y))
-                        .collect::<Vec<_>>(),
-                )
-            }),
+            uvs: cpu_mesh
+                .uvs
+                .as_ref()
+                .map(|data| Self::uv_buffer(context, data)),
+            uvs2: None,
             colors: cpu_mesh.colors.as_ref().map(|data| {
                 VertexBuffer::new_with_data(
                     context,
@@ -305,6 +326,26 @@ impl BaseMesh {
         }
     }
 
+    ///
+    /// Sets the second set of uv coordinates, which is not part of a [CpuMesh].
+    ///
+    pub fn set_uvs2(&mut self, context: &Context, uvs: &[Vec2]) {
+        if uvs.len() as u32 != self.positions.vertex_count() {
+            panic!("Failed updating the second set of uv coordinates: The number of uv coordinates {} does not match the number of vertices {} in the mesh.", uvs.len(), self.positions.vertex_count())
+        }
+        self.uvs2 = Some(Self::uv_buffer(context, uvs));
+    }
+
+    fn uv_buffer(context: &Context, uvs: &[Vec2]) -> VertexBuffer {
+        // The uv coordinates are flipped since the textures are flipped when uploaded to the GPU
+        VertexBuffer::new_with_data(
+            context,
+            &uvs.iter()
+                .map(|uv| vec2(uv.x, 1.0 - uv.y))
+                .collect::<Vec<_>>(),
+        )
+    }
+
     pub fn draw(
         &self,
         program: &Program,
@@ -354,7 +395,8 @@ impl BaseMesh {
     fn use_attributes(&self, program: &Program, attributes: FragmentAttributes) {
         program.use_vertex_attribute("position", &self.positions);
 
-        if attributes.normal {
+        // The normals and tangents are only used for lighting, so they are removed by the shader compiler when for example only using baked lighting
+        if attributes.normal && program.requires_attribute("normal") {
             program.use_vertex_attribute(
                 "normal",
                 self.normals.as_ref().unwrap_or_else(|| {
@@ -365,7 +407,7 @@ impl BaseMesh {
             );
         }
 
-        if attributes.tangents {
+        if attributes.tangents && program.requires_attribute("tangent") {
             program.use_vertex_attribute(
                 "tangent",
                 self.tangents.as_ref().unwrap_or_else(|| {
@@ -387,6 +429,17 @@ impl BaseMesh {
             );
         }
 
+        if attributes.uv2 {
+            program.use_vertex_attribute(
+                "uv2_coordinates",
+                self.uvs2.as_ref().unwrap_or_else(|| {
+                    panic!(
+                        "the material requires a second set of uv coordinate attributes but the geometry did not provide it"
+                    )
+                }),
+            );
+        }
+
         if attributes.color {
             if let Some(colors) = &self.colors {
                 program.use_vertex_attribute("color", colors);
@@ -394,3 +447,40 @@ impl BaseMesh {
         }
     }
 }
+
+///
+/// Returns the start and end distances of the dashes along a line of the given length which starts the given distance into the dash pattern.
+/// The dash pattern contains alternating lengths of dashes and gaps and, like the SVG `stroke-dasharray`, a pattern with an odd number of lengths is repeated twice,
+/// so `[5.0]` is the same as `[5.0, 5.0]`.
+/// Returns [None] if the pattern does not define any dashes, in which case the line should be solid.
+///
+fn dash_intervals(length: f32, pattern: &[f32], offset: f32) -> Option<Vec<(f32, f32)>> {
+    if !pattern.iter().any(|l| *l > 0.0) || pattern.iter().any(|l| *l < 0.0 || !l.is_finite()) {
+        return None;
+    }
+    let pattern = if pattern.len() % 2 == 1 {
+        [pattern, pattern].concat()
+    } else {
+        pattern.to_vec()
+    };
+
+    // Find the position in the pattern at the start of the line
+    let total: f32 = pattern.iter().sum();
+    let mut start = -offset.rem_euclid(total);
+    let mut index = 0;
+    while start + pattern[index] <= 0.0 {
+        start += pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+
+    let mut intervals = Vec::new();
+    while start < length {
+        let end = start + pattern[index];
+        if index % 2 == 0 && end.min(length) > start.max(0.0) {
+            intervals.push((start.max(0.0), end.min(length)));
+        }
+        start = end;
+        index = (index + 1) % pattern.len();
+    }
+    Some(intervals)
+}
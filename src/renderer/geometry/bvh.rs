@@ -0,0 +1,647 @@
+use crate::renderer::*;
+
+///
+/// A triangle found by one of the queries on a [Bvh].
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BvhHit {
+    /// The index of the instance that contains the triangle, which is zero if the bounding volume hierarchy is not instanced.
+    pub instance: u32,
+    /// The index of the triangle in the mesh, ie. the index of the first of the three vertices (or vertex indices) divided by three.
+    pub triangle: u32,
+    /// The barycentric coordinates of the position with respect to the three vertices of the triangle.
+    pub barycentric: Vec3,
+    /// The position on the triangle in world space.
+    /// For ray and segment queries, this is the intersection point, otherwise it is the point on the triangle closest to the query center.
+    pub position: Vec3,
+    /// The normal of the triangle in world space, which points towards the side where the vertices are ordered counter clockwise.
+    pub normal: Vec3,
+    /// The distance from the ray origin, segment start or query center to the position.
+    pub distance: f32,
+}
+
+///
+/// A bounding volume hierarchy built from a [CpuMesh] on the CPU, which enables fast ray, segment, sphere and box queries against the triangles of the mesh
+/// without a round trip to the GPU, see [ray_intersect] for the GPU alternative.
+/// The mesh can be instanced using the same [Instances] as an [InstancedMesh].
+///
+/// The hierarchy is built in the local space of the mesh, so use [Bvh::set_transformation] with the transformation of the corresponding [Mesh] or [InstancedMesh]
+/// to query in world space.
+///
+pub struct Bvh {
+    positions: Vec<Vec3>,
+    triangles: Vec<[usize; 3]>,
+    triangle_tree: Tree,
+    transformation: Mat4,
+    instance_transformations: Vec<Mat4>,
+    world_transformations: Vec<(Mat4, Mat4)>,
+    instance_bounds: Vec<Bounds>,
+    instance_tree: Tree,
+}
+
+impl Bvh {
+    ///
+    /// Builds a bounding volume hierarchy for the triangles of the given mesh.
+    ///
+    pub fn new(cpu_mesh: &CpuMesh) -> Self {
+        Self::new_instanced(
+            cpu_mesh,
+            &Instances {
+                transformations: vec![Mat4::identity()],
+                ..Default::default()
+            },
+        )
+    }
+
+    ///
+    /// Builds a bounding volume hierarchy for the triangles of the given mesh, which is repeated for each of the given instances like an [InstancedMesh].
+    ///
+    pub fn new_instanced(cpu_mesh: &CpuMesh, instances: &Instances) -> Self {
+        let positions = cpu_mesh.positions.to_f32();
+        let indices = cpu_mesh
+            .indices
+            .to_u32()
+            .unwrap_or_else(|| (0..positions.len() as u32).collect());
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect::<Vec<_>>();
+        let bounds = triangles
+            .iter()
+            .map(|t| Bounds::new(&[positions[t[0]], positions[t[1]], positions[t[2]]]))
+            .collect::<Vec<_>>();
+        let mut bvh = Self {
+            triangle_tree: Tree::new(&bounds),
+            positions,
+            triangles,
+            transformation: Mat4::identity(),
+            instance_transformations: Vec::new(),
+            world_transformations: Vec::new(),
+            instance_bounds: Vec::new(),
+            instance_tree: Tree::new(&[]),
+        };
+        bvh.set_instances(instances);
+        bvh
+    }
+
+    ///
+    /// Returns the local to world transformation applied to all instances.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.transformation
+    }
+
+    ///
+    /// Set the local to world transformation applied to all instances, which should be the same as the transformation of the corresponding [Mesh] or [InstancedMesh].
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        self.transformation = transformation;
+        self.update_instances();
+    }
+
+    ///
+    /// Replaces the instances, which should be the same as the instances of the corresponding [InstancedMesh].
+    /// Only the transformations of the instances are used.
+    ///
+    pub fn set_instances(&mut self, instances: &Instances) {
+        #[cfg(debug_assertions)]
+        instances.validate().expect("invalid instances");
+        self.instance_transformations = instances.transformations.clone();
+        self.update_instances();
+    }
+
+    ///
+    /// Returns the number of instances.
+    ///
+    pub fn instance_count(&self) -> u32 {
+        self.instance_transformations.len() as u32
+    }
+
+    ///
+    /// Returns the axis aligned bounding box of all instances in world space.
+    ///
+    pub fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.instance_tree
+            .nodes
+            .first()
+            .map(|n| n.bounds.aabb())
+            .unwrap_or(AxisAlignedBoundingBox::EMPTY)
+    }
+
+    ///
+    /// Finds the closest triangle hit by the ray starting at the given position in the given direction.
+    /// Returns ```None``` if no triangle was hit before the given maximum distance.
+    ///
+    pub fn ray_intersect(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<BvhHit> {
+        let direction = direction.normalize();
+        // Visit the instances in the order they are entered by the ray, so the rest can be skipped when a closer hit is found
+        let mut instances = Vec::new();
+        self.instance_tree.traverse(
+            |bounds| {
+                bounds
+                    .ray_distance(origin, direction, max_distance)
+                    .is_some()
+            },
+            |instance| {
+                if let Some(t) =
+                    self.instance_bounds[instance].ray_distance(origin, direction, max_distance)
+                {
+                    instances.push((t, instance));
+                }
+            },
+        );
+        instances.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut closest: Option<BvhHit> = None;
+        for (entry, instance) in instances {
+            let max = closest.map(|c| c.distance).unwrap_or(max_distance);
+            if entry > max {
+                break;
+            }
+            let (world, inverse) = self.world_transformations[instance];
+            // The direction is not normalized in local space, which means the ray parameter is the same in both spaces
+            let local_origin = (inverse * origin.extend(1.0)).truncate();
+            let local_direction = (inverse * direction.extend(0.0)).truncate();
+            let best = std::cell::Cell::new(None::<(usize, f32, Vec3)>);
+            self.triangle_tree.traverse(
+                |bounds| {
+                    let max = best.get().map(|b| b.1).unwrap_or(max);
+                    bounds
+                        .ray_distance(local_origin, local_direction, max)
+                        .is_some()
+                },
+                |triangle| {
+                    let max = best.get().map(|b| b.1).unwrap_or(max);
+                    let [p0, p1, p2] = self.triangle_positions(triangle);
+                    if let Some((t, barycentric)) =
+                        ray_triangle(local_origin, local_direction, p0, p1, p2)
+                    {
+                        if t <= max {
+                            best.set(Some((triangle, t, barycentric)));
+                        }
+                    }
+                },
+            );
+            if let Some((triangle, t, barycentric)) = best.get() {
+                closest = Some(self.hit(
+                    instance,
+                    triangle,
+                    barycentric,
+                    &world,
+                    origin + direction * t,
+                    t,
+                ));
+            }
+        }
+        closest
+    }
+
+    ///
+    /// Finds the triangle hit closest to the start of the line segment between the two given positions.
+    ///
+    pub fn segment_intersect(&self, start: Vec3, end: Vec3) -> Option<BvhHit> {
+        let length = start.distance(end);
+        if length < f32::EPSILON {
+            return None;
+        }
+        self.ray_intersect(start, end - start, length)
+    }
+
+    ///
+    /// Finds all triangles that intersect the sphere with the given center and radius.
+    /// For each triangle, the hit contains the point on the triangle closest to the center.
+    ///
+    pub fn sphere_intersect(&self, center: Vec3, radius: f32) -> Vec<BvhHit> {
+        let query = Bounds {
+            min: center - vec3(radius, radius, radius),
+            max: center + vec3(radius, radius, radius),
+        };
+        self.query(&query, |[p0, p1, p2]| {
+            let (position, barycentric) = closest_point_on_triangle(center, p0, p1, p2);
+            if position.distance2(center) <= radius * radius {
+                Some((position, barycentric, position.distance(center)))
+            } else {
+                None
+            }
+        })
+    }
+
+    ///
+    /// Finds all triangles that intersect the given axis aligned bounding box.
+    /// For each triangle, the hit contains the point on the triangle closest to the center of the box.
+    ///
+    pub fn aabb_intersect(&self, aabb: &AxisAlignedBoundingBox) -> Vec<BvhHit> {
+        if aabb.is_empty() {
+            return Vec::new();
+        }
+        let query = Bounds {
+            min: aabb.min(),
+            max: aabb.max(),
+        };
+        let center = aabb.center();
+        self.query(&query, |[p0, p1, p2]| {
+            if triangle_overlaps_box(p0, p1, p2, center, 0.5 * aabb.size()) {
+                let (position, barycentric) = closest_point_on_triangle(center, p0, p1, p2);
+                Some((position, barycentric, position.distance(center)))
+            } else {
+                None
+            }
+        })
+    }
+
+    ///
+    /// Tests all triangles whose bounds overlap the given world space bounds with the given test, which receives the triangle in world space.
+    ///
+    fn query(
+        &self,
+        query: &Bounds,
+        test: impl Fn([Vec3; 3]) -> Option<(Vec3, Vec3, f32)>,
+    ) -> Vec<BvhHit> {
+        let mut hits = Vec::new();
+        self.instance_tree.traverse(
+            |bounds| bounds.overlaps(query),
+            |instance| {
+                let (world, inverse) = self.world_transformations[instance];
+                // The query bounds in local space is conservative, the exact test is done in world space
+                let local_query = query.transform(&inverse);
+                self.triangle_tree.traverse(
+                    |bounds| bounds.overlaps(&local_query),
+                    |triangle| {
+                        let [p0, p1, p2] = self.triangle_positions(triangle);
+                        let transform = |p: Vec3| (world * p.extend(1.0)).truncate();
+                        if let Some((position, barycentric, distance)) =
+                            test([transform(p0), transform(p1), transform(p2)])
+                        {
+                            hits.push(self.hit(
+                                instance,
+                                triangle,
+                                barycentric,
+                                &world,
+                                position,
+                                distance,
+                            ));
+                        }
+                    },
+                );
+            },
+        );
+        hits
+    }
+
+    fn hit(
+        &self,
+        instance: usize,
+        triangle: usize,
+        barycentric: Vec3,
+        world: &Mat4,
+        position: Vec3,
+        distance: f32,
+    ) -> BvhHit {
+        let [p0, p1, p2] = self
+            .triangle_positions(triangle)
+            .map(|p| (world * p.extend(1.0)).truncate());
+        BvhHit {
+            instance: instance as u32,
+            triangle: triangle as u32,
+            barycentric,
+            position,
+            normal: (p1 - p0).cross(p2 - p0).normalize(),
+            distance,
+        }
+    }
+
+    fn triangle_positions(&self, triangle: usize) -> [Vec3; 3] {
+        self.triangles[triangle].map(|i| self.positions[i])
+    }
+
+    fn update_instances(&mut self) {
+        let local_bounds = self
+            .triangle_tree
+            .nodes
+            .first()
+            .map(|n| n.bounds)
+            .unwrap_or(Bounds::EMPTY);
+        self.world_transformations = self
+            .instance_transformations
+            .iter()
+            .map(|t| {
+                let world = self.transformation * t;
+                (world, world.invert().unwrap_or(Mat4::identity()))
+            })
+            .collect();
+        self.instance_bounds = self
+            .world_transformations
+            .iter()
+            .map(|(world, _)| local_bounds.transform(world))
+            .collect();
+        self.instance_tree = Tree::new(&self.instance_bounds);
+    }
+}
+
+///
+/// Axis aligned bounds which are cheaper to work with than [AxisAlignedBoundingBox] during traversal.
+///
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Bounds {
+    const EMPTY: Self = Self {
+        min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+    };
+
+    fn new(positions: &[Vec3]) -> Self {
+        let mut bounds = Self::EMPTY;
+        for p in positions {
+            bounds.expand(&Self { min: *p, max: *p });
+        }
+        bounds
+    }
+
+    fn expand(&mut self, other: &Self) {
+        self.min = vec3(
+            self.min.x.min(other.min.x),
+            self.min.y.min(other.min.y),
+            self.min.z.min(other.min.z),
+        );
+        self.max = vec3(
+            self.max.x.max(other.max.x),
+            self.max.y.max(other.max.y),
+            self.max.z.max(other.max.z),
+        );
+    }
+
+    fn center(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+            && self.min.z <= other.max.z
+            && self.max.z >= other.min.z
+    }
+
+    fn transform(&self, transformation: &Mat4) -> Self {
+        let mut corners = Vec::with_capacity(8);
+        for i in 0..8 {
+            let corner = vec3(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            corners.push((transformation * corner.extend(1.0)).truncate());
+        }
+        Self::new(&corners)
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        AxisAlignedBoundingBox::new_with_positions(&[self.min, self.max])
+    }
+
+    ///
+    /// Returns the distance along the ray to the bounds using the slab method, if the ray hits the bounds before the maximum distance.
+    ///
+    fn ray_distance(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = max_distance;
+        for i in 0..3 {
+            let inverse = 1.0 / direction[i];
+            let mut t0 = (self.min[i] - origin[i]) * inverse;
+            let mut t1 = (self.max[i] - origin[i]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Comparisons with NaN are false, which keeps the current values when the ray is parallel to the slab and starts on its boundary
+            if t0 > t_min {
+                t_min = t0;
+            }
+            if t1 < t_max {
+                t_max = t1;
+            }
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+}
+
+///
+/// A node in a [Tree]. Leaf nodes reference a range of the indices, while inner nodes have the left child directly after the node itself.
+///
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Bounds,
+    start: u32,
+    count: u32,
+    right: u32,
+}
+
+///
+/// A bounding volume hierarchy over a set of bounds.
+///
+struct Tree {
+    nodes: Vec<Node>,
+    indices: Vec<u32>,
+}
+
+const MAX_LEAF_SIZE: usize = 4;
+
+impl Tree {
+    fn new(bounds: &[Bounds]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            indices: (0..bounds.len() as u32).collect(),
+        };
+        if !bounds.is_empty() {
+            tree.build(bounds, 0, bounds.len());
+        }
+        tree
+    }
+
+    fn build(&mut self, bounds: &[Bounds], start: usize, end: usize) {
+        let mut node_bounds = Bounds::EMPTY;
+        let mut centers = Bounds::EMPTY;
+        for i in start..end {
+            let b = &bounds[self.indices[i] as usize];
+            node_bounds.expand(b);
+            centers.expand(&Bounds::new(&[b.center()]));
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            start: start as u32,
+            count: (end - start) as u32,
+            right: 0,
+        });
+        let extent = centers.max - centers.min;
+        if end - start <= MAX_LEAF_SIZE || extent.x.max(extent.y).max(extent.z) <= 0.0 {
+            return;
+        }
+
+        // Split at the median along the longest axis of the centers
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let middle = (start + end) / 2;
+        self.indices[start..end].select_nth_unstable_by(middle - start, |a, b| {
+            bounds[*a as usize].center()[axis]
+                .partial_cmp(&bounds[*b as usize].center()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.nodes[index].count = 0;
+        self.build(bounds, start, middle);
+        self.nodes[index].right = self.nodes.len() as u32;
+        self.build(bounds, middle, end);
+    }
+
+    ///
+    /// Visits all leaf items in nodes for which the given test returns true.
+    ///
+    fn traverse(&self, mut test: impl FnMut(&Bounds) -> bool, mut visit: impl FnMut(usize)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.bounds) {
+                continue;
+            }
+            if node.count > 0 {
+                for i in node.start..node.start + node.count {
+                    visit(self.indices[i as usize] as usize);
+                }
+            } else {
+                stack.push(node.right as usize);
+                stack.push(index + 1);
+            }
+        }
+    }
+}
+
+///
+/// Returns the ray parameter and barycentric coordinates of the intersection between the ray and the triangle (Möller–Trumbore).
+///
+fn ray_triangle(
+    origin: Vec3,
+    direction: Vec3,
+    p0: Vec3,
+    p1: Vec3,
+    p2: Vec3,
+) -> Option<(f32, Vec3)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let p = direction.cross(e2);
+    let determinant = e1.dot(p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s = origin - p0;
+    let u = s.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(q) * inverse;
+    if t < 0.0 {
+        return None;
+    }
+    Some((t, vec3(1.0 - u - v, u, v)))
+}
+
+///
+/// Returns the point on the triangle closest to the given point together with its barycentric coordinates.
+///
+fn closest_point_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> (Vec3, Vec3) {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return (a, vec3(1.0, 0.0, 0.0));
+    }
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return (b, vec3(0.0, 1.0, 0.0));
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, vec3(1.0 - v, v, 0.0));
+    }
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return (c, vec3(0.0, 0.0, 1.0));
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, vec3(1.0 - w, 0.0, w));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, vec3(0.0, 1.0 - w, w));
+    }
+    let denominator = 1.0 / (va + vb + vc);
+    let v = vb * denominator;
+    let w = vc * denominator;
+    (a + ab * v + ac * w, vec3(1.0 - v - w, v, w))
+}
+
+///
+/// Returns whether or not the triangle overlaps the box with the given center and half size using the separating axis theorem.
+///
+fn triangle_overlaps_box(p0: Vec3, p1: Vec3, p2: Vec3, center: Vec3, half_size: Vec3) -> bool {
+    let v = [p0 - center, p1 - center, p2 - center];
+    let edges = [v[1] - v[0], v[2] - v[1], v[0] - v[2]];
+    let separated = |axis: Vec3| {
+        if axis.magnitude2() < 1e-12 {
+            return false;
+        }
+        let p = v.map(|v| v.dot(axis));
+        let r =
+            half_size.x * axis.x.abs() + half_size.y * axis.y.abs() + half_size.z * axis.z.abs();
+        p[0].min(p[1]).min(p[2]) > r || p[0].max(p[1]).max(p[2]) < -r
+    };
+    let box_axes = [
+        vec3(1.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(0.0, 0.0, 1.0),
+    ];
+    if box_axes.iter().any(|a| separated(*a)) {
+        return false;
+    }
+    if separated(edges[0].cross(edges[1])) {
+        return false;
+    }
+    !box_axes
+        .iter()
+        .any(|a| edges.iter().any(|e| separated(a.cross(*e))))
+}
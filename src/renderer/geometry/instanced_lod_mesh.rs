@@ -0,0 +1,295 @@
+use crate::core::*;
+use crate::renderer::*;
+use std::sync::Arc;
+
+use super::lod_mesh::{screen_size, select_level, simplified_levels};
+
+///
+/// Similar to [LodMesh], except it is possible to render many instances of the same mesh efficiently, see [InstancedMesh].
+/// The level of detail is selected for each instance based on the size of that instance on the screen
+/// when calling [InstancedLodMesh::update_levels] with the camera used to view the scene, typically once each frame before rendering.
+///
+pub struct InstancedLodMesh {
+    ///
+    /// The width of the transition between two levels relative to the screen size threshold.
+    /// The level of an instance is only changed when the screen size moves outside of the transition,
+    /// which avoids switching back and forth between two levels when the screen size is close to the threshold.
+    /// Negative values are treated as zero.
+    ///
+    pub hysteresis: f32,
+    levels: Vec<InstancedMesh>,
+    screen_sizes: Vec<f32>,
+    instances: Instances,
+    current_levels: Vec<usize>,
+    aabb_local: AxisAlignedBoundingBox,
+    transformation: Mat4,
+    current_transformation: Mat4,
+    animation: Option<Arc<dyn Fn(f32) -> Mat4 + Send + Sync>>,
+}
+
+impl InstancedLodMesh {
+    ///
+    /// Creates a new instanced mesh with the given levels of detail, where the first [CpuMesh] is the most detailed,
+    /// and the minimum screen size at which each of the levels is used, see [LodMesh::new].
+    ///
+    /// # Panic
+    /// Will panic if the number of levels and screen sizes are not the same or if no levels are given.
+    ///
+    pub fn new(
+        context: &Context,
+        instances: &Instances,
+        cpu_meshes: &[CpuMesh],
+        screen_sizes: &[f32],
+    ) -> Self {
+        assert!(
+            !cpu_meshes.is_empty() && cpu_meshes.len() == screen_sizes.len(),
+            "a screen size must be specified for each of the levels of detail"
+        );
+        let mut mesh = Self {
+            hysteresis: 0.1,
+            levels: cpu_meshes
+                .iter()
+                .map(|m| {
+                    let mut level = InstancedMesh::new(context, &Instances::default(), m);
+                    // All levels are drawn with the same program, so they must use the same instance buffers
+                    level.use_full_transformations();
+                    level
+                })
+                .collect(),
+            screen_sizes: screen_sizes.to_vec(),
+            instances: Instances::default(),
+            current_levels: Vec::new(),
+            aabb_local: cpu_meshes[0].compute_aabb(),
+            transformation: Mat4::identity(),
+            current_transformation: Mat4::identity(),
+            animation: None,
+        };
+        mesh.set_instances(instances);
+        mesh
+    }
+
+    ///
+    /// Creates a new instanced mesh with a level of detail for each of the given screen sizes,
+    /// where the levels are generated by simplifying the given [CpuMesh], see [LodMesh::new_simplified].
+    ///
+    pub fn new_simplified(
+        context: &Context,
+        instances: &Instances,
+        cpu_mesh: &CpuMesh,
+        screen_sizes: &[f32],
+    ) -> Self {
+        Self::new(
+            context,
+            instances,
+            &simplified_levels(cpu_mesh, screen_sizes.len()),
+            screen_sizes,
+        )
+    }
+
+    ///
+    /// Returns the number of levels of detail.
+    ///
+    pub fn level_count(&self) -> usize {
+        self.screen_sizes.len()
+    }
+
+    ///
+    /// Returns the number of instances rendered with each of the levels, as selected the last time [InstancedLodMesh::update_levels] was called.
+    ///
+    pub fn level_instance_counts(&self) -> Vec<u32> {
+        self.levels
+            .iter()
+            .map(|level| level.instance_count())
+            .collect()
+    }
+
+    ///
+    /// Returns the number of instances.
+    ///
+    pub fn instance_count(&self) -> u32 {
+        self.instances.count()
+    }
+
+    ///
+    /// Update the instances.
+    /// If the number of instances is unchanged, each instance keeps its current level until [InstancedLodMesh::update_levels] is called, otherwise all instances use level 0.
+    ///
+    pub fn set_instances(&mut self, instances: &Instances) {
+        #[cfg(debug_assertions)]
+        instances.validate().expect("invalid instances");
+        self.instances = instances.clone();
+        if self.current_levels.len() != instances.count() as usize {
+            self.current_levels = vec![0; instances.count() as usize];
+        }
+        self.update_level_instances();
+    }
+
+    ///
+    /// Returns the local to world transformation applied to all instances.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.transformation
+    }
+
+    ///
+    /// Set the local to world transformation applied to all instances.
+    /// This is applied before the transform for each instance.
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        self.transformation = transformation;
+        self.current_transformation = transformation;
+        for level in self.levels.iter_mut() {
+            level.set_transformation(transformation);
+        }
+    }
+
+    ///
+    /// Specifies a function which takes a time parameter as input and returns a transformation that should be applied to all instances at the given time.
+    /// See [InstancedMesh::set_animation] for more information.
+    ///
+    pub fn set_animation(&mut self, animation: impl Fn(f32) -> Mat4 + Send + Sync + 'static) {
+        let animation = Arc::new(animation);
+        for level in self.levels.iter_mut() {
+            let animation = animation.clone();
+            level.set_animation(move |time| animation(time));
+        }
+        self.animation = Some(animation);
+    }
+
+    ///
+    /// Selects the level of each instance based on the size of the instance on the screen when viewed with the given camera.
+    /// Call this with the camera used to view the scene each time the camera or the instances have moved, typically once each frame.
+    /// The instances are only sent to the GPU again if the level of any of the instances changed.
+    ///
+    pub fn update_levels(&mut self, camera: &Camera) {
+        let levels = self
+            .instances
+            .transformations
+            .iter()
+            .zip(self.current_levels.iter())
+            .map(|(transformation, current_level)| {
+                let mut aabb = self.aabb_local;
+                aabb.transform(&(self.current_transformation * transformation));
+                select_level(
+                    screen_size(camera, &aabb),
+                    &self.screen_sizes,
+                    self.hysteresis,
+                    *current_level,
+                )
+            })
+            .collect::<Vec<_>>();
+        if levels != self.current_levels {
+            self.current_levels = levels;
+            self.update_level_instances();
+        }
+    }
+
+    ///
+    /// Moves the instances to the level meshes given by the current levels.
+    ///
+    fn update_level_instances(&mut self) {
+        for (l, level) in self.levels.iter_mut().enumerate() {
+            let indices = (0..self.current_levels.len())
+                .filter(|i| self.current_levels[*i] == l)
+                .collect::<Vec<_>>();
+            level.set_instances(&Instances {
+                transformations: indices
+                    .iter()
+                    .map(|i| self.instances.transformations[*i])
+                    .collect(),
+                texture_transformations: self
+                    .instances
+                    .texture_transformations
+                    .as_ref()
+                    .map(|t| indices.iter().map(|i| t[*i]).collect()),
+                colors: self
+                    .instances
+                    .colors
+                    .as_ref()
+                    .map(|c| indices.iter().map(|i| c[*i]).collect()),
+            });
+            level.set_instance_ids(indices.iter().map(|i| *i as u32).collect());
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a InstancedLodMesh {
+    type Item = &'a dyn Geometry;
+    type IntoIter = std::iter::Once<&'a dyn Geometry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for InstancedLodMesh {
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        for transformation in self.instances.transformations.iter() {
+            let mut instance_aabb = self.aabb_local;
+            instance_aabb.transform(&(self.current_transformation * transformation));
+            aabb.expand_with_aabb(&instance_aabb);
+        }
+        aabb
+    }
+
+    fn animate(&mut self, time: f32) {
+        if let Some(animation) = &self.animation {
+            self.current_transformation = self.transformation * animation(time);
+        }
+        for level in self.levels.iter_mut() {
+            level.animate(time);
+        }
+    }
+
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        for level in self.levels.iter() {
+            if level.instance_count() > 0 {
+                level.draw(camera, program, render_states, attributes);
+            }
+        }
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        self.levels[0].vertex_shader_source(required_attributes)
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        self.levels[0].id(required_attributes)
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        // Each level is rendered with its own program, since the levels can have different vertex attributes
+        for level in self.levels.iter() {
+            if level.instance_count() > 0 {
+                level.render_with_material(material, camera, lights);
+            }
+        }
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        for level in self.levels.iter() {
+            if level.instance_count() > 0 {
+                level.render_with_effect(material, camera, lights, color_texture, depth_texture);
+            }
+        }
+    }
+}
@@ -11,13 +11,15 @@ use super::BaseMesh;
 pub struct InstancedMesh {
     context: Context,
     base_mesh: BaseMesh,
-    instance_buffers: RwLock<(HashMap<String, InstanceBuffer>, Vec3)>,
+    instance_buffers: RwLock<(HashMap<String, InstanceBuffer>, Vec3, Vec<usize>)>,
     aabb: AxisAlignedBoundingBox,
     aabb_local: AxisAlignedBoundingBox,
     transformation: Mat4,
     current_transformation: Mat4,
     animation: Option<Box<dyn Fn(f32) -> Mat4 + Send + Sync>>,
     instances: Instances,
+    instance_ids: Option<Vec<u32>>,
+    full_transformations: bool,
 }
 
 impl InstancedMesh {
@@ -31,13 +33,15 @@ impl InstancedMesh {
         let mut instanced_mesh = Self {
             context: context.clone(),
             base_mesh: BaseMesh::new(context, cpu_mesh),
-            instance_buffers: RwLock::new((Default::default(), vec3(0.0, 0.0, 0.0))),
+            instance_buffers: RwLock::new((Default::default(), vec3(0.0, 0.0, 0.0), Vec::new())),
             aabb,
             aabb_local: aabb,
             transformation: Mat4::identity(),
             current_transformation: Mat4::identity(),
             animation: None,
             instances: instances.clone(),
+            instance_ids: None,
+            full_transformations: false,
         };
         instanced_mesh.set_instances(instances);
         instanced_mesh
@@ -80,11 +84,46 @@ impl InstancedMesh {
         #[cfg(debug_assertions)]
         instances.validate().expect("invalid instances");
         self.instances = instances.clone();
+        self.instance_ids = None;
         self.update_aabb();
 
         self.update_instance_buffers(None);
     }
 
+    ///
+    /// Updates the second set of uv coordinates of the mesh, which is used by materials that require [FragmentAttributes::uv2],
+    /// for example a [PhysicalMaterial] with a [PhysicalMaterial::lightmap_texture].
+    /// The uv coordinates are shared by all of the instances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of uv coordinates does not match the number of vertices in the mesh.
+    pub fn update_uvs2(&mut self, uvs: &[Vec2]) {
+        self.base_mesh.set_uvs2(&self.context, uvs);
+    }
+
+    ///
+    /// Always use the full instance transformations instead of only the translations when none of the instances are rotated or scaled,
+    /// so that the same shader program can be used to draw this mesh independently of the instances.
+    ///
+    pub(super) fn use_full_transformations(&mut self) {
+        self.full_transformations = true;
+        self.update_instance_buffers(None);
+    }
+
+    ///
+    /// Specifies the instance index that is reported for each of the instances when picking, for example the index of the instance in the [Instances] of an [InstancedLodMesh].
+    /// The ids are reset when the instances are updated.
+    ///
+    pub(super) fn set_instance_ids(&mut self, instance_ids: Vec<u32>) {
+        self.instance_ids = Some(instance_ids);
+        self.instance_buffers
+            .write()
+            .unwrap()
+            .0
+            .remove("instance_index");
+    }
+
     fn update_aabb(&mut self) {
         let mut aabb = AxisAlignedBoundingBox::EMPTY;
         for transformation in self.instances.transformations.iter() {
@@ -125,10 +164,13 @@ impl InstancedMesh {
         let instance_buffers = &mut s.0;
         instance_buffers.clear();
 
-        if indices
-            .iter()
-            .map(|i| self.instances.transformations[*i])
-            .all(|t| Mat3::from_cols(t.x.truncate(), t.y.truncate(), t.z.truncate()).is_identity())
+        if !self.full_transformations
+            && indices
+                .iter()
+                .map(|i| self.instances.transformations[*i])
+                .all(|t| {
+                    Mat3::from_cols(t.x.truncate(), t.y.truncate(), t.z.truncate()).is_identity()
+                })
         {
             instance_buffers.insert(
                 "instance_translation".to_string(),
@@ -200,6 +242,27 @@ impl InstancedMesh {
                 InstanceBuffer::new_with_data(&self.context, &ordered_instance_colors),
             );
         }
+        s.2 = indices;
+    }
+
+    ///
+    /// Creates the buffer with the index of each instance in the current ordering, which is only needed when picking.
+    ///
+    fn update_instance_indices(&self) {
+        let mut s = self.instance_buffers.write().unwrap();
+        let instance_indices =
+            s.2.iter()
+                .map(|i| {
+                    self.instance_ids
+                        .as_ref()
+                        .map(|ids| ids[*i])
+                        .unwrap_or(*i as u32) as f32
+                })
+                .collect::<Vec<_>>();
+        s.0.insert(
+            "instance_index".to_string(),
+            InstanceBuffer::new_with_data(&self.context, &instance_indices),
+        );
     }
 }
 
@@ -226,6 +289,16 @@ impl Geometry for InstancedMesh {
         {
             self.update_instance_buffers(Some(camera));
         }
+        if attributes.instance_id
+            && !self
+                .instance_buffers
+                .read()
+                .unwrap()
+                .0
+                .contains_key("instance_index")
+        {
+            self.update_instance_indices();
+        }
 
         let instance_buffers = &self.instance_buffers.read().unwrap().0;
         if attributes.normal && instance_buffers.contains_key("instance_translation") {
@@ -247,6 +320,7 @@ impl Geometry for InstancedMesh {
             "tex_transform_row1",
             "tex_transform_row2",
             "instance_color",
+            "instance_index",
         ] {
             if program.requires_attribute(attribute_name) {
                 program.use_instance_attribute(
@@ -268,7 +342,7 @@ impl Geometry for InstancedMesh {
     fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
         let instance_buffers = &self.instance_buffers.read().unwrap().0;
         format!(
-            "{}{}{}{}{}{}{}{}{}",
+            "{}{}{}{}{}{}{}{}{}{}{}",
             if required_attributes.normal {
                 "#define USE_NORMALS\n"
             } else {
@@ -284,6 +358,11 @@ impl Geometry for InstancedMesh {
             } else {
                 ""
             },
+            if required_attributes.uv2 {
+                "#define USE_UVS2\n"
+            } else {
+                ""
+            },
             if required_attributes.color && self.base_mesh.colors.is_some() {
                 "#define USE_VERTEX_COLORS\n"
             } else {
@@ -304,6 +383,11 @@ impl Geometry for InstancedMesh {
             } else {
                 ""
             },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n#define USE_INSTANCE_INDICES\n"
+            } else {
+                ""
+            },
             include_str!("../../core/shared.frag"),
             include_str!("shaders/mesh.vert"),
         )
@@ -325,6 +409,9 @@ impl Geometry for InstancedMesh {
         if required_attributes.uv {
             id |= 0b1u16 << 2;
         }
+        if required_attributes.uv2 {
+            id |= 0b1u16 << 11;
+        }
         if required_attributes.color && self.base_mesh.colors.is_some() {
             id |= 0b1u16 << 3;
         }
@@ -337,6 +424,9 @@ impl Geometry for InstancedMesh {
         if required_attributes.uv && instance_buffers.contains_key("tex_transform_row1") {
             id |= 0b1u16 << 6;
         }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
         id
     }
 
@@ -0,0 +1,379 @@
+use crate::core::*;
+use crate::renderer::*;
+use std::collections::HashMap;
+
+use super::dash_intervals;
+
+///
+/// Defines how the positions in [CpuLines] are connected.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineTopology {
+    /// Each pair of positions is a separate line segment.
+    #[default]
+    Segments,
+    /// Each position is connected to the next position, ie. a polyline.
+    Strip,
+}
+
+///
+/// A set of 3D lines that can be rendered using [Lines3D].
+///
+#[derive(Clone, Debug, Default)]
+pub struct CpuLines {
+    /// The positions of the lines, which are connected as defined by the topology.
+    pub positions: Vec<Vec3>,
+    /// The color at each position which is interpolated along the lines. If not specified, the lines are white.
+    pub colors: Option<Vec<Srgba>>,
+    /// Defines how the positions are connected.
+    pub topology: LineTopology,
+}
+
+impl CpuLines {
+    ///
+    /// Returns an error if the lines are not valid.
+    ///
+    pub fn validate(&self) -> Result<(), RendererError> {
+        if let Some(colors) = &self.colors {
+            if colors.len() != self.positions.len() {
+                Err(RendererError::InvalidBufferLength(
+                    "colors".to_string(),
+                    self.positions.len(),
+                    colors.len(),
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns the line segments as pairs of indices into the positions.
+    ///
+    fn segments(&self) -> Vec<(usize, usize)> {
+        match self.topology {
+            LineTopology::Segments => (0..self.positions.len() / 2)
+                .map(|i| (2 * i, 2 * i + 1))
+                .collect(),
+            LineTopology::Strip => (1..self.positions.len()).map(|i| (i - 1, i)).collect(),
+        }
+    }
+}
+
+///
+/// The width of the lines in [Lines3D].
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineWidth {
+    /// The width in pixels, which means the lines have the same width on the screen independent of the distance to the camera.
+    Pixels(f32),
+    /// The width in world units, which means the lines get thinner when they are further away from the camera.
+    World(f32),
+}
+
+const CAP_SUBDIVISIONS: usize = 8;
+
+///
+/// A geometry consisting of 3D lines, see [CpuLines], which are rendered as camera facing quads with round ends and joins.
+/// The lines are depth tested against the rest of the scene and can be rendered with any material that does not require normals,
+/// for example a [ColorMaterial] which uses the per-vertex colors of the lines.
+///
+pub struct Lines3D {
+    context: Context,
+    template_buffer: VertexBuffer,
+    instance_buffers: HashMap<&'static str, InstanceBuffer>,
+    instance_count: u32,
+    lines: CpuLines,
+    width: LineWidth,
+    dash_pattern: Vec<f32>,
+    dash_offset: f32,
+    transformation: Mat4,
+    aabb: AxisAlignedBoundingBox,
+}
+
+impl Lines3D {
+    ///
+    /// Creates a new lines geometry from the given lines with the given width.
+    ///
+    pub fn new(context: &Context, lines: &CpuLines, width: LineWidth) -> Self {
+        // Each segment is a quad with half circles at each end.
+        // The template positions contain which end of the segment the vertex belongs to
+        // and the offset along and across the segment relative to half the width of the line.
+        let mut template = Vec::new();
+        template.extend([
+            vec3(0.0, 0.0, -1.0),
+            vec3(1.0, 0.0, -1.0),
+            vec3(1.0, 0.0, 1.0),
+            vec3(1.0, 0.0, 1.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, -1.0),
+        ]);
+        for end in [0.0, 1.0] {
+            let sign = if end == 0.0 { -1.0 } else { 1.0 };
+            for i in 0..CAP_SUBDIVISIONS {
+                let a0 = std::f32::consts::PI * (i as f32 / CAP_SUBDIVISIONS as f32 - 0.5);
+                let a1 = std::f32::consts::PI * ((i + 1) as f32 / CAP_SUBDIVISIONS as f32 - 0.5);
+                template.extend([
+                    vec3(end, 0.0, 0.0),
+                    vec3(end, sign * a0.cos(), a0.sin()),
+                    vec3(end, sign * a1.cos(), a1.sin()),
+                ]);
+            }
+        }
+        let mut lines_3d = Self {
+            context: context.clone(),
+            template_buffer: VertexBuffer::new_with_data(context, &template),
+            instance_buffers: HashMap::new(),
+            instance_count: 0,
+            lines: CpuLines::default(),
+            width,
+            dash_pattern: Vec::new(),
+            dash_offset: 0.0,
+            transformation: Mat4::identity(),
+            aabb: AxisAlignedBoundingBox::EMPTY,
+        };
+        lines_3d.set_lines(lines);
+        lines_3d
+    }
+
+    ///
+    /// Replaces the lines with the given lines.
+    ///
+    pub fn set_lines(&mut self, lines: &CpuLines) {
+        #[cfg(debug_assertions)]
+        lines.validate().expect("invalid lines");
+        self.lines = lines.clone();
+        self.update();
+    }
+
+    ///
+    /// Returns the width of the lines.
+    ///
+    pub fn width(&self) -> LineWidth {
+        self.width
+    }
+
+    ///
+    /// Set the width of the lines.
+    ///
+    pub fn set_width(&mut self, width: LineWidth) {
+        self.width = width;
+    }
+
+    ///
+    /// Set the alternating lengths of dashes and gaps in the local coordinate system of the lines and the distance into the pattern at which the lines start.
+    /// Strips are dashed continuously while each segment starts over in the pattern when using [LineTopology::Segments].
+    /// A pattern with an odd number of lengths is repeated twice, so `[5.0]` is the same as `[5.0, 5.0]`.
+    /// Use an empty dash pattern for solid lines.
+    ///
+    pub fn set_dash_pattern(&mut self, dash_pattern: &[f32], dash_offset: f32) {
+        self.dash_pattern = dash_pattern.to_vec();
+        self.dash_offset = dash_offset;
+        self.update();
+    }
+
+    ///
+    /// Returns the local to world transformation applied to the lines.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.transformation
+    }
+
+    ///
+    /// Set the local to world transformation applied to the lines.
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        self.transformation = transformation;
+        self.update_aabb();
+    }
+
+    fn update(&mut self) {
+        let lines = &self.lines;
+        let color = |i: usize| {
+            lines
+                .colors
+                .as_ref()
+                .map(|c| c[i].to_linear_srgb())
+                .unwrap_or(vec4(1.0, 1.0, 1.0, 1.0))
+        };
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        let mut start_colors = Vec::new();
+        let mut end_colors = Vec::new();
+        let mut distance = 0.0;
+        for (i0, i1) in lines.segments() {
+            let (p0, p1) = (lines.positions[i0], lines.positions[i1]);
+            let (c0, c1) = (color(i0), color(i1));
+            let length = (p1 - p0).magnitude();
+            if lines.topology == LineTopology::Segments {
+                distance = 0.0;
+            }
+            let dashes = dash_intervals(length, &self.dash_pattern, distance + self.dash_offset)
+                .map(|dashes| {
+                    dashes
+                        .into_iter()
+                        .map(|(start, end)| (start / length, end / length))
+                        .collect()
+                })
+                .unwrap_or_else(|| vec![(0.0, 1.0)]);
+            for (t0, t1) in dashes {
+                starts.push(p0 + (p1 - p0) * t0);
+                ends.push(p0 + (p1 - p0) * t1);
+                start_colors.push(c0 + (c1 - c0) * t0);
+                end_colors.push(c0 + (c1 - c0) * t1);
+            }
+            distance += length;
+        }
+
+        // The buffers are reused since the lines might be updated every frame
+        self.instance_count = starts.len() as u32;
+        self.fill_instance_buffer("start", &starts);
+        self.fill_instance_buffer("end", &ends);
+        if self.lines.colors.is_some() {
+            self.fill_instance_buffer("start_color", &start_colors);
+            self.fill_instance_buffer("end_color", &end_colors);
+        } else {
+            self.instance_buffers.remove("start_color");
+            self.instance_buffers.remove("end_color");
+        }
+        self.update_aabb();
+    }
+
+    fn fill_instance_buffer<T: BufferDataType>(&mut self, name: &'static str, data: &[T]) {
+        self.instance_buffers
+            .entry(name)
+            .or_insert_with(|| InstanceBuffer::new(&self.context))
+            .fill(data);
+    }
+
+    fn update_aabb(&mut self) {
+        let mut aabb = AxisAlignedBoundingBox::new_with_positions(&self.lines.positions);
+        aabb.transform(&self.transformation);
+        self.aabb = aabb;
+    }
+}
+
+impl<'a> IntoIterator for &'a Lines3D {
+    type Item = &'a dyn Geometry;
+    type IntoIter = std::iter::Once<&'a dyn Geometry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for Lines3D {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        if attributes.normal || attributes.tangents || attributes.uv {
+            panic!("lines only provide positions and colors")
+        }
+        if self.instance_count == 0 {
+            return;
+        }
+        let viewport = camera.viewport();
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform("transformation", self.transformation);
+        program.use_uniform(
+            "viewportSize",
+            vec2(viewport.width as f32, viewport.height as f32),
+        );
+        match self.width {
+            LineWidth::Pixels(width) => {
+                program.use_uniform("halfWidth", 0.5 * width);
+            }
+            LineWidth::World(width) => {
+                program.use_uniform("halfWidth", 0.5 * width);
+                // The size of one world unit in pixels at a distance of one
+                program.use_uniform(
+                    "projectionScale",
+                    0.5 * viewport.height as f32 * camera.projection().y.y,
+                );
+            }
+        }
+        program.use_vertex_attribute("position", &self.template_buffer);
+        for (name, buffer) in self.instance_buffers.iter() {
+            if program.requires_attribute(name) {
+                program.use_instance_attribute(name, buffer);
+            }
+        }
+        program.draw_arrays_instanced(
+            render_states,
+            viewport,
+            self.template_buffer.vertex_count(),
+            self.instance_count,
+        )
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "{}{}{}{}",
+            if required_attributes.color && self.lines.colors.is_some() {
+                "#define USE_VERTEX_COLORS\n"
+            } else {
+                ""
+            },
+            if let LineWidth::World(_) = self.width {
+                "#define WORLD_WIDTH\n"
+            } else {
+                ""
+            },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("shaders/lines_3d.vert")
+        )
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15 | 0b1u16 << 8;
+        if required_attributes.color && self.lines.colors.is_some() {
+            id |= 0b1u16;
+        }
+        if let LineWidth::World(_) = self.width {
+            id |= 0b1u16 << 1;
+        }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, self, material, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.aabb
+    }
+}
@@ -0,0 +1,639 @@
+use crate::core::*;
+use crate::renderer::*;
+use std::sync::Arc;
+
+///
+/// A triangle mesh with several levels of detail, where the level is selected based on the size of the mesh on the screen.
+/// The levels are either given by the user, see [LodMesh::new], or generated by simplifying a [CpuMesh], see [LodMesh::new_simplified].
+/// Level 0 is the most detailed level.
+///
+/// The level is selected when calling [LodMesh::update_level] with the camera used to view the scene, typically once each frame before rendering.
+/// The selected level is then used in all render passes, also when rendering with other cameras, for example when generating shadow maps.
+///
+/// The size on the screen is measured as the diameter of the bounding sphere of the mesh, projected to the screen, divided by the height of the viewport.
+/// Level `i` is used when the screen size is larger than or equal to the `i`'th screen size given at construction,
+/// and the mesh is not rendered if the screen size is smaller than the last screen size.
+///
+pub struct LodMesh {
+    ///
+    /// The width of the transition between two levels relative to the screen size threshold.
+    /// If [LodMesh::cross_fade] is disabled, the level is only changed when the screen size moves outside of the transition,
+    /// which avoids switching back and forth between two levels when the screen size is close to the threshold.
+    /// Negative values are treated as zero.
+    ///
+    pub hysteresis: f32,
+    ///
+    /// Whether or not to cross-fade between two levels inside the transition defined by [LodMesh::hysteresis] using a dither pattern.
+    /// The cross-fade is only applied when rendering with a [material], not an [effect].
+    ///
+    pub cross_fade: bool,
+    context: Context,
+    levels: Vec<Mesh>,
+    screen_sizes: Vec<f32>,
+    current_level: usize,
+    fade: Option<f32>,
+}
+
+impl LodMesh {
+    ///
+    /// Creates a new mesh with the given levels of detail, where the first [CpuMesh] is the most detailed,
+    /// and the minimum screen size at which each of the levels is used.
+    /// All of the [CpuMesh]es must contain the same vertex attributes (normals, uv coordinates etc.).
+    ///
+    /// # Panic
+    /// Will panic if the number of levels and screen sizes are not the same or if no levels are given.
+    ///
+    pub fn new(context: &Context, cpu_meshes: &[CpuMesh], screen_sizes: &[f32]) -> Self {
+        assert!(
+            !cpu_meshes.is_empty() && cpu_meshes.len() == screen_sizes.len(),
+            "a screen size must be specified for each of the levels of detail"
+        );
+        Self {
+            hysteresis: 0.1,
+            cross_fade: false,
+            context: context.clone(),
+            levels: cpu_meshes.iter().map(|m| Mesh::new(context, m)).collect(),
+            screen_sizes: screen_sizes.to_vec(),
+            current_level: 0,
+            fade: None,
+        }
+    }
+
+    ///
+    /// Creates a new mesh with a level of detail for each of the given screen sizes, where the first level is the given [CpuMesh]
+    /// and each of the following levels is generated by simplifying the previous level to half the number of triangles using quadric error metrics.
+    ///
+    /// The simplification merges vertices with the same position, so seams in the uv coordinates, vertex colors or normals are not preserved in the generated levels.
+    /// Normals and tangents are recomputed for the generated levels, if the given [CpuMesh] contains normals and tangents.
+    ///
+    pub fn new_simplified(context: &Context, cpu_mesh: &CpuMesh, screen_sizes: &[f32]) -> Self {
+        Self::new(
+            context,
+            &simplified_levels(cpu_mesh, screen_sizes.len()),
+            screen_sizes,
+        )
+    }
+
+    ///
+    /// Returns the number of levels of detail.
+    ///
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    ///
+    /// Returns the level selected the last time [LodMesh::update_level] was called or `None` if the mesh was too small to be rendered.
+    /// When cross-fading, this is the most detailed of the two levels.
+    ///
+    pub fn current_level(&self) -> Option<usize> {
+        Some(self.current_level).filter(|l| *l < self.levels.len())
+    }
+
+    ///
+    /// Selects the level to render based on the size of the mesh on the screen when viewed with the given camera.
+    /// Call this with the camera used to view the scene each time the camera or the mesh has moved, typically once each frame.
+    ///
+    pub fn update_level(&mut self, camera: &Camera) {
+        let size = screen_size(camera, &self.aabb());
+        let hysteresis = self.hysteresis.max(0.0);
+        let transition = if self.cross_fade && hysteresis > 0.0 {
+            self.screen_sizes.iter().enumerate().find_map(|(i, s)| {
+                let start = s * (1.0 - hysteresis);
+                let end = s * (1.0 + hysteresis);
+                (size >= start && size < end).then(|| (i, (size - start) / (end - start)))
+            })
+        } else {
+            None
+        };
+        if let Some((level, fade)) = transition {
+            self.current_level = level;
+            self.fade = Some(fade);
+        } else {
+            self.current_level = select_level(
+                size,
+                &self.screen_sizes,
+                self.hysteresis,
+                self.current_level,
+            );
+            self.fade = None;
+        }
+    }
+
+    ///
+    /// Returns the local to world transformation applied to this mesh.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.levels[0].transformation()
+    }
+
+    ///
+    /// Set the local to world transformation applied to this mesh.
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        for level in self.levels.iter_mut() {
+            level.set_transformation(transformation);
+        }
+    }
+
+    ///
+    /// Specifies a function which takes a time parameter as input and returns a transformation that should be applied to this mesh at the given time.
+    /// See [Mesh::set_animation] for more information.
+    ///
+    pub fn set_animation(&mut self, animation: impl Fn(f32) -> Mat4 + Send + Sync + 'static) {
+        let animation = Arc::new(animation);
+        for level in self.levels.iter_mut() {
+            let animation = animation.clone();
+            level.set_animation(move |time| animation(time));
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a LodMesh {
+    type Item = &'a dyn Geometry;
+    type IntoIter = std::iter::Once<&'a dyn Geometry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for LodMesh {
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.levels[0].aabb()
+    }
+
+    fn animate(&mut self, time: f32) {
+        for level in self.levels.iter_mut() {
+            level.animate(time);
+        }
+    }
+
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        if let Some(level) = self.levels.get(self.current_level) {
+            level.draw(camera, program, render_states, attributes);
+        }
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        self.levels[0].vertex_shader_source(required_attributes)
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        self.levels[0].id(required_attributes)
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        let Some(fade) = self.fade.filter(|_| self.cross_fade) else {
+            render_with_material(&self.context, camera, self, material, lights);
+            return;
+        };
+
+        // Render the most detailed level where the dither pattern is below the fade value and the other level everywhere else
+        for (level, invert) in [(self.current_level, false), (self.current_level + 1, true)] {
+            if let Some(mesh) = self.levels.get(level) {
+                CrossFadeGeometry {
+                    context: &self.context,
+                    mesh,
+                    fade,
+                    invert,
+                }
+                .render_with_material(material, camera, lights);
+            }
+        }
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+}
+
+///
+/// One of the two levels rendered while cross-fading, which wraps the material in a [CrossFadeMaterial] when rendering.
+/// The id of the level is marked, so that the programs with the dither test are cached separately from the programs of the level without it.
+///
+struct CrossFadeGeometry<'a> {
+    context: &'a Context,
+    mesh: &'a Mesh,
+    fade: f32,
+    invert: bool,
+}
+
+impl Geometry for CrossFadeGeometry<'_> {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        program.use_uniform(
+            "lodFade",
+            vec2(self.fade, if self.invert { 1.0 } else { 0.0 }),
+        );
+        self.mesh.draw(camera, program, render_states, attributes)
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        self.mesh.vertex_shader_source(required_attributes)
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        self.mesh.id(required_attributes) | 0b1u16 << 12
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(
+            self.context,
+            camera,
+            self,
+            CrossFadeMaterial { material },
+            lights,
+        )
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        self.mesh
+            .render_with_effect(material, camera, lights, color_texture, depth_texture)
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.mesh.aabb()
+    }
+}
+
+///
+/// Wraps the material of a [LodMesh] while cross-fading, see [CrossFadeGeometry].
+/// The main function of the material is renamed by the preprocessor and called from a main function which first discards the fragments covered by the other level.
+///
+struct CrossFadeMaterial<'a> {
+    material: &'a dyn Material,
+}
+
+impl Material for CrossFadeMaterial<'_> {
+    fn fragment_shader_source(&self, lights: &[&dyn Light]) -> String {
+        format!(
+            "#define main lod_main\n{}\n#undef main\n{}",
+            self.material.fragment_shader_source(lights),
+            include_str!("shaders/lod_cross_fade.frag")
+        )
+    }
+
+    fn id(&self) -> u16 {
+        self.material.id()
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        self.material.fragment_attributes()
+    }
+
+    fn use_uniforms(&self, program: &Program, camera: &Camera, lights: &[&dyn Light]) {
+        self.material.use_uniforms(program, camera, lights)
+    }
+
+    fn render_states(&self) -> RenderStates {
+        self.material.render_states()
+    }
+
+    fn material_type(&self) -> MaterialType {
+        self.material.material_type()
+    }
+}
+
+///
+/// Returns the diameter of the bounding sphere of the bounding box projected to the screen divided by the height of the viewport.
+///
+pub(super) fn screen_size(camera: &Camera, aabb: &AxisAlignedBoundingBox) -> f32 {
+    if aabb.is_empty() {
+        return 0.0;
+    }
+    if aabb.is_infinite() {
+        return f32::INFINITY;
+    }
+    let radius = 0.5 * aabb.size().magnitude();
+    let w = (camera.projection() * camera.view() * aabb.center().extend(1.0)).w;
+    if w <= radius * f32::EPSILON {
+        f32::INFINITY
+    } else {
+        radius * camera.projection()[1][1] / w
+    }
+}
+
+///
+/// Returns the level to use for the given screen size, where the level only changes from the current level if the screen size is outside of the hysteresis.
+/// Returns the number of levels if nothing should be rendered.
+///
+pub(super) fn select_level(
+    screen_size: f32,
+    screen_sizes: &[f32],
+    hysteresis: f32,
+    current_level: usize,
+) -> usize {
+    let hysteresis = hysteresis.max(0.0);
+    let level = |scale: f32| {
+        screen_sizes
+            .iter()
+            .position(|s| screen_size >= s * scale)
+            .unwrap_or(screen_sizes.len())
+    };
+    current_level.clamp(level(1.0 - hysteresis), level(1.0 + hysteresis))
+}
+
+///
+/// Returns the given mesh followed by the given number of levels minus one, each simplified to half of the triangles of the previous.
+///
+pub(super) fn simplified_levels(cpu_mesh: &CpuMesh, level_count: usize) -> Vec<CpuMesh> {
+    let mut levels = vec![cpu_mesh.clone()];
+    while levels.len() < level_count {
+        let previous = levels.last().unwrap();
+        levels.push(simplify(previous, previous.triangle_count() / 2));
+    }
+    levels
+}
+
+///
+/// A symmetric 4x4 matrix representing the sum of squared distances to a set of planes.
+///
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vector3<f64>, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|v| v * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+
+    fn error(&self, p: Vector3<f64>) -> f64 {
+        let q = &self.0;
+        q[0] * p.x * p.x
+            + 2.0 * q[1] * p.x * p.y
+            + 2.0 * q[2] * p.x * p.z
+            + 2.0 * q[3] * p.x
+            + q[4] * p.y * p.y
+            + 2.0 * q[5] * p.y * p.z
+            + 2.0 * q[6] * p.y
+            + q[7] * p.z * p.z
+            + 2.0 * q[8] * p.z
+            + q[9]
+    }
+}
+
+///
+/// Simplifies the given mesh to at most the target number of triangles, if possible, by collapsing edges in the order given by the quadric error metric.
+/// Each edge is collapsed into one of its end points, so the attributes of the remaining vertices are taken from the given mesh.
+///
+fn simplify(cpu_mesh: &CpuMesh, target_triangle_count: usize) -> CpuMesh {
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, HashMap};
+
+    let positions = cpu_mesh.positions.to_f32();
+    let indices = cpu_mesh
+        .indices
+        .to_u32()
+        .unwrap_or_else(|| (0..positions.len() as u32).collect());
+
+    // Merge vertices with the same position so the topology is connected across attribute seams
+    let mut welded = HashMap::new();
+    let mut representatives = Vec::new();
+    let remap = positions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            *welded
+                .entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+                .or_insert_with(|| {
+                    representatives.push(i);
+                    representatives.len() - 1
+                })
+        })
+        .collect::<Vec<_>>();
+    let points = representatives
+        .iter()
+        .map(|i| positions[*i].cast::<f64>().unwrap())
+        .collect::<Vec<_>>();
+    let mut faces = indices
+        .chunks_exact(3)
+        .map(|t| [0, 1, 2].map(|i| remap[t[i] as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect::<Vec<_>>();
+    let mut alive = vec![true; faces.len()];
+    let mut triangle_count = faces.len();
+
+    // Compute the quadric for each vertex from the planes of the adjacent triangles weighted by area
+    let mut quadrics = vec![Quadric::default(); points.len()];
+    let mut vertex_faces = vec![Vec::new(); points.len()];
+    let mut edges = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        let [a, b, c] = face.map(|v| points[v]);
+        let normal = (b - a).cross(c - a);
+        let area = normal.magnitude();
+        if area > 0.0 {
+            let normal = normal / area;
+            let quadric = Quadric::from_plane(normal, -normal.dot(a), 0.5 * area);
+            for v in face {
+                quadrics[*v].add(&quadric);
+            }
+        }
+        for (i, v) in face.iter().enumerate() {
+            vertex_faces[*v].push(f);
+            let w = face[(i + 1) % 3];
+            edges.entry((*v.min(&w), *v.max(&w))).or_insert((0, f)).0 += 1;
+        }
+    }
+
+    // Constrain the boundary edges to the plane perpendicular to the adjacent triangle to avoid shrinking holes and borders
+    const BOUNDARY_WEIGHT: f64 = 10.0;
+    for ((v, w), (count, f)) in edges.iter() {
+        if *count == 1 {
+            let [a, b, c] = faces[*f].map(|v| points[v]);
+            let edge = points[*w] - points[*v];
+            let normal = edge.cross((b - a).cross(c - a));
+            if normal.magnitude2() > 0.0 {
+                let normal = normal.normalize();
+                let quadric = Quadric::from_plane(
+                    normal,
+                    -normal.dot(points[*v]),
+                    BOUNDARY_WEIGHT * edge.magnitude2(),
+                );
+                quadrics[*v].add(&quadric);
+                quadrics[*w].add(&quadric);
+            }
+        }
+    }
+
+    // The collapses are ordered by error, which is non-negative so the bit representation has the same ordering
+    let mut versions = vec![0u32; points.len()];
+    let mut heap = BinaryHeap::new();
+    let push =
+        |heap: &mut BinaryHeap<_>, quadrics: &[Quadric], versions: &[u32], v: usize, w: usize| {
+            let mut quadric = quadrics[v];
+            quadric.add(&quadrics[w]);
+            let (error_v, error_w) = (quadric.error(points[v]), quadric.error(points[w]));
+            let (keep, remove, error) = if error_v <= error_w {
+                (v, w, error_v)
+            } else {
+                (w, v, error_w)
+            };
+            heap.push(Reverse((
+                (error.max(0.0) as f32).to_bits(),
+                keep,
+                remove,
+                versions[keep],
+                versions[remove],
+            )));
+        };
+    for (v, w) in edges.keys() {
+        push(&mut heap, &quadrics, &versions, *v, *w);
+    }
+
+    while triangle_count > target_triangle_count {
+        let Some(Reverse((_, keep, remove, version_keep, version_remove))) = heap.pop() else {
+            break;
+        };
+        if versions[keep] != version_keep || versions[remove] != version_remove {
+            continue;
+        }
+
+        // Avoid collapses that flip the orientation of the remaining triangles
+        let flips = vertex_faces[remove].iter().any(|f| {
+            let face = faces[*f];
+            if !alive[*f] || face.contains(&keep) {
+                return false;
+            }
+            let [a, b, c] = face.map(|v| points[v]);
+            let [d, e, g] = face.map(|v| points[if v == remove { keep } else { v }]);
+            let before = (b - a).cross(c - a);
+            let after = (e - d).cross(g - d);
+            after.dot(before) <= 0.2 * before.magnitude() * after.magnitude()
+        });
+        if flips {
+            continue;
+        }
+
+        for f in std::mem::take(&mut vertex_faces[remove]) {
+            if !alive[f] {
+                continue;
+            }
+            if faces[f].contains(&keep) {
+                alive[f] = false;
+                triangle_count -= 1;
+            } else {
+                for v in faces[f].iter_mut() {
+                    if *v == remove {
+                        *v = keep;
+                    }
+                }
+                vertex_faces[keep].push(f);
+            }
+        }
+        vertex_faces[keep].retain(|f| alive[*f]);
+        let quadric = quadrics[remove];
+        quadrics[keep].add(&quadric);
+        versions[keep] += 1;
+        versions[remove] += 1;
+
+        let mut neighbours = vertex_faces[keep]
+            .iter()
+            .flat_map(|f| faces[*f])
+            .filter(|v| *v != keep)
+            .collect::<Vec<_>>();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for v in neighbours {
+            push(&mut heap, &quadrics, &versions, keep, v);
+        }
+    }
+
+    // Build the simplified mesh from the remaining triangles and the attributes of the remaining vertices
+    let mut vertex_map = HashMap::new();
+    let mut vertices = Vec::new();
+    let indices = faces
+        .iter()
+        .zip(alive)
+        .filter(|(_, alive)| *alive)
+        .flat_map(|(face, _)| *face)
+        .map(|v| {
+            *vertex_map.entry(v).or_insert_with(|| {
+                vertices.push(representatives[v]);
+                vertices.len() as u32 - 1
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut simplified = CpuMesh {
+        positions: Positions::F32(vertices.iter().map(|i| positions[*i]).collect()),
+        indices: Indices::U32(indices),
+        uvs: cpu_mesh
+            .uvs
+            .as_ref()
+            .map(|uvs| vertices.iter().map(|i| uvs[*i]).collect()),
+        colors: cpu_mesh
+            .colors
+            .as_ref()
+            .map(|colors| vertices.iter().map(|i| colors[*i]).collect()),
+        ..Default::default()
+    };
+    if cpu_mesh.normals.is_some() {
+        simplified.compute_normals();
+    }
+    if cpu_mesh.tangents.is_some() && simplified.uvs.is_some() {
+        simplified.compute_tangents();
+    }
+    simplified
+}
@@ -114,6 +114,19 @@ impl Mesh {
             self.base_mesh.normals = Some(VertexBuffer::new_with_data(&self.context, normals));
         }
     }
+
+    ///
+    /// Updates the second set of uv coordinates of the mesh, which is used by materials that require [FragmentAttributes::uv2],
+    /// for example a [PhysicalMaterial] with a [PhysicalMaterial::lightmap_texture].
+    /// A [CpuMesh] only contains one set of uv coordinates, so the second set must be specified using this method,
+    /// also when loading a glTF file, since the `TEXCOORD_1` attribute is not loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of uv coordinates does not match the number of vertices in the mesh.
+    pub fn update_uvs2(&mut self, uvs: &[Vec2]) {
+        self.base_mesh.set_uvs2(&self.context, uvs);
+    }
 }
 
 impl<'a> IntoIterator for &'a Mesh {
@@ -163,7 +176,7 @@ impl Geometry for Mesh {
 
     fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
         format!(
-            "{}{}{}{}{}{}",
+            "{}{}{}{}{}{}{}{}",
             if required_attributes.normal {
                 "#define USE_NORMALS\n"
             } else {
@@ -179,11 +192,21 @@ impl Geometry for Mesh {
             } else {
                 ""
             },
+            if required_attributes.uv2 {
+                "#define USE_UVS2\n"
+            } else {
+                ""
+            },
             if required_attributes.color && self.base_mesh.colors.is_some() {
                 "#define USE_VERTEX_COLORS\n"
             } else {
                 ""
             },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
             include_str!("../../core/shared.frag"),
             include_str!("shaders/mesh.vert"),
         )
@@ -200,9 +223,15 @@ impl Geometry for Mesh {
         if required_attributes.uv {
             id |= 0b1u16 << 2;
         }
+        if required_attributes.uv2 {
+            id |= 0b1u16 << 11;
+        }
         if required_attributes.color && self.base_mesh.colors.is_some() {
             id |= 0b1u16 << 3;
         }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
         id
     }
 
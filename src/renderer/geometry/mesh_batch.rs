@@ -0,0 +1,326 @@
+use crate::core::*;
+use crate::renderer::*;
+
+use super::BaseMesh;
+
+// The number of transformations stored in each row of the transformation texture.
+const TRANSFORMATIONS_PER_ROW: usize = 256;
+
+///
+/// A set of static meshes merged into shared vertex and index buffers, so that they can be rendered with the same [material] using a few draw calls.
+/// This is much faster than rendering many small [Mesh]es individually, since each of those requires binding the program and uploading uniforms.
+///
+/// Each of the meshes keeps its own transformation, see [MeshBatch::set_transformation], and meshes outside the camera frustum are not rendered.
+/// The visible meshes which are next to each other in the shared buffers are drawn with a single draw call.
+/// When picking, see [pick_geometry], the index of the mesh in the batch is returned as the instance index.
+///
+pub struct MeshBatch {
+    context: Context,
+    base_mesh: BaseMesh,
+    batch_indices: VertexBuffer,
+    transformations_texture: Texture2D,
+    transformations: Vec<Mat4>,
+    ranges: Vec<(u32, u32)>,
+    aabbs_local: Vec<AxisAlignedBoundingBox>,
+    aabbs: Vec<AxisAlignedBoundingBox>,
+    aabb: AxisAlignedBoundingBox,
+}
+
+impl MeshBatch {
+    ///
+    /// Creates a new batch from the given [CpuMesh]es, each with the transformation at the same position in the given list of transformations.
+    /// Only the vertex attributes (normals, uv coordinates etc.) which are present in all of the meshes are used.
+    ///
+    /// # Panic
+    /// Will panic if the number of meshes and transformations are not the same.
+    ///
+    pub fn new(context: &Context, cpu_meshes: &[&CpuMesh], transformations: &[Mat4]) -> Self {
+        assert_eq!(
+            cpu_meshes.len(),
+            transformations.len(),
+            "a transformation must be specified for each of the meshes"
+        );
+        let all =
+            |has: fn(&CpuMesh) -> bool| !cpu_meshes.is_empty() && cpu_meshes.iter().all(|m| has(m));
+        let mut merged = CpuMesh {
+            normals: all(|m| m.normals.is_some()).then(Vec::new),
+            tangents: all(|m| m.tangents.is_some()).then(Vec::new),
+            uvs: all(|m| m.uvs.is_some()).then(Vec::new),
+            colors: all(|m| m.colors.is_some()).then(Vec::new),
+            ..Default::default()
+        };
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        let mut batch_indices = Vec::new();
+        let mut ranges = Vec::new();
+        let mut aabbs_local = Vec::new();
+        for (i, cpu_mesh) in cpu_meshes.iter().enumerate() {
+            let offset = positions.len() as u32;
+            let vertex_count = cpu_mesh.vertex_count();
+            positions.extend(cpu_mesh.positions.to_f32());
+            let first = indices.len() as u32;
+            match cpu_mesh.indices.to_u32() {
+                Some(mesh_indices) => indices.extend(mesh_indices.into_iter().map(|i| i + offset)),
+                None => indices.extend(offset..offset + vertex_count as u32),
+            }
+            ranges.push((first, indices.len() as u32 - first));
+            batch_indices.extend(std::iter::repeat_n(i as f32, vertex_count));
+            aabbs_local.push(cpu_mesh.compute_aabb());
+            if let Some(normals) = merged.normals.as_mut() {
+                normals.extend(cpu_mesh.normals.as_ref().unwrap());
+            }
+            if let Some(tangents) = merged.tangents.as_mut() {
+                tangents.extend(cpu_mesh.tangents.as_ref().unwrap());
+            }
+            if let Some(uvs) = merged.uvs.as_mut() {
+                uvs.extend(cpu_mesh.uvs.as_ref().unwrap());
+            }
+            if let Some(colors) = merged.colors.as_mut() {
+                colors.extend(cpu_mesh.colors.as_ref().unwrap());
+            }
+        }
+        merged.positions = Positions::F32(positions);
+        merged.indices = Indices::U32(indices);
+
+        let width = 4 * cpu_meshes.len().clamp(1, TRANSFORMATIONS_PER_ROW);
+        let height = cpu_meshes.len().max(1).div_ceil(TRANSFORMATIONS_PER_ROW);
+        let mut batch = Self {
+            context: context.clone(),
+            base_mesh: BaseMesh::new(context, &merged),
+            batch_indices: VertexBuffer::new_with_data(context, &batch_indices),
+            transformations_texture: Texture2D::new_empty::<[f32; 4]>(
+                context,
+                width as u32,
+                height as u32,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+            transformations: transformations.to_vec(),
+            ranges,
+            aabbs: aabbs_local.clone(),
+            aabbs_local,
+            aabb: AxisAlignedBoundingBox::EMPTY,
+        };
+        batch.update_transformations();
+        batch
+    }
+
+    ///
+    /// Returns the number of meshes in this batch.
+    ///
+    pub fn mesh_count(&self) -> usize {
+        self.transformations.len()
+    }
+
+    ///
+    /// Returns the local to world transformation of the mesh with the given index.
+    ///
+    pub fn transformation(&self, index: usize) -> Mat4 {
+        self.transformations[index]
+    }
+
+    ///
+    /// Set the local to world transformation of the mesh with the given index.
+    /// Use [MeshBatch::set_transformations] to update many transformations at once.
+    ///
+    pub fn set_transformation(&mut self, index: usize, transformation: Mat4) {
+        self.transformations[index] = transformation;
+        self.update_transformations();
+    }
+
+    ///
+    /// Set the local to world transformations of all of the meshes.
+    ///
+    /// # Panic
+    /// Will panic if the number of transformations is not the same as the number of meshes.
+    ///
+    pub fn set_transformations(&mut self, transformations: &[Mat4]) {
+        assert_eq!(
+            transformations.len(),
+            self.transformations.len(),
+            "a transformation must be specified for each of the meshes"
+        );
+        self.transformations = transformations.to_vec();
+        self.update_transformations();
+    }
+
+    ///
+    /// Updates the second set of uv coordinates, which is used by materials that require [FragmentAttributes::uv2],
+    /// for example a [PhysicalMaterial] with a [PhysicalMaterial::lightmap_texture].
+    /// The uv coordinates of all of the meshes are given in one list, in the same order as the meshes were given at construction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of uv coordinates does not match the total number of vertices in the meshes.
+    pub fn update_uvs2(&mut self, uvs: &[Vec2]) {
+        self.base_mesh.set_uvs2(&self.context, uvs);
+    }
+
+    ///
+    /// Returns the axis aligned bounding box of the mesh with the given index in world space.
+    ///
+    pub fn mesh_aabb(&self, index: usize) -> AxisAlignedBoundingBox {
+        self.aabbs[index]
+    }
+
+    fn update_transformations(&mut self) {
+        let width = self.transformations_texture.width() as usize;
+        let height = self.transformations_texture.height() as usize;
+        let mut data = vec![[0.0f32; 4]; width * height];
+        self.aabb = AxisAlignedBoundingBox::EMPTY;
+        for (i, transformation) in self.transformations.iter().enumerate() {
+            for c in 0..4 {
+                data[4 * i + c] = transformation[c].into();
+            }
+            let mut aabb = self.aabbs_local[i];
+            aabb.transform(transformation);
+            self.aabbs[i] = aabb;
+            self.aabb.expand_with_aabb(&aabb);
+        }
+        self.transformations_texture.fill(&data);
+    }
+}
+
+impl<'a> IntoIterator for &'a MeshBatch {
+    type Item = &'a dyn Geometry;
+    type IntoIter = std::iter::Once<&'a dyn Geometry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for MeshBatch {
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.aabb
+    }
+
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        // Merge the ranges of neighbouring visible meshes to minimize the number of draw calls
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for ((first, count), aabb) in self.ranges.iter().zip(self.aabbs.iter()) {
+            if !camera.in_frustum(aabb) {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some((last_first, last_count)) if *last_first + *last_count == *first => {
+                    *last_count += count;
+                }
+                _ => ranges.push((*first, *count)),
+            }
+        }
+        if ranges.is_empty() {
+            return;
+        }
+
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform("modelMatrix", Mat4::identity());
+        program.use_texture("batchTransformations", &self.transformations_texture);
+        self.base_mesh.use_attributes(program, attributes);
+        program.use_vertex_attribute("batch_index", &self.batch_indices);
+        program.draw_subsets_of_elements(
+            render_states,
+            camera.viewport(),
+            self.base_mesh.indices.as_ref().unwrap(),
+            &ranges,
+        );
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "#define USE_BATCH_TRANSFORMATIONS\n{}{}{}{}{}{}{}{}",
+            if required_attributes.normal {
+                "#define USE_NORMALS\n"
+            } else {
+                ""
+            },
+            if required_attributes.tangents {
+                "#define USE_TANGENTS\n"
+            } else {
+                ""
+            },
+            if required_attributes.uv {
+                "#define USE_UVS\n"
+            } else {
+                ""
+            },
+            if required_attributes.uv2 {
+                "#define USE_UVS2\n"
+            } else {
+                ""
+            },
+            if required_attributes.color && self.base_mesh.colors.is_some() {
+                "#define USE_VERTEX_COLORS\n"
+            } else {
+                ""
+            },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("../../core/shared.frag"),
+            include_str!("shaders/mesh.vert"),
+        )
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15 | 0b1u16 << 10;
+        if required_attributes.normal {
+            id |= 0b1u16;
+        }
+        if required_attributes.tangents {
+            id |= 0b1u16 << 1;
+        }
+        if required_attributes.uv {
+            id |= 0b1u16 << 2;
+        }
+        if required_attributes.uv2 {
+            id |= 0b1u16 << 11;
+        }
+        if required_attributes.color && self.base_mesh.colors.is_some() {
+            id |= 0b1u16 << 3;
+        }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, self, material, lights);
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+}
@@ -175,6 +175,18 @@ impl ParticleSystem {
             );
         }
     }
+
+    ///
+    /// Updates the second set of uv coordinates of the mesh, which is used by materials that require [FragmentAttributes::uv2],
+    /// for example a [PhysicalMaterial] with a [PhysicalMaterial::lightmap_texture].
+    /// The uv coordinates are shared by all of the particles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of uv coordinates does not match the number of vertices in the mesh.
+    pub fn update_uvs2(&mut self, uvs: &[Vec2]) {
+        self.base_mesh.set_uvs2(&self.context, uvs);
+    }
 }
 
 impl<'a> IntoIterator for &'a ParticleSystem {
@@ -198,6 +210,9 @@ impl Geometry for ParticleSystem {
         if required_attributes.uv {
             id |= 0b1u16 << 2;
         }
+        if required_attributes.uv2 {
+            id |= 0b1u16 << 11;
+        }
         if required_attributes.color && self.base_mesh.colors.is_some() {
             id |= 0b1u16 << 3;
         }
@@ -207,12 +222,15 @@ impl Geometry for ParticleSystem {
         if required_attributes.uv && self.instance_buffers.contains_key("tex_transform_row1") {
             id |= 0b1u16 << 5;
         }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
         id
     }
 
     fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
         format!(
-            "#define PARTICLES\n{}{}{}{}{}{}{}{}",
+            "#define PARTICLES\n{}{}{}{}{}{}{}{}{}{}",
             if required_attributes.normal {
                 "#define USE_NORMALS\n"
             } else {
@@ -228,6 +246,11 @@ impl Geometry for ParticleSystem {
             } else {
                 ""
             },
+            if required_attributes.uv2 {
+                "#define USE_UVS2\n"
+            } else {
+                ""
+            },
             if required_attributes.color && self.base_mesh.colors.is_some() {
                 "#define USE_VERTEX_COLORS\n"
             } else {
@@ -243,6 +266,11 @@ impl Geometry for ParticleSystem {
             } else {
                 ""
             },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
             include_str!("../../core/shared.frag"),
             include_str!("shaders/mesh.vert"),
         )
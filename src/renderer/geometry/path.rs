@@ -0,0 +1,561 @@
+use crate::renderer::*;
+
+use super::dash_intervals;
+
+///
+/// The shape used at the corners of a stroked [Path].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    /// The outer edges of the two line segments are extended until they meet.
+    /// If the resulting corner is longer than the miter limit (see [StrokeStyle::miter_limit]), a bevel join is used instead.
+    Miter,
+    /// The corner is rounded.
+    Round,
+    /// The corner is cut off.
+    Bevel,
+}
+
+///
+/// The shape used at the ends of an open stroked [Path] and at the ends of each dash.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// The stroke ends exactly at the end point.
+    Butt,
+    /// The stroke ends with a half circle centered at the end point.
+    Round,
+    /// The stroke is extended with half the stroke width beyond the end point.
+    Square,
+}
+
+///
+/// Defines how a [Path] is stroked, see [Path::stroke].
+///
+#[derive(Clone, Debug)]
+pub struct StrokeStyle {
+    /// The width of the stroke.
+    pub width: f32,
+    /// The shape used at the corners.
+    pub join: LineJoin,
+    /// The maximum length of a miter join relative to half the stroke width.
+    pub miter_limit: f32,
+    /// The shape used at the ends.
+    pub cap: LineCap,
+    /// Alternating lengths of dashes and gaps. If empty, the stroke is solid.
+    /// A pattern with an odd number of lengths is repeated twice, so `[5.0]` is the same as `[5.0, 5.0]`.
+    pub dash_pattern: Vec<f32>,
+    /// The distance into the dash pattern at which the stroke starts.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+            cap: LineCap::Butt,
+            dash_pattern: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PathCommand {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadTo(Vec2, Vec2),
+    CubicTo(Vec2, Vec2, Vec2),
+    Arc(Vec2, f32, Radians, Radians),
+    Close,
+}
+
+///
+/// A 2D vector path consisting of one or more sub paths made of line segments, quadratic and cubic Bézier curves and circular arcs.
+/// The path can be tessellated into a filled mesh using [Path::fill] or a stroked mesh using [Path::stroke].
+/// The resulting [CpuMesh] is placed in the xy-plane and can be used to construct a [Mesh] which, for example, can be rendered
+/// with a [ColorMaterial] using a camera created by [Camera::new_2d].
+/// A [CpuMesh] is returned instead of a [Geometry], like for [CpuMesh::circle] and the other shapes, so that the tessellation can be used with any geometry,
+/// for example [Mesh], [InstancedMesh] or [MeshBatch], and combined with other meshes before it is sent to the GPU.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+impl Path {
+    ///
+    /// Creates a new empty path.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Starts a new sub path at the given point.
+    ///
+    pub fn move_to(mut self, point: impl Into<PhysicalPoint>) -> Self {
+        self.commands.push(PathCommand::MoveTo(point.into().into()));
+        self
+    }
+
+    ///
+    /// Adds a straight line from the current point to the given point.
+    ///
+    pub fn line_to(mut self, point: impl Into<PhysicalPoint>) -> Self {
+        self.commands.push(PathCommand::LineTo(point.into().into()));
+        self
+    }
+
+    ///
+    /// Adds a quadratic Bézier curve from the current point to the given point using the given control point.
+    ///
+    pub fn quad_to(
+        mut self,
+        control: impl Into<PhysicalPoint>,
+        point: impl Into<PhysicalPoint>,
+    ) -> Self {
+        self.commands.push(PathCommand::QuadTo(
+            control.into().into(),
+            point.into().into(),
+        ));
+        self
+    }
+
+    ///
+    /// Adds a cubic Bézier curve from the current point to the given point using the two given control points.
+    ///
+    pub fn cubic_to(
+        mut self,
+        control0: impl Into<PhysicalPoint>,
+        control1: impl Into<PhysicalPoint>,
+        point: impl Into<PhysicalPoint>,
+    ) -> Self {
+        self.commands.push(PathCommand::CubicTo(
+            control0.into().into(),
+            control1.into().into(),
+            point.into().into(),
+        ));
+        self
+    }
+
+    ///
+    /// Adds a circular arc with the given center and radius going from the start angle to the end angle,
+    /// where an angle of zero is along the x-axis. The arc goes counterclockwise if the end angle is larger than the start angle and clockwise otherwise.
+    /// A straight line is added from the current point to the start of the arc. If there is no current point, a new sub path is started at the start of the arc.
+    ///
+    pub fn arc(
+        mut self,
+        center: impl Into<PhysicalPoint>,
+        radius: f32,
+        start_angle: impl Into<Radians>,
+        end_angle: impl Into<Radians>,
+    ) -> Self {
+        self.commands.push(PathCommand::Arc(
+            center.into().into(),
+            radius,
+            start_angle.into(),
+            end_angle.into(),
+        ));
+        self
+    }
+
+    ///
+    /// Closes the current sub path by adding a straight line back to the start point of the sub path.
+    ///
+    pub fn close(mut self) -> Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    ///
+    /// Returns a mesh covering the inside of the path where curves are approximated by line segments that deviates at most the given tolerance from the curve.
+    /// Each sub path is implicitly closed and filled separately, which means that sub paths cannot be used to cut holes in other sub paths.
+    /// Self-intersecting sub paths are not supported.
+    ///
+    pub fn fill(&self, tolerance: f32) -> CpuMesh {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for (points, _) in self.flatten(tolerance) {
+            triangulate(&points, &mut positions, &mut indices);
+        }
+        mesh_2d(positions, indices)
+    }
+
+    ///
+    /// Returns a mesh covering the outline of the path using the given [StrokeStyle],
+    /// where curves are approximated by line segments that deviates at most the given tolerance from the curve.
+    /// If the [StrokeStyle::dash_pattern] has an odd number of lengths, it is repeated twice, like the SVG `stroke-dasharray`.
+    ///
+    /// **Note:** The stroke consists of a quad for each line segment and separate triangles for the joins and caps, which overlap.
+    /// A translucent material therefore blends the overlapping parts twice, which is visible as darker corners.
+    /// To avoid this, render the stroke with [DepthTest::Less] and [WriteMask::COLOR_AND_DEPTH] in the render states of the material,
+    /// since the stroke is flat, so only the first triangle covering a pixel passes the depth test.
+    /// Note that this also hides the stroke where other geometry at the same depth is already rendered.
+    ///
+    pub fn stroke(&self, style: &StrokeStyle, tolerance: f32) -> CpuMesh {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for (points, closed) in self.flatten(tolerance) {
+            if let Some(dashes) = dash(&points, closed, &style.dash_pattern, style.dash_offset) {
+                for dash in dashes {
+                    stroke_polyline(&dash, false, style, tolerance, &mut positions, &mut indices);
+                }
+            } else {
+                stroke_polyline(
+                    &points,
+                    closed,
+                    style,
+                    tolerance,
+                    &mut positions,
+                    &mut indices,
+                );
+            }
+        }
+        mesh_2d(positions, indices)
+    }
+
+    ///
+    /// Returns the sub paths as lists of points without consecutive duplicates together with whether or not the sub path is closed.
+    ///
+    fn flatten(&self, tolerance: f32) -> Vec<(Vec<Vec2>, bool)> {
+        let tolerance = tolerance.max(1e-4);
+        let mut sub_paths = Vec::new();
+        let mut points: Vec<Vec2> = Vec::new();
+        let mut closed = false;
+        let mut finish = |points: &mut Vec<Vec2>, closed: bool| {
+            let mut p: Vec<Vec2> = Vec::with_capacity(points.len());
+            for point in points.drain(..) {
+                if p.last()
+                    .map(|l| (point - l).magnitude2() > 1e-12)
+                    .unwrap_or(true)
+                {
+                    p.push(point);
+                }
+            }
+            if closed && p.len() > 1 && (p[0] - p[p.len() - 1]).magnitude2() <= 1e-12 {
+                p.pop();
+            }
+            if p.len() > 1 {
+                sub_paths.push((p, closed));
+            }
+        };
+        for command in self.commands.iter() {
+            match *command {
+                PathCommand::MoveTo(_) | PathCommand::Close => {}
+                _ => {
+                    // Drawing after closing a sub path starts a new sub path at the start point of the closed sub path
+                    if closed {
+                        let start = points.first().copied();
+                        finish(&mut points, closed);
+                        closed = false;
+                        points.extend(start);
+                    }
+                }
+            }
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    finish(&mut points, closed);
+                    closed = false;
+                    points.push(p);
+                }
+                PathCommand::LineTo(p) => points.push(p),
+                PathCommand::QuadTo(c, p) => {
+                    let p0 = *points.last().unwrap_or(&c);
+                    let dd = (p0 - 2.0 * c + p).magnitude();
+                    let n = ((dd / (8.0 * tolerance)).sqrt().ceil() as usize).clamp(1, 1000);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let s = 1.0 - t;
+                        points.push(s * s * p0 + 2.0 * s * t * c + t * t * p);
+                    }
+                }
+                PathCommand::CubicTo(c0, c1, p) => {
+                    let p0 = *points.last().unwrap_or(&c0);
+                    let dd = (p0 - 2.0 * c0 + c1)
+                        .magnitude()
+                        .max((c0 - 2.0 * c1 + p).magnitude());
+                    let n = ((0.75 * dd / tolerance).sqrt().ceil() as usize).clamp(1, 1000);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let s = 1.0 - t;
+                        points.push(
+                            s * s * s * p0
+                                + 3.0 * s * s * t * c0
+                                + 3.0 * s * t * t * c1
+                                + t * t * t * p,
+                        );
+                    }
+                }
+                PathCommand::Arc(center, radius, start_angle, end_angle) => {
+                    let angle = (end_angle - start_angle).0;
+                    let n = arc_subdivisions(angle.abs(), radius.abs(), tolerance);
+                    for i in 0..=n {
+                        let a = start_angle.0 + angle * i as f32 / n as f32;
+                        points.push(center + radius * vec2(a.cos(), a.sin()));
+                    }
+                }
+                PathCommand::Close => {
+                    closed = true;
+                }
+            }
+        }
+        finish(&mut points, closed);
+        sub_paths
+    }
+}
+
+///
+/// Returns the number of line segments needed to approximate an arc with the given angle and radius within the given tolerance.
+///
+fn arc_subdivisions(angle: f32, radius: f32, tolerance: f32) -> usize {
+    let step = if tolerance < radius {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+    ((angle / step.max(1e-3)).ceil() as usize).clamp(1, 1000)
+}
+
+fn perpendicular(direction: Vec2) -> Vec2 {
+    vec2(-direction.y, direction.x)
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn add_triangle(positions: &mut Vec<Vec3>, indices: &mut Vec<u32>, a: Vec2, b: Vec2, c: Vec2) {
+    let i = positions.len() as u32;
+    // Make sure the triangle is counterclockwise so it is front facing when seen from the positive z-axis
+    if cross(b - a, c - a) < 0.0 {
+        positions.extend([a.extend(0.0), c.extend(0.0), b.extend(0.0)]);
+    } else {
+        positions.extend([a.extend(0.0), b.extend(0.0), c.extend(0.0)]);
+    }
+    indices.extend([i, i + 1, i + 2]);
+}
+
+///
+/// Adds a fan of triangles around the given center going from the start offset and the given angle (counterclockwise if positive).
+///
+fn add_fan(
+    positions: &mut Vec<Vec3>,
+    indices: &mut Vec<u32>,
+    center: Vec2,
+    start: Vec2,
+    angle: f32,
+    tolerance: f32,
+) {
+    let n = arc_subdivisions(angle.abs(), start.magnitude(), tolerance);
+    let mut previous = center + start;
+    for i in 1..=n {
+        let (sin, cos) = (angle * i as f32 / n as f32).sin_cos();
+        let next = center + vec2(cos * start.x - sin * start.y, sin * start.x + cos * start.y);
+        add_triangle(positions, indices, center, previous, next);
+        previous = next;
+    }
+}
+
+fn stroke_polyline(
+    points: &[Vec2],
+    closed: bool,
+    style: &StrokeStyle,
+    tolerance: f32,
+    positions: &mut Vec<Vec3>,
+    indices: &mut Vec<u32>,
+) {
+    let half_width = 0.5 * style.width.abs();
+    if points.len() < 2 || half_width <= 0.0 {
+        return;
+    }
+    let mut points = points.to_vec();
+    if closed {
+        points.push(points[0]);
+    } else if style.cap == LineCap::Square {
+        let n = points.len();
+        let d0 = (points[1] - points[0]).normalize();
+        let d1 = (points[n - 1] - points[n - 2]).normalize();
+        points[0] -= d0 * half_width;
+        points[n - 1] += d1 * half_width;
+    }
+    let n = points.len();
+
+    // Segments
+    for i in 0..n - 1 {
+        let (a, b) = (points[i], points[i + 1]);
+        let offset = perpendicular((b - a).normalize()) * half_width;
+        add_triangle(positions, indices, a + offset, a - offset, b - offset);
+        add_triangle(positions, indices, b - offset, b + offset, a + offset);
+    }
+
+    // Joins
+    let joins = if closed { 1..n } else { 1..n - 1 };
+    for i in joins {
+        let p = points[i];
+        let d0 = (p - points[i - 1]).normalize();
+        let d1 = (points[if i + 1 < n { i + 1 } else { 1 }] - p).normalize();
+        let turn = cross(d0, d1);
+        if turn.abs() < 1e-6 && d0.dot(d1) > 0.0 {
+            continue;
+        }
+        // The join is added on the outer side of the corner
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let n0 = side * perpendicular(d0) * half_width;
+        let n1 = side * perpendicular(d1) * half_width;
+        match style.join {
+            LineJoin::Miter => {
+                let m = (n0 + n1).normalize();
+                let cos_half_angle = m.dot(n0) / half_width;
+                if cos_half_angle > 1e-6 && 1.0 / cos_half_angle <= style.miter_limit {
+                    let tip = p + m * half_width / cos_half_angle;
+                    add_triangle(positions, indices, p, p + n0, tip);
+                    add_triangle(positions, indices, p, tip, p + n1);
+                } else {
+                    add_triangle(positions, indices, p, p + n0, p + n1);
+                }
+            }
+            LineJoin::Round => {
+                let angle = (n0.dot(n1) / (half_width * half_width))
+                    .clamp(-1.0, 1.0)
+                    .acos();
+                let angle = if cross(n0, n1) < 0.0 { -angle } else { angle };
+                add_fan(positions, indices, p, n0, angle, tolerance);
+            }
+            LineJoin::Bevel => {
+                add_triangle(positions, indices, p, p + n0, p + n1);
+            }
+        }
+    }
+
+    // Caps
+    if !closed && style.cap == LineCap::Round {
+        let d0 = (points[1] - points[0]).normalize();
+        let d1 = (points[n - 1] - points[n - 2]).normalize();
+        add_fan(
+            positions,
+            indices,
+            points[0],
+            -perpendicular(d0) * half_width,
+            -std::f32::consts::PI,
+            tolerance,
+        );
+        add_fan(
+            positions,
+            indices,
+            points[n - 1],
+            perpendicular(d1) * half_width,
+            -std::f32::consts::PI,
+            tolerance,
+        );
+    }
+}
+
+///
+/// Splits the polyline into the dashes defined by the dash pattern or returns [None] if the stroke is solid.
+///
+fn dash(points: &[Vec2], closed: bool, pattern: &[f32], offset: f32) -> Option<Vec<Vec<Vec2>>> {
+    let mut points = points.to_vec();
+    if closed {
+        points.push(points[0]);
+    }
+    let mut distances = vec![0.0];
+    for i in 1..points.len() {
+        distances.push(distances[i - 1] + (points[i] - points[i - 1]).magnitude());
+    }
+    let point_at = |distance: f32, i: usize| {
+        let t = (distance - distances[i]) / (distances[i + 1] - distances[i]);
+        points[i] + (points[i + 1] - points[i]) * t
+    };
+
+    let mut dashes = Vec::new();
+    let mut i = 0;
+    for (start, end) in dash_intervals(distances[distances.len() - 1], pattern, offset)? {
+        while distances[i + 1] <= start {
+            i += 1;
+        }
+        let mut dash = vec![point_at(start, i)];
+        while distances[i + 1] < end {
+            i += 1;
+            dash.push(points[i]);
+        }
+        dash.push(point_at(end, i));
+        dashes.push(dash);
+    }
+    // A dash crossing the start point of a closed polyline is joined with the corner at the start point
+    let at_start = |p: &Vec2| (p - points[0]).magnitude2() <= 1e-12;
+    if closed
+        && dashes.len() > 1
+        && at_start(&dashes[0][0])
+        && dashes.last().and_then(|d| d.last()).is_some_and(at_start)
+    {
+        let first = dashes.remove(0);
+        dashes.last_mut().unwrap().extend(&first[1..]);
+    }
+    Some(dashes)
+}
+
+///
+/// Triangulates the simple polygon using ear clipping.
+///
+fn triangulate(points: &[Vec2], positions: &mut Vec<Vec3>, indices: &mut Vec<u32>) {
+    if points.len() < 3 {
+        return;
+    }
+    let offset = positions.len() as u32;
+    positions.extend(points.iter().map(|p| p.extend(0.0)));
+
+    // Make sure the polygon is counterclockwise
+    let area: f32 = (0..points.len())
+        .map(|i| cross(points[i], points[(i + 1) % points.len()]))
+        .sum();
+    let mut remaining: Vec<usize> = if area < 0.0 {
+        (0..points.len()).rev().collect()
+    } else {
+        (0..points.len()).collect()
+    };
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let is_ear = |i: usize| {
+            let (a, b, c) = (
+                points[remaining[(i + n - 1) % n]],
+                points[remaining[i]],
+                points[remaining[(i + 1) % n]],
+            );
+            if cross(b - a, c - b) <= 0.0 {
+                return false;
+            }
+            !remaining.iter().any(|j| {
+                let p = points[*j];
+                p != a
+                    && p != b
+                    && p != c
+                    && cross(b - a, p - a) >= 0.0
+                    && cross(c - b, p - b) >= 0.0
+                    && cross(a - c, p - c) >= 0.0
+            })
+        };
+        // If no ear is found, the polygon is degenerate or self-intersecting, so just clip any vertex
+        let i = (0..n).find(|i| is_ear(*i)).unwrap_or(0);
+        indices.extend([
+            offset + remaining[(i + n - 1) % n] as u32,
+            offset + remaining[i] as u32,
+            offset + remaining[(i + 1) % n] as u32,
+        ]);
+        remaining.remove(i);
+    }
+    indices.extend(remaining.iter().map(|i| offset + *i as u32));
+}
+
+fn mesh_2d(positions: Vec<Vec3>, indices: Vec<u32>) -> CpuMesh {
+    let normals = vec![vec3(0.0, 0.0, 1.0); positions.len()];
+    CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        normals: Some(normals),
+        ..Default::default()
+    }
+}
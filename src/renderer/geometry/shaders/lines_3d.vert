@@ -0,0 +1,82 @@
+uniform mat4 viewProjection;
+uniform mat4 transformation;
+uniform vec2 viewportSize;
+uniform float halfWidth;
+
+#ifdef WORLD_WIDTH
+uniform float projectionScale;
+#endif
+
+// The end of the segment, the offset along the segment and the offset across the segment
+in vec3 position;
+
+in vec3 start;
+in vec3 end;
+
+#ifdef USE_VERTEX_COLORS
+in vec4 start_color;
+in vec4 end_color;
+#endif
+
+out vec3 pos;
+out vec4 col;
+
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
+void main()
+{
+#ifdef USE_INSTANCE_ID
+    instanceId = 0;
+#endif
+    vec4 worldStart = transformation * vec4(start, 1.0);
+    vec4 worldEnd = transformation * vec4(end, 1.0);
+    vec4 clipStart = viewProjection * worldStart;
+    vec4 clipEnd = viewProjection * worldEnd;
+
+    // Clip the segment against the near plane
+    float distanceStart = clipStart.z + clipStart.w;
+    float distanceEnd = clipEnd.z + clipEnd.w;
+    if (distanceStart < 0.0 && distanceEnd < 0.0) {
+        gl_Position = vec4(0.0, 0.0, -2.0, 1.0);
+        return;
+    }
+    float tStart = 0.0;
+    float tEnd = 1.0;
+    if (distanceStart < 0.0) {
+        tStart = distanceStart / (distanceStart - distanceEnd);
+    } else if (distanceEnd < 0.0) {
+        tEnd = distanceStart / (distanceStart - distanceEnd);
+    }
+    vec4 c0 = mix(clipStart, clipEnd, tStart);
+    vec4 c1 = mix(clipStart, clipEnd, tEnd);
+
+    // The direction of the segment in pixels
+    vec2 screenStart = 0.5 * viewportSize * c0.xy / c0.w;
+    vec2 screenEnd = 0.5 * viewportSize * c1.xy / c1.w;
+    vec2 direction = screenEnd - screenStart;
+    direction = length(direction) > 1e-6 ? normalize(direction) : vec2(1.0, 0.0);
+    vec2 perpendicular = vec2(-direction.y, direction.x);
+
+    float t = position.x < 0.5 ? tStart : tEnd;
+    vec4 clipPosition = position.x < 0.5 ? c0 : c1;
+
+#ifdef WORLD_WIDTH
+    float w = halfWidth * projectionScale / clipPosition.w;
+#else
+    float w = halfWidth;
+#endif
+    vec2 offset = w * (position.y * direction + position.z * perpendicular);
+    gl_Position = clipPosition;
+    gl_Position.xy += 2.0 * offset / viewportSize * clipPosition.w;
+
+    vec4 worldPosition = mix(worldStart, worldEnd, t);
+    pos = worldPosition.xyz / worldPosition.w;
+
+#ifdef USE_VERTEX_COLORS
+    col = mix(start_color, end_color, t);
+#else
+    col = vec4(1.0);
+#endif
+}
@@ -0,0 +1,12 @@
+
+uniform vec2 lodFade;
+
+void main()
+{
+    // Interleaved gradient noise, the two levels that are cross-faded use complementary parts of the pattern
+    float noise = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+    if ((noise < lodFade.x) == (lodFade.y > 0.5)) {
+        discard;
+    }
+    lod_main();
+}
@@ -20,6 +20,11 @@ in vec4 row2;
 in vec4 row3;
 #endif
 
+#ifdef USE_BATCH_TRANSFORMATIONS
+uniform sampler2D batchTransformations;
+in float batch_index;
+#endif
+
 out vec3 pos;
 
 #ifdef USE_NORMALS 
@@ -45,6 +50,11 @@ in vec2 uv_coordinates;
 out vec2 uvs;
 #endif
 
+#ifdef USE_UVS2
+in vec2 uv2_coordinates;
+out vec2 uvs2;
+#endif
+
 #ifdef USE_VERTEX_COLORS 
 in vec4 color;
 #endif
@@ -54,6 +64,13 @@ in vec4 instance_color;
 
 out vec4 col;
 
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#ifdef USE_INSTANCE_INDICES
+in float instance_index;
+#endif
+#endif
+
 void main()
 {
     // *** POSITION ***
@@ -68,6 +85,17 @@ void main()
     local2World *= transform;
 #endif
 
+#ifdef USE_BATCH_TRANSFORMATIONS
+    // Four texels per transformation, stored from the top row since the texture data is flipped
+    ivec2 size = textureSize(batchTransformations, 0);
+    int texel = 4 * int(batch_index);
+    mat4 batchTransform;
+    for (int i = 0; i < 4; i++) {
+        batchTransform[i] = texelFetch(batchTransformations, ivec2((texel + i) % size.x, size.y - 1 - (texel + i) / size.x), 0);
+    }
+    local2World *= batchTransform;
+#endif
+
     vec4 worldPosition = local2World * vec4(position, 1.);
     worldPosition /= worldPosition.w;
 #ifdef PARTICLES
@@ -82,7 +110,7 @@ void main()
 
     // *** NORMAL ***
 #ifdef USE_NORMALS 
-#ifdef USE_INSTANCE_TRANSFORMS
+#if defined(USE_INSTANCE_TRANSFORMS) || defined(USE_BATCH_TRANSFORMATIONS)
     mat3 normalMat = mat3(transpose(inverse(local2World)));
 #else
     mat3 normalMat = mat3(normalMatrix);
@@ -107,6 +135,9 @@ void main()
 #else
     uvs = uv_coordinates;
 #endif
+#endif
+#ifdef USE_UVS2
+    uvs2 = uv2_coordinates;
 #endif
 
     // *** COLOR ***
@@ -116,5 +147,16 @@ void main()
 #endif
 #ifdef USE_INSTANCE_COLORS
     col *= instance_color;
+#endif
+
+    // *** INSTANCE ID ***
+#ifdef USE_INSTANCE_ID
+#if defined(USE_INSTANCE_INDICES)
+    instanceId = int(instance_index);
+#elif defined(USE_BATCH_TRANSFORMATIONS)
+    instanceId = int(batch_index);
+#else
+    instanceId = gl_InstanceID;
+#endif
 #endif
 }
\ No newline at end of file
@@ -5,6 +5,23 @@ uniform vec3 direction;
 
 in vec3 center;
 
+#ifdef USE_INSTANCE_SIZES
+in vec2 instance_size;
+#endif
+
+#ifdef USE_INSTANCE_ROTATIONS
+in float instance_rotation;
+#endif
+
+#ifdef USE_INSTANCE_COLORS
+in vec4 instance_color;
+#endif
+
+#ifdef USE_INSTANCE_TEXTURE_TRANSFORMATION
+in vec3 tex_transform_row1;
+in vec3 tex_transform_row2;
+#endif
+
 in vec3 position;
 in vec2 uv_coordinate;
 
@@ -12,10 +29,26 @@ out vec2 uvs;
 out vec4 col;
 out vec3 pos;
 
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
 void main()
 {
+#ifdef USE_INSTANCE_TEXTURE_TRANSFORMATION
+    mat3 texTransform;
+    texTransform[0] = vec3(tex_transform_row1.x, tex_transform_row2.x, 0.0);
+    texTransform[1] = vec3(tex_transform_row1.y, tex_transform_row2.y, 0.0);
+    texTransform[2] = vec3(tex_transform_row1.z, tex_transform_row2.z, 1.0);
+    uvs = (texTransform * vec3(uv_coordinate, 1.0)).xy;
+#else
     uvs = uv_coordinate;
+#endif
+
     col = vec4(1.0);
+#ifdef USE_INSTANCE_COLORS
+    col *= instance_color;
+#endif
 
     vec3 z = normalize(eye - center);
     vec3 y = direction;
@@ -31,11 +64,25 @@ void main()
         x = normalize(cross(y, z));
     }
 
+    vec3 p = position;
+#ifdef USE_INSTANCE_SIZES
+    p.xy *= 0.5 * instance_size;
+#endif
+#ifdef USE_INSTANCE_ROTATIONS
+    float c = cos(instance_rotation);
+    float s = sin(instance_rotation);
+    p.xy = vec2(c * p.x - s * p.y, s * p.x + c * p.y);
+#endif
+
     mat4 instanced_transform = mat4(x, 0.0,
                 y, 0.0,
                 z, 0.0,
                 center.x, center.y, center.z, 1.0);
-    vec4 world_pos = instanced_transform * transformation * vec4(position, 1.);
+    vec4 world_pos = instanced_transform * transformation * vec4(p, 1.);
     pos = world_pos.xyz / world_pos.w;
     gl_Position = viewProjection * world_pos;
+
+#ifdef USE_INSTANCE_ID
+    instanceId = gl_InstanceID;
+#endif
 }
@@ -1,5 +1,6 @@
 use crate::core::*;
 use crate::renderer::*;
+use std::collections::HashMap;
 
 ///
 /// A set of sprites, ie. a set of quads that orients itself towards the camera.
@@ -12,7 +13,9 @@ pub struct Sprites {
     context: Context,
     position_buffer: VertexBuffer,
     uv_buffer: VertexBuffer,
-    center_buffer: InstanceBuffer,
+    instance_buffers: HashMap<String, InstanceBuffer>,
+    instances: SpriteInstances,
+    animation: Option<SpriteAnimation>,
     transformation: Mat4,
     direction: Option<Vec3>,
 }
@@ -23,6 +26,25 @@ impl Sprites {
     /// The sprites will always orient themselves towards the camera, but if a direction is specified, the sprite normals will always be orthogonal to that direction.
     ///
     pub fn new(context: &Context, centers: &[Vec3], direction: Option<Vec3>) -> Self {
+        Self::new_with_instances(
+            context,
+            &SpriteInstances {
+                centers: centers.to_vec(),
+                ..Default::default()
+            },
+            direction,
+        )
+    }
+
+    ///
+    /// Create a new set of [Sprites] with the given [SpriteInstances] which defines the center, size, rotation, color and texture transformation of each sprite.
+    /// The sprites will always orient themselves towards the camera, but if a direction is specified, the sprite normals will always be orthogonal to that direction.
+    ///
+    pub fn new_with_instances(
+        context: &Context,
+        instances: &SpriteInstances,
+        direction: Option<Vec3>,
+    ) -> Self {
         let position_buffer = VertexBuffer::new_with_data(
             context,
             &[
@@ -45,14 +67,18 @@ impl Sprites {
                 vec2(0.0, 0.0),
             ],
         );
-        Self {
+        let mut sprites = Self {
             context: context.clone(),
             position_buffer,
             uv_buffer,
-            center_buffer: InstanceBuffer::new_with_data(context, centers),
+            instance_buffers: HashMap::new(),
+            instances: instances.clone(),
+            animation: None,
             transformation: Mat4::identity(),
             direction,
-        }
+        };
+        sprites.set_instances(instances);
+        sprites
     }
 
     ///
@@ -78,9 +104,115 @@ impl Sprites {
 
     ///
     /// Set the centers of the sprites. The centers also determines the number of sprites.
+    /// The rest of the [SpriteInstances] are kept, so they must contain at least as many elements as the number of centers.
+    /// If the number of sprites is unchanged, only the centers are updated and the existing buffer is reused, so it is cheap to move the sprites every frame.
     ///
     pub fn set_centers(&mut self, centers: &[Vec3]) {
-        self.center_buffer.fill(centers);
+        if centers.len() == self.instances.centers.len() {
+            self.instances.centers = centers.to_vec();
+            self.fill_instance_buffer("center", centers);
+        } else {
+            let instances = SpriteInstances {
+                centers: centers.to_vec(),
+                ..self.instances.clone()
+            };
+            self.set_instances(&instances);
+        }
+    }
+
+    ///
+    /// Returns the number of sprites.
+    ///
+    pub fn instance_count(&self) -> u32 {
+        self.instances.count()
+    }
+
+    ///
+    /// Update the center, size, rotation, color and texture transformation of each sprite.
+    ///
+    pub fn set_instances(&mut self, instances: &SpriteInstances) {
+        #[cfg(debug_assertions)]
+        instances.validate().expect("invalid sprite instances");
+        self.instances = instances.clone();
+        self.instance_buffers.clear();
+        self.instance_buffers.insert(
+            "center".to_string(),
+            InstanceBuffer::new_with_data(&self.context, &instances.centers),
+        );
+        if let Some(sizes) = &instances.sizes {
+            self.instance_buffers.insert(
+                "instance_size".to_string(),
+                InstanceBuffer::new_with_data(&self.context, sizes),
+            );
+        }
+        if let Some(rotations) = &instances.rotations {
+            self.instance_buffers.insert(
+                "instance_rotation".to_string(),
+                InstanceBuffer::new_with_data(
+                    &self.context,
+                    &rotations.iter().map(|r| r.0).collect::<Vec<_>>(),
+                ),
+            );
+        }
+        if let Some(colors) = &instances.colors {
+            self.instance_buffers.insert(
+                "instance_color".to_string(),
+                InstanceBuffer::new_with_data(
+                    &self.context,
+                    &colors
+                        .iter()
+                        .map(|c| c.to_linear_srgb())
+                        .collect::<Vec<_>>(),
+                ),
+            );
+        }
+        if let Some(texture_transformations) = &instances.texture_transformations {
+            self.update_texture_transformations(texture_transformations);
+        }
+    }
+
+    ///
+    /// Set a [SpriteAnimation] which animates the sprites using a sprite sheet.
+    /// To actually animate the sprites, call [Geometry::animate] at each frame.
+    ///
+    pub fn set_animation(&mut self, animation: Option<SpriteAnimation>) {
+        self.animation = animation;
+        if self.animation.is_none() {
+            // Reset the texture transformations to the ones defined by the instances
+            let instances = self.instances.clone();
+            self.set_instances(&instances);
+        }
+    }
+
+    fn update_texture_transformations(&mut self, texture_transformations: &[Mat3]) {
+        let mut instance_tex_transform1 = Vec::new();
+        let mut instance_tex_transform2 = Vec::new();
+        for texture_transform in texture_transformations.iter() {
+            instance_tex_transform1.push(vec3(
+                texture_transform.x.x,
+                texture_transform.y.x,
+                texture_transform.z.x,
+            ));
+            instance_tex_transform2.push(vec3(
+                texture_transform.x.y,
+                texture_transform.y.y,
+                texture_transform.z.y,
+            ));
+        }
+        // The texture transformations are updated every frame when animating, so reuse the buffers
+        self.fill_instance_buffer("tex_transform_row1", &instance_tex_transform1);
+        self.fill_instance_buffer("tex_transform_row2", &instance_tex_transform2);
+    }
+
+    fn fill_instance_buffer<T: BufferDataType>(&mut self, name: &str, data: &[T]) {
+        if let Some(buffer) = self.instance_buffers.get_mut(name) {
+            buffer.fill(data);
+        } else {
+            self.instance_buffers.insert(
+                name.to_string(),
+                InstanceBuffer::new_with_data(&self.context, data),
+            );
+        }
     }
 
     fn draw(&self, program: &Program, render_states: RenderStates, camera: &Camera) {
@@ -88,15 +220,137 @@ impl Sprites {
         program.use_uniform("viewProjection", camera.projection() * camera.view());
         program.use_uniform("transformation", self.transformation);
         program.use_vertex_attribute("position", &self.position_buffer);
-        program.use_vertex_attribute("uv_coordinate", &self.uv_buffer);
-        program.use_instance_attribute("center", &self.center_buffer);
+        if program.requires_attribute("uv_coordinate") {
+            program.use_vertex_attribute("uv_coordinate", &self.uv_buffer);
+        }
+        for attribute_name in [
+            "center",
+            "instance_size",
+            "instance_rotation",
+            "instance_color",
+            "tex_transform_row1",
+            "tex_transform_row2",
+        ] {
+            if program.requires_attribute(attribute_name) {
+                program.use_instance_attribute(
+                    attribute_name,
+                    self.instance_buffers.get(attribute_name).unwrap_or_else(|| {
+                        panic!(
+                            "the render call requires the {} instance buffer which is missing on the given sprites",
+                            attribute_name
+                        )
+                    }),
+                );
+            }
+        }
         program.use_uniform("direction", self.direction.unwrap_or(vec3(0.0, 0.0, 0.0)));
-        program.draw_arrays_instanced(
-            render_states,
-            camera.viewport(),
-            6,
-            self.center_buffer.instance_count(),
-        )
+        program.draw_arrays_instanced(render_states, camera.viewport(), 6, self.instance_count())
+    }
+}
+
+///
+/// Defines the attributes for each sprite in [Sprites].
+///
+/// Each list of attributes must contain the same number of elements as the number of centers, which is also the number of sprites.
+///
+#[derive(Clone, Debug, Default)]
+pub struct SpriteInstances {
+    /// The center of each sprite.
+    pub centers: Vec<Vec3>,
+    /// The width and height of each sprite before the transformation applied to all sprites (see [Sprites::set_transformation]).
+    /// If not specified, the size of each sprite is 2 by 2.
+    pub sizes: Option<Vec<Vec2>>,
+    /// The rotation of each sprite around its normal, ie. in the plane of the sprite.
+    pub rotations: Option<Vec<Radians>>,
+    /// Colors multiplied onto the base color of each sprite.
+    pub colors: Option<Vec<Srgba>>,
+    /// The texture transform applied to the uv coordinates of each sprite, for example to select a part of a texture atlas.
+    pub texture_transformations: Option<Vec<Mat3>>,
+}
+
+impl SpriteInstances {
+    ///
+    /// Returns an error if the sprite instances is not valid.
+    ///
+    pub fn validate(&self) -> Result<(), RendererError> {
+        let instance_count = self.count();
+        let buffer_check = |length: Option<usize>, name: &str| -> Result<(), RendererError> {
+            if let Some(length) = length {
+                if length < instance_count as usize {
+                    Err(RendererError::InvalidBufferLength(
+                        name.to_string(),
+                        instance_count as usize,
+                        length,
+                    ))?;
+                }
+            }
+            Ok(())
+        };
+
+        buffer_check(self.sizes.as_ref().map(|b| b.len()), "sizes")?;
+        buffer_check(self.rotations.as_ref().map(|b| b.len()), "rotations")?;
+        buffer_check(self.colors.as_ref().map(|b| b.len()), "colors")?;
+        buffer_check(
+            self.texture_transformations.as_ref().map(|b| b.len()),
+            "texture transformations",
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the number of sprites.
+    pub fn count(&self) -> u32 {
+        self.centers.len() as u32
+    }
+}
+
+///
+/// An animation of [Sprites] using a sprite sheet, ie. a texture containing the frames of the animation in a grid.
+/// The frames are read row by row starting in the top left corner of the texture.
+///
+#[derive(Clone, Debug)]
+pub struct SpriteAnimation {
+    /// The number of columns of frames in the sprite sheet.
+    pub columns: u32,
+    /// The number of rows of frames in the sprite sheet.
+    pub rows: u32,
+    /// The number of frames in the animation, which must be less than or equal to the number of columns times the number of rows.
+    pub frame_count: u32,
+    /// The number of frames shown per time unit, ie. frames per second if the time given to [Geometry::animate] is in seconds.
+    pub frames_per_second: f32,
+    /// Whether or not to start over when the last frame is reached, otherwise the last frame is shown until the animation is restarted.
+    pub looping: bool,
+    /// The time at which the animation starts for each sprite, for example when an explosion is triggered.
+    /// Before the start time, the first frame is shown. If not specified, the animation starts at time zero for all sprites.
+    pub start_times: Option<Vec<f32>>,
+}
+
+impl SpriteAnimation {
+    ///
+    /// Returns the index of the frame shown at the given time for an animation started at the given start time.
+    ///
+    pub fn frame_at(&self, time: f32, start_time: f32) -> u32 {
+        let frame_count = self.frame_count.max(1);
+        let frame = ((time - start_time) * self.frames_per_second).max(0.0) as u32;
+        if self.looping {
+            frame % frame_count
+        } else {
+            frame.min(frame_count - 1)
+        }
+    }
+
+    ///
+    /// Returns the texture transformation that maps the uv coordinates of a sprite to the given frame in the sprite sheet.
+    ///
+    pub fn texture_transformation(&self, frame: u32) -> Mat3 {
+        let columns = self.columns.max(1);
+        let rows = self.rows.max(1);
+        let column = frame % columns;
+        let row = (frame / columns).min(rows - 1);
+        Mat3::from_translation(vec2(
+            column as f32 / columns as f32,
+            (rows - 1 - row) as f32 / rows as f32,
+        )) * Mat3::from_nonuniform_scale(1.0 / columns as f32, 1.0 / rows as f32)
     }
 }
 
@@ -117,21 +371,62 @@ impl Geometry for Sprites {
         render_states: RenderStates,
         attributes: FragmentAttributes,
     ) {
-        if !attributes.uv {
-            todo!()
-        }
         if attributes.normal || attributes.tangents {
             todo!()
         }
         self.draw(program, render_states, camera);
     }
 
-    fn vertex_shader_source(&self, _required_attributes: FragmentAttributes) -> String {
-        include_str!("shaders/sprites.vert").to_owned()
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "{}{}{}{}{}{}",
+            if self.instance_buffers.contains_key("instance_size") {
+                "#define USE_INSTANCE_SIZES\n"
+            } else {
+                ""
+            },
+            if self.instance_buffers.contains_key("instance_rotation") {
+                "#define USE_INSTANCE_ROTATIONS\n"
+            } else {
+                ""
+            },
+            if required_attributes.color && self.instance_buffers.contains_key("instance_color") {
+                "#define USE_INSTANCE_COLORS\n"
+            } else {
+                ""
+            },
+            if required_attributes.uv && self.instance_buffers.contains_key("tex_transform_row1") {
+                "#define USE_INSTANCE_TEXTURE_TRANSFORMATION\n"
+            } else {
+                ""
+            },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("shaders/sprites.vert"),
+        )
     }
 
-    fn id(&self, _required_attributes: FragmentAttributes) -> u16 {
-        0b1u16 << 15 | 0b100u16
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15 | 0b1u16 << 6;
+        if self.instance_buffers.contains_key("instance_size") {
+            id |= 0b1u16;
+        }
+        if self.instance_buffers.contains_key("instance_rotation") {
+            id |= 0b1u16 << 1;
+        }
+        if required_attributes.color && self.instance_buffers.contains_key("instance_color") {
+            id |= 0b1u16 << 2;
+        }
+        if required_attributes.uv && self.instance_buffers.contains_key("tex_transform_row1") {
+            id |= 0b1u16 << 3;
+        }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
     }
 
     fn render_with_material(
@@ -165,4 +460,26 @@ impl Geometry for Sprites {
     fn aabb(&self) -> AxisAlignedBoundingBox {
         AxisAlignedBoundingBox::INFINITE
     }
+
+    fn animate(&mut self, time: f32) {
+        if let Some(animation) = &self.animation {
+            let texture_transformations = (0..self.instances.count() as usize)
+                .map(|i| {
+                    let start_time = animation
+                        .start_times
+                        .as_ref()
+                        .and_then(|t| t.get(i).copied())
+                        .unwrap_or(0.0);
+                    let frame_transformation =
+                        animation.texture_transformation(animation.frame_at(time, start_time));
+                    self.instances
+                        .texture_transformations
+                        .as_ref()
+                        .map(|t| t[i] * frame_transformation)
+                        .unwrap_or(frame_transformation)
+                })
+                .collect::<Vec<_>>();
+            self.update_texture_transformations(&texture_transformations);
+        }
+    }
 }
@@ -40,6 +40,14 @@ mod environment;
 #[doc(inline)]
 pub use environment::*;
 
+mod reflection_probe;
+#[doc(inline)]
+pub use reflection_probe::*;
+
+mod irradiance_volume;
+#[doc(inline)]
+pub use irradiance_volume::*;
+
 use crate::core::*;
 use crate::renderer::camera::*;
 
@@ -1,10 +1,87 @@
+use super::irradiance_volume::project_onto_spherical_harmonics;
 use crate::core::*;
 use crate::renderer::*;
 
+///
+/// The data format of each channel of the irradiance and prefilter maps of an [Environment].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvironmentFormat {
+    /// 16-bit float, which is enough for most environments and uses half the memory.
+    F16,
+    /// 32-bit float.
+    F32,
+}
+
+///
+/// Settings used when computing an [Environment], see [Environment::new_with_settings].
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvironmentSettings {
+    /// The lighting model used when computing the prefilter map and the BRDF lookup table.
+    /// The default is a Cook-Torrance lighting model with the Trowbridge-Reitz GGX normal distribution function and the Smith-Schlick GGX geometry function.
+    pub lighting_model: LightingModel,
+    /// The size in pixels of each side of the irradiance map.
+    /// The default is 32.
+    pub irradiance_size: u32,
+    /// The number of samples along the polar angle when integrating the light over the hemisphere for each pixel in the irradiance map.
+    /// Four times as many samples are used along the azimuthal angle.
+    /// The default is 100.
+    pub irradiance_sample_count: u32,
+    /// Whether to compute the irradiance map by projecting the environment map onto L2 spherical harmonics instead of integrating over the hemisphere for each pixel.
+    /// This is much faster, especially for large irradiance maps or when using a software rasterizer, but smooths out details like the direction of a small and bright sun.
+    /// If true, the [EnvironmentSettings::irradiance_sample_count] is not used.
+    /// The default is false.
+    pub spherical_harmonics_irradiance: bool,
+    /// The size in pixels of each side of the first mip level of the prefilter map.
+    /// Increase this to get sharper reflections in mirror-like materials.
+    /// The size is at least 32, since the prefilter map contains five mip levels.
+    /// The default is 128.
+    pub prefilter_size: u32,
+    /// The number of samples used when computing each pixel in the prefilter map.
+    /// The default is 1024.
+    pub prefilter_sample_count: u32,
+    /// The width and height in pixels of the BRDF lookup table.
+    /// The default is 512.
+    pub brdf_size: u32,
+    /// The number of samples used when computing each pixel in the BRDF lookup table.
+    /// The default is 1024.
+    pub brdf_sample_count: u32,
+    /// The data format of the irradiance and prefilter maps. The BRDF lookup table always uses 32-bit floats.
+    /// The default is [EnvironmentFormat::F16].
+    pub format: EnvironmentFormat,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            lighting_model: LightingModel::Cook(
+                NormalDistributionFunction::TrowbridgeReitzGGX,
+                GeometryFunction::SmithSchlickGGX,
+            ),
+            irradiance_size: 32,
+            irradiance_sample_count: 100,
+            spherical_harmonics_irradiance: false,
+            prefilter_size: 128,
+            prefilter_sample_count: 1024,
+            brdf_size: 512,
+            brdf_sample_count: 1024,
+            format: EnvironmentFormat::F16,
+        }
+    }
+}
+
+const PREFILTER_MIP_LEVELS: u32 = 5;
+const FILE_IDENTIFIER: &[u8; 4] = b"3DEN";
+const FILE_VERSION: u32 = 1;
+
 ///
 /// Precalculations of light shining from an environment map (known as image based lighting - IBL).
 /// This allows for real-time rendering of ambient light from the environment (see [AmbientLight](crate::AmbientLight)).
 ///
+/// The precalculations can be slow, so use [Environment::to_bytes] or [Environment::save] to store the result
+/// and [Environment::from_bytes] or [Environment::load] to load it again instead of computing it every time the application starts.
+///
 pub struct Environment {
     /// A cube map used to calculate the diffuse contribution from the environment.
     pub irradiance_map: TextureCubeMap,
@@ -21,14 +98,7 @@ impl Environment {
     /// A default Cook-Torrance lighting model is used.
     ///
     pub fn new(context: &Context, environment_map: &TextureCubeMap) -> Self {
-        Self::new_with_lighting_model(
-            context,
-            environment_map,
-            LightingModel::Cook(
-                NormalDistributionFunction::TrowbridgeReitzGGX,
-                GeometryFunction::SmithSchlickGGX,
-            ),
-        )
+        Self::new_with_settings(context, environment_map, EnvironmentSettings::default())
     }
 
     ///
@@ -39,91 +109,71 @@ impl Environment {
         environment_map: &TextureCubeMap,
         lighting_model: LightingModel,
     ) -> Self {
-        // Diffuse
-        let irradiance_size = 32;
-        let mut irradiance_map = TextureCubeMap::new_empty::<[f16; 4]>(
+        Self::new_with_settings(
             context,
-            irradiance_size,
-            irradiance_size,
-            Interpolation::Linear,
-            Interpolation::Linear,
-            Some(Interpolation::Linear),
-            Wrapping::ClampToEdge,
-            Wrapping::ClampToEdge,
-            Wrapping::ClampToEdge,
-        );
-        {
-            let viewport = Viewport::new_at_origo(irradiance_size, irradiance_size);
-            for side in CubeMapSide::iter() {
-                irradiance_map
-                    .as_color_target(&[side], None)
-                    .clear(ClearState::default())
-                    .apply_screen_material(
-                        &IrradianceMaterial {
-                            environment_map,
-                            side,
-                        },
-                        &Camera::new_2d(viewport),
-                        &[],
-                    );
-            }
+            environment_map,
+            EnvironmentSettings {
+                lighting_model,
+                ..Default::default()
+            },
+        )
+    }
+
+    ///
+    /// Computes the maps needed for physically based rendering with lighting from an environment from the given environment map
+    /// with the resolutions, sample counts and formats specified in the settings.
+    ///
+    pub fn new_with_settings(
+        context: &Context,
+        environment_map: &TextureCubeMap,
+        settings: EnvironmentSettings,
+    ) -> Self {
+        // Diffuse
+        let mut irradiance_map =
+            new_environment_cube_map(context, settings.irradiance_size, settings.format);
+        if settings.spherical_harmonics_irradiance {
+            let coefficients = environment_spherical_harmonics(environment_map);
+            apply_to_sides(&mut irradiance_map, None, |side| {
+                SphericalHarmonicsMaterial { coefficients, side }
+            });
+        } else {
+            let sample_delta =
+                0.5 * std::f32::consts::PI / settings.irradiance_sample_count.max(1) as f32;
+            apply_to_sides(&mut irradiance_map, None, |side| IrradianceMaterial {
+                environment_map,
+                side,
+                sample_delta,
+            });
         }
 
         // Prefilter
-        let prefilter_size = 128;
-        let mut prefilter_map = TextureCubeMap::new_empty::<[f16; 4]>(
+        let mut prefilter_map = new_environment_cube_map(
             context,
-            prefilter_size,
-            prefilter_size,
-            Interpolation::Linear,
-            Interpolation::Linear,
-            Some(Interpolation::Linear),
-            Wrapping::ClampToEdge,
-            Wrapping::ClampToEdge,
-            Wrapping::ClampToEdge,
+            settings.prefilter_size.max(1 << (PREFILTER_MIP_LEVELS)),
+            settings.format,
         );
-        {
-            let max_mip_levels = 5;
-            for mip in 0..max_mip_levels {
-                for side in CubeMapSide::iter() {
-                    let sides = [side];
-                    let color_target = prefilter_map.as_color_target(&sides, Some(mip));
-                    let viewport =
-                        Viewport::new_at_origo(color_target.width(), color_target.height());
-                    color_target
-                        .clear(ClearState::default())
-                        .apply_screen_material(
-                            &PrefilterMaterial {
-                                lighting_model,
-                                environment_map,
-                                side,
-                                mip,
-                                max_mip_levels,
-                            },
-                            &Camera::new_2d(viewport),
-                            &[],
-                        );
-                }
-            }
+        for mip in 0..PREFILTER_MIP_LEVELS {
+            apply_to_sides(&mut prefilter_map, Some(mip), |side| PrefilterMaterial {
+                lighting_model: settings.lighting_model,
+                environment_map,
+                side,
+                mip,
+                max_mip_levels: PREFILTER_MIP_LEVELS,
+                sample_count: settings.prefilter_sample_count,
+            });
         }
 
         // BRDF
-        let mut brdf_map = Texture2D::new_empty::<[f32; 2]>(
-            context,
-            512,
-            512,
-            Interpolation::Linear,
-            Interpolation::Linear,
-            None,
-            Wrapping::ClampToEdge,
-            Wrapping::ClampToEdge,
-        );
+        let mut brdf_map = new_brdf_map(context, settings.brdf_size, settings.brdf_size);
         let viewport = Viewport::new_at_origo(brdf_map.width(), brdf_map.height());
         brdf_map
             .as_color_target(None)
             .clear(ClearState::default())
             .apply_screen_material(
-                &BrdfMaterial { lighting_model },
+                &BrdfMaterial {
+                    lighting_model: settings.lighting_model,
+                    sample_count: settings.brdf_sample_count,
+                },
                 &Camera::new_2d(viewport),
                 &[],
             );
@@ -134,6 +184,383 @@ impl Environment {
             brdf_map,
         }
     }
+
+    ///
+    /// Returns the irradiance map, all mip levels of the prefilter map and the BRDF lookup table in a binary format which can be loaded again using [Environment::from_bytes].
+    /// The data is stored in the [EnvironmentFormat] used when computing the environment,
+    /// ie. 32-bit floats if the irradiance map contains 32-bit floats and otherwise 16-bit floats.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(FILE_IDENTIFIER);
+        let environment_format =
+            if self.irradiance_map.data_byte_size() == std::mem::size_of::<[f32; 4]>() {
+                EnvironmentFormat::F32
+            } else {
+                EnvironmentFormat::F16
+            };
+        let format = match environment_format {
+            EnvironmentFormat::F16 => 0,
+            EnvironmentFormat::F32 => 1,
+        };
+        for value in [
+            FILE_VERSION,
+            format,
+            self.irradiance_map.width(),
+            self.prefilter_map.width(),
+            PREFILTER_MIP_LEVELS,
+            self.brdf_map.width(),
+            self.brdf_map.height(),
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut write = |values: &[f32], format: EnvironmentFormat| {
+            for value in values {
+                match format {
+                    EnvironmentFormat::F16 => {
+                        bytes.extend_from_slice(&f16::from_f32(*value).to_le_bytes())
+                    }
+                    EnvironmentFormat::F32 => bytes.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        };
+        for side in CubeMapSide::iter() {
+            write(
+                &read_side(&self.irradiance_map, side, 0),
+                environment_format,
+            );
+        }
+        for mip in 0..PREFILTER_MIP_LEVELS {
+            for side in CubeMapSide::iter() {
+                write(
+                    &read_side(&self.prefilter_map, side, mip),
+                    environment_format,
+                );
+            }
+        }
+        // Read as RGBA since reading two channels is not supported on all platforms
+        let brdf = match self.brdf_map.read_cpu_texture::<[f32; 4]>().data {
+            TextureData::RgbaF32(data) => data,
+            _ => unreachable!(),
+        };
+        let brdf = brdf.iter().flat_map(|p| [p[0], p[1]]).collect::<Vec<_>>();
+        write(&brdf, EnvironmentFormat::F32);
+        bytes
+    }
+
+    ///
+    /// Creates an environment from data created using [Environment::to_bytes] without doing any of the expensive precalculations.
+    ///
+    pub fn from_bytes(context: &Context, bytes: &[u8]) -> Result<Self, RendererError> {
+        let mut reader = EnvironmentReader { bytes, position: 0 };
+        if reader.take(4)? != FILE_IDENTIFIER {
+            return Err(RendererError::InvalidEnvironmentData(
+                "the data is not an environment".to_owned(),
+            ));
+        }
+        let version = reader.read_u32()?;
+        if version != FILE_VERSION {
+            return Err(RendererError::InvalidEnvironmentData(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let format = match reader.read_u32()? {
+            0 => EnvironmentFormat::F16,
+            1 => EnvironmentFormat::F32,
+            format => {
+                return Err(RendererError::InvalidEnvironmentData(format!(
+                    "unsupported format {}",
+                    format
+                )))
+            }
+        };
+        let irradiance_size = reader.read_u32()?;
+        let prefilter_size = reader.read_u32()?;
+        let prefilter_mip_levels = reader.read_u32()?;
+        let brdf_width = reader.read_u32()?;
+        let brdf_height = reader.read_u32()?;
+        if irradiance_size == 0
+            || brdf_width == 0
+            || brdf_height == 0
+            || prefilter_mip_levels != PREFILTER_MIP_LEVELS
+            || prefilter_size < 1 << (PREFILTER_MIP_LEVELS)
+        {
+            return Err(RendererError::InvalidEnvironmentData(
+                "invalid texture sizes".to_owned(),
+            ));
+        }
+
+        // Check the length of the data before allocating any textures, since the sizes cannot be trusted
+        let value_size = match format {
+            EnvironmentFormat::F16 => 2,
+            EnvironmentFormat::F32 => 4,
+        };
+        let side_bytes = |size: u32| {
+            (size as usize)
+                .checked_mul(size as usize)
+                .and_then(|pixels| pixels.checked_mul(6 * 4 * value_size))
+        };
+        let expected_length = (0..PREFILTER_MIP_LEVELS)
+            .map(|mip| side_bytes((prefilter_size >> mip).max(1)))
+            .chain([
+                side_bytes(irradiance_size),
+                (brdf_width as usize)
+                    .checked_mul(brdf_height as usize)
+                    .and_then(|pixels| pixels.checked_mul(2 * 4)),
+            ])
+            .try_fold(reader.position, |length, bytes| length.checked_add(bytes?));
+        if expected_length != Some(bytes.len()) {
+            return Err(RendererError::InvalidEnvironmentData(
+                "the length of the data does not match the texture sizes".to_owned(),
+            ));
+        }
+
+        let mut irradiance_map = new_environment_cube_map(context, irradiance_size, format);
+        let size = irradiance_size as usize;
+        let sides = reader.read_sides(4 * size * size, format)?;
+        fill_sides(&mut irradiance_map, 0, &sides, format);
+
+        let mut prefilter_map = new_environment_cube_map(context, prefilter_size, format);
+        for mip in 0..PREFILTER_MIP_LEVELS {
+            let size = (prefilter_size >> mip).max(1) as usize;
+            let sides = reader.read_sides(4 * size * size, format)?;
+            fill_sides(&mut prefilter_map, mip, &sides, format);
+        }
+
+        let mut brdf_map = new_brdf_map(context, brdf_width, brdf_height);
+        let brdf = reader.read_values(
+            2 * brdf_width as usize * brdf_height as usize,
+            EnvironmentFormat::F32,
+        )?;
+        brdf_map.fill(&brdf.chunks(2).map(|p| [p[0], p[1]]).collect::<Vec<_>>());
+
+        Ok(Self {
+            irradiance_map,
+            prefilter_map,
+            brdf_map,
+        })
+    }
+
+    ///
+    /// Saves the environment to the file at the given path, see [Environment::to_bytes].
+    ///
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), RendererError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|e| RendererError::EnvironmentIo(path.to_path_buf(), e))
+    }
+
+    ///
+    /// Loads an environment from the file at the given path which was saved using [Environment::save], see [Environment::from_bytes].
+    ///
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(
+        context: &Context,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, RendererError> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).map_err(|e| RendererError::EnvironmentIo(path.to_path_buf(), e))?;
+        Self::from_bytes(context, &bytes)
+    }
+}
+
+fn new_environment_cube_map(
+    context: &Context,
+    size: u32,
+    format: EnvironmentFormat,
+) -> TextureCubeMap {
+    match format {
+        EnvironmentFormat::F16 => TextureCubeMap::new_empty::<[f16; 4]>(
+            context,
+            size,
+            size,
+            Interpolation::Linear,
+            Interpolation::Linear,
+            Some(Interpolation::Linear),
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        ),
+        EnvironmentFormat::F32 => TextureCubeMap::new_empty::<[f32; 4]>(
+            context,
+            size,
+            size,
+            Interpolation::Linear,
+            Interpolation::Linear,
+            Some(Interpolation::Linear),
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        ),
+    }
+}
+
+fn new_brdf_map(context: &Context, width: u32, height: u32) -> Texture2D {
+    Texture2D::new_empty::<[f32; 2]>(
+        context,
+        width,
+        height,
+        Interpolation::Linear,
+        Interpolation::Linear,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
+
+fn apply_to_sides<M: Material>(
+    texture: &mut TextureCubeMap,
+    mip_level: Option<u32>,
+    material: impl Fn(CubeMapSide) -> M,
+) {
+    for side in CubeMapSide::iter() {
+        let sides = [side];
+        let color_target = texture.as_color_target(&sides, mip_level);
+        let viewport = Viewport::new_at_origo(color_target.width(), color_target.height());
+        color_target
+            .clear(ClearState::default())
+            .apply_screen_material(&material(side), &Camera::new_2d(viewport), &[]);
+    }
+}
+
+fn read_side(texture: &TextureCubeMap, side: CubeMapSide, mip_level: u32) -> Vec<f32> {
+    let cpu_texture = texture.read_cpu_texture::<[f32; 4]>(side, mip_level);
+    let data = match cpu_texture.data {
+        TextureData::RgbaF32(data) => data,
+        _ => unreachable!(),
+    };
+    // The rows are returned in the opposite order of the order expected by TextureCubeMap::fill_mip_level
+    data.chunks(cpu_texture.width as usize)
+        .rev()
+        .flatten()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn fill_sides(
+    texture: &mut TextureCubeMap,
+    mip_level: u32,
+    sides: &[Vec<f32>],
+    format: EnvironmentFormat,
+) {
+    match format {
+        EnvironmentFormat::F16 => {
+            let sides = sides
+                .iter()
+                .map(|values| {
+                    values
+                        .chunks(4)
+                        .map(|p| p.iter().map(|v| f16::from_f32(*v)).collect::<Vec<_>>())
+                        .map(|p| [p[0], p[1], p[2], p[3]])
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            texture.fill_mip_level(
+                mip_level, &sides[0], &sides[1], &sides[2], &sides[3], &sides[4], &sides[5],
+            );
+        }
+        EnvironmentFormat::F32 => {
+            let sides = sides
+                .iter()
+                .map(|values| {
+                    values
+                        .chunks(4)
+                        .map(|p| [p[0], p[1], p[2], p[3]])
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            texture.fill_mip_level(
+                mip_level, &sides[0], &sides[1], &sides[2], &sides[3], &sides[4], &sides[5],
+            );
+        }
+    }
+}
+
+struct EnvironmentReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> EnvironmentReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], RendererError> {
+        let end = self.position.checked_add(count).ok_or_else(|| {
+            RendererError::InvalidEnvironmentData("unexpected end of data".to_owned())
+        })?;
+        let bytes = self.bytes.get(self.position..end).ok_or_else(|| {
+            RendererError::InvalidEnvironmentData("unexpected end of data".to_owned())
+        })?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, RendererError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_values(
+        &mut self,
+        count: usize,
+        format: EnvironmentFormat,
+    ) -> Result<Vec<f32>, RendererError> {
+        Ok(match format {
+            EnvironmentFormat::F16 => self
+                .take(2 * count)?
+                .chunks(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            EnvironmentFormat::F32 => self
+                .take(4 * count)?
+                .chunks(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        })
+    }
+
+    fn read_sides(
+        &mut self,
+        count: usize,
+        format: EnvironmentFormat,
+    ) -> Result<Vec<Vec<f32>>, RendererError> {
+        (0..6).map(|_| self.read_values(count, format)).collect()
+    }
+}
+
+///
+/// Projects the environment map onto L2 spherical harmonics convolved with the cosine lobe, see [IrradianceVolume::coefficients].
+///
+fn environment_spherical_harmonics(environment_map: &TextureCubeMap) -> [Vec3; 9] {
+    // An equirectangular image of this size is enough to capture the low frequencies represented by the spherical harmonics
+    let width = 128;
+    let texture = environment_map.to_equirectangular::<[f32; 4]>(width);
+    let height = texture.height;
+    let pixels = match texture.data {
+        TextureData::RgbaF32(data) => data,
+        _ => unreachable!(),
+    };
+    let samples = (0..height).flat_map(|y| {
+        let pixels = &pixels;
+        (0..width).map(move |x| {
+            let phi = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * std::f32::consts::PI;
+            let theta = (0.5 - (y as f32 + 0.5) / height as f32) * std::f32::consts::PI;
+            let pixel = pixels[(y * width + x) as usize];
+            (
+                vec3(
+                    theta.cos() * phi.cos(),
+                    theta.sin(),
+                    theta.cos() * phi.sin(),
+                ),
+                vec3(pixel[0], pixel[1], pixel[2]),
+                // The solid angle of the pixel is proportional to the cosine of the latitude
+                theta.cos(),
+            )
+        })
+    });
+    project_onto_spherical_harmonics(samples)
 }
 
 struct PrefilterMaterial<'a> {
@@ -142,6 +569,7 @@ struct PrefilterMaterial<'a> {
     side: CubeMapSide,
     mip: u32,
     max_mip_levels: u32,
+    sample_count: u32,
 }
 
 impl Material for PrefilterMaterial<'_> {
@@ -175,6 +603,7 @@ impl Material for PrefilterMaterial<'_> {
         program.use_uniform("resolution", self.environment_map.width() as f32);
         program.use_uniform("direction", self.side.direction());
         program.use_uniform("up", self.side.up());
+        program.use_uniform("sampleCount", self.sample_count.max(1));
     }
 
     fn render_states(&self) -> RenderStates {
@@ -188,6 +617,7 @@ impl Material for PrefilterMaterial<'_> {
 
 struct BrdfMaterial {
     lighting_model: LightingModel,
+    sample_count: u32,
 }
 
 impl Material for BrdfMaterial {
@@ -212,7 +642,9 @@ impl Material for BrdfMaterial {
         }
     }
 
-    fn use_uniforms(&self, _program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {}
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform("sampleCount", self.sample_count.max(1));
+    }
 
     fn render_states(&self) -> RenderStates {
         RenderStates::default()
@@ -226,6 +658,7 @@ impl Material for BrdfMaterial {
 struct IrradianceMaterial<'a> {
     environment_map: &'a TextureCubeMap,
     side: CubeMapSide,
+    sample_delta: f32,
 }
 
 impl Material for IrradianceMaterial<'_> {
@@ -252,6 +685,43 @@ impl Material for IrradianceMaterial<'_> {
         program.use_texture_cube("environmentMap", self.environment_map);
         program.use_uniform("direction", self.side.direction());
         program.use_uniform("up", self.side.up());
+        program.use_uniform("sampleDelta", self.sample_delta);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates::default()
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+struct SphericalHarmonicsMaterial {
+    coefficients: [Vec3; 9],
+    side: CubeMapSide,
+}
+
+impl Material for SphericalHarmonicsMaterial {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        include_str!("shaders/spherical_harmonics_irradiance.frag").to_owned()
+    }
+
+    fn id(&self) -> u16 {
+        0b1u16 << 15 | 0b1011u16
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            uv: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform_array("coefficients", &self.coefficients);
+        program.use_uniform("direction", self.side.direction());
+        program.use_uniform("up", self.side.up());
     }
 
     fn render_states(&self) -> RenderStates {
@@ -0,0 +1,322 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// A light which shines on all surfaces like an [AmbientLight] but where the light varies with the position of the surface,
+/// which for example gives dynamic objects moving through a scene the lighting of the part of the scene they are in.
+///
+/// The light is stored in a regular 3D grid of light probes covering an axis aligned bounding box, where each probe stores the light arriving at
+/// the position of the probe as L2 spherical harmonics, ie. 9 coefficients per color channel. The probes are stored in a [Texture3D] and are
+/// interpolated trilinearly between the probes. Surfaces outside the bounding box use the light from the nearest probes.
+///
+/// The volume does not contain any light before the probes are computed using [IrradianceVolume::capture] or set using [IrradianceVolume::set_coefficients].
+///
+/// The irradiance volume is a [Light] which is added to the other lights given to the render call, so it is not sampled by the materials themselves.
+/// It replaces an [AmbientLight] in the parts of the scene that it covers, so it should in most cases not be used together with an ambient light.
+///
+pub struct IrradianceVolume {
+    /// The intensity of the light. This allows for higher intensity than 1 which can be used to simulate high intensity light sources like the sun.
+    pub intensity: f32,
+    /// The base color of the light.
+    pub color: Srgba,
+    /// The size in pixels of each side of the cube maps used when capturing the scene at the position of each probe.
+    /// The default is 32.
+    pub capture_resolution: u32,
+    context: Context,
+    aabb: AxisAlignedBoundingBox,
+    probe_count: [u32; 3],
+    coefficients: Vec<[Vec3; 9]>,
+    texture: Texture3D,
+}
+
+impl IrradianceVolume {
+    ///
+    /// Creates a new irradiance volume with the given number of probes in each direction evenly distributed inside the given bounding box,
+    /// such that the probes in the corners of the grid are placed in the corners of the bounding box.
+    ///
+    /// # Panic
+    /// The 9 spherical harmonics coefficients of the probes are stored after each other along the z-axis of a 3D texture,
+    /// so this will panic if 9 times the number of probes in the z direction, or the number of probes in the x or y direction,
+    /// exceeds the maximum 3D texture size supported by the graphics driver, which is at least 256.
+    ///
+    pub fn new(context: &Context, aabb: AxisAlignedBoundingBox, probe_count: [u32; 3]) -> Self {
+        let probe_count = probe_count.map(|c| c.max(1));
+        #[allow(unsafe_code)]
+        let max_texture_size =
+            unsafe { context.get_parameter_i32(crate::context::MAX_3D_TEXTURE_SIZE) } as u32;
+        assert!(
+            probe_count[0] <= max_texture_size
+                && probe_count[1] <= max_texture_size
+                && 9 * probe_count[2] <= max_texture_size,
+            "the number of probes {:?} exceeds the maximum 3D texture size {} (9 times the number of probes in the z direction)",
+            probe_count,
+            max_texture_size
+        );
+        let texture = Texture3D::new_empty::<[f16; 4]>(
+            context,
+            probe_count[0],
+            probe_count[1],
+            9 * probe_count[2],
+            Interpolation::Linear,
+            Interpolation::Linear,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let mut volume = Self {
+            intensity: 1.0,
+            color: Srgba::WHITE,
+            capture_resolution: 32,
+            context: context.clone(),
+            aabb,
+            probe_count,
+            coefficients: Vec::new(),
+            texture,
+        };
+        volume.set_coefficients(vec![[Vec3::zero(); 9]; volume.probe_positions().len()]);
+        volume
+    }
+
+    ///
+    /// Returns the bounding box covered by the probes.
+    ///
+    pub fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.aabb
+    }
+
+    ///
+    /// Returns the number of probes in each direction.
+    ///
+    pub fn probe_count(&self) -> [u32; 3] {
+        self.probe_count
+    }
+
+    ///
+    /// Returns the positions of the probes ordered by x first, then y and then z.
+    ///
+    pub fn probe_positions(&self) -> Vec<Vec3> {
+        let [nx, ny, nz] = self.probe_count;
+        let coordinate = |i: u32, n: u32| {
+            if n > 1 {
+                i as f32 / (n - 1) as f32
+            } else {
+                0.5
+            }
+        };
+        let (min, size) = (self.aabb.min(), self.aabb.size());
+        let mut positions = Vec::with_capacity((nx * ny * nz) as usize);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    positions.push(
+                        min + vec3(
+                            coordinate(x, nx) * size.x,
+                            coordinate(y, ny) * size.y,
+                            coordinate(z, nz) * size.z,
+                        ),
+                    );
+                }
+            }
+        }
+        positions
+    }
+
+    ///
+    /// Returns the spherical harmonics coefficients of the irradiance of each probe in the same order as [IrradianceVolume::probe_positions].
+    /// The coefficients are ordered as `L00, L1-1, L10, L11, L2-2, L2-1, L20, L21, L22` and are already convolved with the cosine lobe,
+    /// so evaluating the spherical harmonics in a direction gives the irradiance divided by π, in the same way as the irradiance map of an [Environment].
+    ///
+    pub fn coefficients(&self) -> &[[Vec3; 9]] {
+        &self.coefficients
+    }
+
+    ///
+    /// Sets the spherical harmonics coefficients of each probe, see [IrradianceVolume::coefficients], for example to use coefficients that were computed earlier.
+    ///
+    /// # Panic
+    /// Will panic if the number of coefficients does not match the number of probes.
+    ///
+    pub fn set_coefficients(&mut self, coefficients: Vec<[Vec3; 9]>) {
+        let probe_count =
+            (self.probe_count[0] * self.probe_count[1] * self.probe_count[2]) as usize;
+        assert_eq!(
+            coefficients.len(),
+            probe_count,
+            "the number of coefficients must match the number of probes"
+        );
+        // The coefficients are stored in 9 blocks along the z-axis of the texture, one for each coefficient
+        let mut data = Vec::with_capacity(9 * probe_count);
+        for k in 0..9 {
+            data.extend(coefficients.iter().map(|c| {
+                [
+                    f16::from_f32(c[k].x),
+                    f16::from_f32(c[k].y),
+                    f16::from_f32(c[k].z),
+                    f16::ONE,
+                ]
+            }));
+        }
+        self.texture.fill(&data);
+        self.coefficients = coefficients;
+    }
+
+    ///
+    /// Computes the spherical harmonics coefficients of all probes by capturing the given objects lit by the given lights from the position of each probe.
+    /// Use a [Skybox] as one of the objects to include the light from the sky.
+    ///
+    pub fn capture(
+        &mut self,
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        lights: &[&dyn Light],
+    ) {
+        let mut capture = CubeMapCapture::new(&self.context, self.capture_resolution);
+        let coefficients = self
+            .probe_positions()
+            .into_iter()
+            .map(|position| {
+                capture.render(
+                    position,
+                    ClearState::color_and_depth(0.0, 0.0, 0.0, 1.0, 1.0),
+                    objects.clone(),
+                    lights,
+                );
+                project_capture_onto_spherical_harmonics(&mut capture)
+            })
+            .collect();
+        self.set_coefficients(coefficients);
+    }
+}
+
+///
+/// Projects the radiance in the captured cube map onto L2 spherical harmonics and convolves the result with the cosine lobe.
+///
+fn project_capture_onto_spherical_harmonics(capture: &mut CubeMapCapture) -> [Vec3; 9] {
+    let size = capture.texture().width();
+    let mut samples = Vec::with_capacity(6 * (size * size) as usize);
+    for side in CubeMapSide::iter() {
+        let direction = side.direction();
+        let up = side.up();
+        let right = direction.cross(up);
+        let pixels = capture.read_side::<[f32; 4]>(side);
+        for y in 0..size {
+            for x in 0..size {
+                let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+                let v = 1.0 - 2.0 * (y as f32 + 0.5) / size as f32;
+                let pixel = pixels[(y * size + x) as usize];
+                samples.push((
+                    direction + right * u + up * v,
+                    vec3(pixel[0], pixel[1], pixel[2]),
+                    // The solid angle of the pixel
+                    1.0 / (1.0 + u * u + v * v).powf(1.5),
+                ));
+            }
+        }
+    }
+    project_onto_spherical_harmonics(samples)
+}
+
+///
+/// Projects the radiance samples, each given as a direction, a radiance and a weight proportional to the solid angle covered by the sample,
+/// onto L2 spherical harmonics and convolves the result with the cosine lobe.
+/// The samples must cover the entire sphere.
+///
+pub(super) fn project_onto_spherical_harmonics(
+    samples: impl IntoIterator<Item = (Vec3, Vec3, f32)>,
+) -> [Vec3; 9] {
+    let mut coefficients = [Vec3::zero(); 9];
+    let mut total_weight = 0.0;
+    for (direction, radiance, weight) in samples {
+        let color = radiance * weight;
+        for (c, basis) in coefficients
+            .iter_mut()
+            .zip(spherical_harmonics_basis(direction.normalize()))
+        {
+            *c += color * basis;
+        }
+        total_weight += weight;
+    }
+    // Normalize such that the weights sum to the solid angle of the sphere and convolve with the cosine lobe divided by π
+    let scale = 4.0 * std::f32::consts::PI / total_weight;
+    for (k, c) in coefficients.iter_mut().enumerate() {
+        let band = match k {
+            0 => 1.0,
+            1..=3 => 2.0 / 3.0,
+            _ => 0.25,
+        };
+        *c *= scale * band;
+    }
+    coefficients
+}
+
+fn spherical_harmonics_basis(n: Vec3) -> [f32; 9] {
+    [
+        0.282095,
+        0.488603 * n.y,
+        0.488603 * n.z,
+        0.488603 * n.x,
+        1.092548 * n.x * n.y,
+        1.092548 * n.y * n.z,
+        0.315392 * (3.0 * n.z * n.z - 1.0),
+        1.092548 * n.x * n.z,
+        0.546274 * (n.x * n.x - n.y * n.y),
+    ]
+}
+
+impl Light for IrradianceVolume {
+    fn shader_source(&self, i: u32) -> String {
+        format!(
+            "
+            uniform sampler3D irradianceVolume{i};
+            uniform vec3 irradianceVolumeMin{i};
+            uniform vec3 irradianceVolumeSize{i};
+            uniform vec3 irradianceVolumeProbeCount{i};
+            uniform vec3 irradianceVolumeColor{i};
+
+            vec3 calculate_lighting{i}(vec3 surface_color, vec3 position, vec3 normal, vec3 view_direction, float metallic, float roughness, float occlusion)
+            {{
+                vec3 probe_count = irradianceVolumeProbeCount{i};
+                vec3 local = clamp((position - irradianceVolumeMin{i}) / max(irradianceVolumeSize{i}, vec3(0.0001)), 0.0, 1.0) * (probe_count - 1.0) + 0.5;
+                vec2 uv = local.xy / probe_count.xy;
+                vec3 c[9];
+                for (int k = 0; k < 9; k++) {{
+                    c[k] = texture(irradianceVolume{i}, vec3(uv, (float(k) * probe_count.z + local.z) / (9.0 * probe_count.z))).rgb;
+                }}
+                vec3 n = normal;
+                vec3 irradiance = c[0] * 0.282095
+                    + c[1] * 0.488603 * n.y
+                    + c[2] * 0.488603 * n.z
+                    + c[3] * 0.488603 * n.x
+                    + c[4] * 1.092548 * n.x * n.y
+                    + c[5] * 1.092548 * n.y * n.z
+                    + c[6] * 0.315392 * (3.0 * n.z * n.z - 1.0)
+                    + c[7] * 1.092548 * n.x * n.z
+                    + c[8] * 0.546274 * (n.x * n.x - n.y * n.y);
+                return occlusion * irradianceVolumeColor{i} * mix(surface_color, vec3(0.0), metallic) * max(irradiance, vec3(0.0));
+            }}
+            "
+        )
+    }
+
+    fn use_uniforms(&self, program: &Program, i: u32) {
+        program.use_texture_3d(&format!("irradianceVolume{}", i), &self.texture);
+        program.use_uniform(&format!("irradianceVolumeMin{}", i), self.aabb.min());
+        program.use_uniform(&format!("irradianceVolumeSize{}", i), self.aabb.size());
+        program.use_uniform(
+            &format!("irradianceVolumeProbeCount{}", i),
+            vec3(
+                self.probe_count[0] as f32,
+                self.probe_count[1] as f32,
+                self.probe_count[2] as f32,
+            ),
+        );
+        program.use_uniform(
+            &format!("irradianceVolumeColor{}", i),
+            self.color.to_linear_srgb().truncate() * self.intensity,
+        );
+    }
+
+    fn id(&self) -> u8 {
+        0b1u8 << 7 | 0b1000u8
+    }
+}
@@ -0,0 +1,361 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// The volume in which a [ReflectionProbe] affects the lighting.
+///
+#[derive(Clone, Copy, Debug)]
+pub enum ReflectionProbeVolume {
+    /// An axis aligned box in world space, which for example matches the walls of a room.
+    Box(AxisAlignedBoundingBox),
+    /// A sphere with the given radius centered at the position of the probe.
+    Sphere(f32),
+}
+
+///
+/// A local light probe which captures the scene from its position into prefiltered cube maps that are used for ambient lighting and reflections of surfaces inside its volume.
+/// Use it together with [ReflectionProbes], which blends the probes based on the position of the surface.
+///
+/// The probe does not contain any lighting before it has been captured using [ReflectionProbe::capture],
+/// which can be done once for static scenes or whenever the scene changes for dynamic scenes.
+///
+pub struct ReflectionProbe {
+    /// The position from which the scene is captured.
+    pub position: Vec3,
+    /// The volume in which the probe affects the lighting.
+    pub volume: ReflectionProbeVolume,
+    /// The distance from the border of the volume over which the probe fades out, which makes the transition to neighbouring probes smooth.
+    pub blend_distance: f32,
+    /// Whether or not to correct the reflection direction by intersecting it with the volume, which gives more accurate reflections of the walls of a room.
+    /// This assumes that the captured scene is located at the border of the volume.
+    pub parallax_correction: bool,
+    context: Context,
+    capture: CubeMapCapture,
+    environment: Option<Environment>,
+}
+
+impl ReflectionProbe {
+    ///
+    /// Creates a new reflection probe at the given position which affects the given volume.
+    /// The scene is captured into cube maps where each side has the given size in pixels.
+    ///
+    pub fn new(
+        context: &Context,
+        position: Vec3,
+        volume: ReflectionProbeVolume,
+        resolution: u32,
+    ) -> Self {
+        Self {
+            position,
+            volume,
+            blend_distance: 0.5,
+            parallax_correction: true,
+            context: context.clone(),
+            capture: CubeMapCapture::new(context, resolution),
+            environment: None,
+        }
+    }
+
+    ///
+    /// Captures the given objects lit by the given lights as seen from the position of the probe and computes the prefiltered cube maps used for lighting.
+    /// Use a [Skybox] as one of the objects to include the sky in the reflections.
+    ///
+    pub fn capture(
+        &mut self,
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        lights: &[&dyn Light],
+    ) {
+        let environment_map = self.capture.render(
+            self.position,
+            ClearState::color_and_depth(0.0, 0.0, 0.0, 1.0, 1.0),
+            objects,
+            lights,
+        );
+        self.environment = Some(Environment::new(&self.context, environment_map));
+    }
+
+    ///
+    /// Returns the prefiltered cube maps computed by the last call to [ReflectionProbe::capture], if any.
+    ///
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    ///
+    /// Returns how much the probe affects the lighting at the given position, which is 1 inside the volume and fades to 0 at the border of the volume.
+    ///
+    pub fn weight(&self, position: Vec3) -> f32 {
+        let distance = match self.volume {
+            ReflectionProbeVolume::Box(aabb) => {
+                let inside = (position - aabb.min()).zip(aabb.max() - position, f32::min);
+                inside.x.min(inside.y).min(inside.z)
+            }
+            ReflectionProbeVolume::Sphere(radius) => radius - position.distance(self.position),
+        };
+        if self.blend_distance > 0.0 {
+            (distance / self.blend_distance).clamp(0.0, 1.0)
+        } else if distance >= 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+///
+/// A light which shines on all surfaces like an [AmbientLight] but where the light is calculated from the [ReflectionProbe]s which contain the surface.
+/// The probes are blended based on the position of the surface, so for example a room with its own probe reflects the room instead of the sky outside.
+/// Where the probes do not cover the surface fully, the remaining light comes from the fallback environment if specified and otherwise from a uniform ambient light.
+///
+/// At most [ReflectionProbes::MAX_PROBES] probes which have been captured are used, any additional probes are ignored.
+///
+pub struct ReflectionProbes {
+    /// The intensity of the light. This allows for higher intensity than 1 which can be used to simulate high intensity light sources like the sun.
+    pub intensity: f32,
+    /// The base color of the light.
+    pub color: Srgba,
+    /// The reflection probes.
+    pub probes: Vec<ReflectionProbe>,
+    /// The light shining from the environment where the probes do not cover the surface.
+    pub fallback: Option<Environment>,
+}
+
+impl ReflectionProbes {
+    ///
+    /// The maximum number of probes used in the lighting calculations.
+    ///
+    pub const MAX_PROBES: usize = 4;
+
+    ///
+    /// Constructs reflection probes lighting with a uniform ambient light where the probes do not cover the surface.
+    ///
+    pub fn new(
+        _context: &Context,
+        intensity: f32,
+        color: Srgba,
+        probes: Vec<ReflectionProbe>,
+    ) -> Self {
+        Self {
+            intensity,
+            color,
+            probes,
+            fallback: None,
+        }
+    }
+
+    ///
+    /// Constructs reflection probes lighting with light calculated from the given environment map where the probes do not cover the surface.
+    ///
+    pub fn new_with_environment(
+        context: &Context,
+        intensity: f32,
+        color: Srgba,
+        probes: Vec<ReflectionProbe>,
+        environment_map: &TextureCubeMap,
+    ) -> Self {
+        Self {
+            intensity,
+            color,
+            probes,
+            fallback: Some(Environment::new(context, environment_map)),
+        }
+    }
+
+    fn captured_probes(&self) -> impl Iterator<Item = (&ReflectionProbe, &Environment)> {
+        self.probes
+            .iter()
+            .filter_map(|probe| probe.environment().map(|e| (probe, e)))
+            .take(Self::MAX_PROBES)
+    }
+}
+
+impl Light for ReflectionProbes {
+    fn shader_source(&self, i: u32) -> String {
+        let probe_count = self.captured_probes().count();
+        let mut source = format!(
+            "
+            uniform vec3 reflectionProbeColor{i};
+
+            float reflection_probe_weight{i}(vec3 position, vec3 center, vec3 box_min, vec3 box_max, float radius, float blend)
+            {{
+                vec3 inside = min(position - box_min, box_max - position);
+                float d = radius > 0.0 ? radius - distance(position, center) : min(min(inside.x, inside.y), inside.z);
+                return blend > 0.0 ? clamp(d / blend, 0.0, 1.0) : step(0.0, d);
+            }}
+
+            vec3 reflection_probe_direction{i}(vec3 position, vec3 R, vec3 center, vec3 box_min, vec3 box_max, float radius, float parallax)
+            {{
+                if (parallax < 0.5) {{
+                    return R;
+                }}
+                float t;
+                if (radius > 0.0) {{
+                    vec3 offset = position - center;
+                    float b = dot(offset, R);
+                    t = -b + sqrt(max(b * b - dot(offset, offset) + radius * radius, 0.0));
+                }} else {{
+                    vec3 t_max = max((box_max - position) / R, (box_min - position) / R);
+                    t = min(min(t_max.x, t_max.y), t_max.z);
+                }}
+                return position + R * max(t, 0.0) - center;
+            }}
+            "
+        );
+        if probe_count > 0 || self.fallback.is_some() {
+            source.push_str(&format!("uniform sampler2D reflectionProbeBrdf{i};\n"));
+        }
+        if self.fallback.is_some() {
+            source.push_str(&format!(
+                "
+                uniform samplerCube reflectionProbeFallbackIrradiance{i};
+                uniform samplerCube reflectionProbeFallbackPrefilter{i};
+                "
+            ));
+        }
+        let mut blend = String::new();
+        for p in 0..probe_count {
+            source.push_str(&format!(
+                "
+                uniform samplerCube reflectionProbeIrradiance{i}_{p};
+                uniform samplerCube reflectionProbePrefilter{i}_{p};
+                uniform vec3 reflectionProbePosition{i}_{p};
+                uniform vec3 reflectionProbeMin{i}_{p};
+                uniform vec3 reflectionProbeMax{i}_{p};
+                uniform float reflectionProbeRadius{i}_{p};
+                uniform float reflectionProbeBlend{i}_{p};
+                uniform float reflectionProbeParallax{i}_{p};
+                "
+            ));
+            blend.push_str(&format!(
+                "
+                    w = reflection_probe_weight{i}(position, reflectionProbePosition{i}_{p}, reflectionProbeMin{i}_{p}, reflectionProbeMax{i}_{p}, reflectionProbeRadius{i}_{p}, reflectionProbeBlend{i}_{p});
+                    if (w > 0.0) {{
+                        vec3 dir = reflection_probe_direction{i}(position, R, reflectionProbePosition{i}_{p}, reflectionProbeMin{i}_{p}, reflectionProbeMax{i}_{p}, reflectionProbeRadius{i}_{p}, reflectionProbeParallax{i}_{p});
+                        irradiance += w * textureLod(reflectionProbeIrradiance{i}_{p}, N, 0.0).rgb;
+                        prefilteredColor += w * textureLod(reflectionProbePrefilter{i}_{p}, dir, roughness * MAX_REFLECTION_LOD).rgb;
+                        total_weight += w;
+                    }}
+                "
+            ));
+        }
+        let remaining = if self.fallback.is_some() {
+            format!(
+                "
+                    irradiance += remaining_weight * textureLod(reflectionProbeFallbackIrradiance{i}, N, 0.0).rgb;
+                    prefilteredColor += remaining_weight * textureLod(reflectionProbeFallbackPrefilter{i}, R, roughness * MAX_REFLECTION_LOD).rgb;
+                    vec3 uniform_ambient = vec3(0.0);
+                "
+            )
+        } else {
+            "vec3 uniform_ambient = remaining_weight * mix(surface_color, vec3(0.0), metallic);"
+                .to_owned()
+        };
+        let image_based = if probe_count > 0 || self.fallback.is_some() {
+            format!(
+                "
+                    vec3 F0 = mix(vec3(0.04), surface_color, metallic);
+                    vec3 specular_fresnel = fresnel_schlick_roughness(F0, NdV, roughness);
+                    vec3 diffuse_fresnel = 1.0 - specular_fresnel;
+                    vec3 diffuse = diffuse_fresnel * mix(surface_color, vec3(0.0), metallic) * irradiance;
+                    vec2 brdf = texture(reflectionProbeBrdf{i}, vec2(NdV, roughness)).rg;
+                    vec3 specular = prefilteredColor * (specular_fresnel * brdf.x + brdf.y);
+                    vec3 image_based = diffuse + specular;
+                "
+            )
+        } else {
+            "vec3 image_based = vec3(0.0);".to_owned()
+        };
+        source.push_str(&format!(
+            "
+            vec3 calculate_lighting{i}(vec3 surface_color, vec3 position, vec3 normal, vec3 view_direction, float metallic, float roughness, float occlusion)
+            {{
+                vec3 N = normal;
+                vec3 V = view_direction;
+                vec3 R = reflect(-V, N);
+                float NdV = max(0.001, dot(N, V));
+                const float MAX_REFLECTION_LOD = 4.0;
+
+                vec3 irradiance = vec3(0.0);
+                vec3 prefilteredColor = vec3(0.0);
+                float total_weight = 0.0;
+                float w;
+                {blend}
+                if (total_weight > 1.0) {{
+                    irradiance /= total_weight;
+                    prefilteredColor /= total_weight;
+                    total_weight = 1.0;
+                }}
+                float remaining_weight = 1.0 - total_weight;
+                {remaining}
+                {image_based}
+                return (image_based + uniform_ambient) * occlusion * reflectionProbeColor{i};
+            }}
+            "
+        ));
+        source
+    }
+
+    fn use_uniforms(&self, program: &Program, i: u32) {
+        program.use_uniform(
+            &format!("reflectionProbeColor{}", i),
+            self.color.to_linear_srgb().truncate() * self.intensity,
+        );
+        let mut brdf_map = None;
+        if let Some(ref fallback) = self.fallback {
+            program.use_texture_cube(
+                &format!("reflectionProbeFallbackIrradiance{}", i),
+                &fallback.irradiance_map,
+            );
+            program.use_texture_cube(
+                &format!("reflectionProbeFallbackPrefilter{}", i),
+                &fallback.prefilter_map,
+            );
+            brdf_map = Some(&fallback.brdf_map);
+        }
+        for (p, (probe, environment)) in self.captured_probes().enumerate() {
+            program.use_texture_cube(
+                &format!("reflectionProbeIrradiance{}_{}", i, p),
+                &environment.irradiance_map,
+            );
+            program.use_texture_cube(
+                &format!("reflectionProbePrefilter{}_{}", i, p),
+                &environment.prefilter_map,
+            );
+            let (min, max, radius) = match probe.volume {
+                ReflectionProbeVolume::Box(aabb) => (aabb.min(), aabb.max(), 0.0),
+                ReflectionProbeVolume::Sphere(radius) => (probe.position, probe.position, radius),
+            };
+            program.use_uniform(
+                &format!("reflectionProbePosition{}_{}", i, p),
+                probe.position,
+            );
+            program.use_uniform(&format!("reflectionProbeMin{}_{}", i, p), min);
+            program.use_uniform(&format!("reflectionProbeMax{}_{}", i, p), max);
+            program.use_uniform(&format!("reflectionProbeRadius{}_{}", i, p), radius);
+            program.use_uniform(
+                &format!("reflectionProbeBlend{}_{}", i, p),
+                probe.blend_distance,
+            );
+            program.use_uniform(
+                &format!("reflectionProbeParallax{}_{}", i, p),
+                if probe.parallax_correction {
+                    1.0f32
+                } else {
+                    0.0
+                },
+            );
+            brdf_map.get_or_insert(&environment.brdf_map);
+        }
+        if let Some(brdf_map) = brdf_map {
+            program.use_texture(&format!("reflectionProbeBrdf{}", i), brdf_map);
+        }
+    }
+
+    fn id(&self) -> u8 {
+        // The shader source depends on the number of captured probes and whether or not there is a fallback environment
+        0b1u8 << 7
+            | 0b1u8 << 4
+            | (self.fallback.is_some() as u8) << 3
+            | self.captured_probes().count() as u8
+    }
+}
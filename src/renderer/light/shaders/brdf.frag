@@ -2,6 +2,8 @@ in vec2 uvs;
 
 out vec2 FragColor;
 
+uniform uint sampleCount;
+
 vec2 IntegrateBRDF(float NdotV, float roughness)
 {
     vec3 V;
@@ -14,7 +16,7 @@ vec2 IntegrateBRDF(float NdotV, float roughness)
 
     vec3 N = vec3(0.0, 0.0, 1.0);
     
-    const uint SAMPLE_COUNT = 1024u;
+    uint SAMPLE_COUNT = sampleCount;
     for(uint i = 0u; i < SAMPLE_COUNT; ++i)
     {
         // generates a sample vector that's biased towards the
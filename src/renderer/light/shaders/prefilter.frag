@@ -6,6 +6,7 @@ uniform float roughness;
 uniform float resolution; // resolution of source cubemap (per face)
 uniform vec3 direction;
 uniform vec3 up;
+uniform uint sampleCount;
 
 void main()
 {
@@ -16,7 +17,7 @@ void main()
     vec3 R = N;
     vec3 V = R;
 
-    const uint SAMPLE_COUNT = 1024u;
+    uint SAMPLE_COUNT = sampleCount;
     vec3 prefilteredColor = vec3(0.0);
     float totalWeight = 0.0;
     
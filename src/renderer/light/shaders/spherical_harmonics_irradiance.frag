@@ -0,0 +1,27 @@
+
+uniform vec3 coefficients[9];
+uniform vec3 direction;
+uniform vec3 up;
+
+in vec2 uvs;
+
+layout (location = 0) out vec4 outColor;
+
+void main()
+{
+    vec3 right = cross(direction, up);
+    vec3 n = normalize(up * (uvs.y - 0.5) * 2.0 + right * (uvs.x - 0.5) * 2.0 + direction);
+
+    // The coefficients are already convolved with the cosine lobe, so evaluating them gives the irradiance divided by PI
+    vec3 irradiance = coefficients[0] * 0.282095
+        + coefficients[1] * 0.488603 * n.y
+        + coefficients[2] * 0.488603 * n.z
+        + coefficients[3] * 0.488603 * n.x
+        + coefficients[4] * 1.092548 * n.x * n.y
+        + coefficients[5] * 1.092548 * n.y * n.z
+        + coefficients[6] * 0.315392 * (3.0 * n.z * n.z - 1.0)
+        + coefficients[7] * 1.092548 * n.x * n.z
+        + coefficients[8] * 0.546274 * (n.x * n.x - n.y * n.y);
+
+    outColor = vec4(max(irradiance, vec3(0.0)), 1.0);
+}
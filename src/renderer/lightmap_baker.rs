@@ -0,0 +1,584 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// A static triangle mesh which is baked into a lightmap by a [LightmapBaker].
+///
+pub struct LightmapMesh<'a> {
+    /// The triangle mesh. If it does not contain normals, they are computed using [CpuMesh::compute_normals].
+    pub cpu_mesh: &'a CpuMesh,
+    /// The material of the mesh. Only the albedo and emissive properties are used, to find the light bouncing off the surface of the mesh and onto other surfaces.
+    pub cpu_material: &'a CpuMaterial,
+    /// The lightmap uv coordinates, one for each vertex in the mesh.
+    /// The coordinates must be in the range `[0, 1]`, where `(0, 0)` is the top left corner of the lightmap, and the triangles must not overlap in the lightmap.
+    /// Use the same coordinates with [Mesh::update_uvs2] when rendering the mesh with the baked lightmap.
+    pub lightmap_uvs: &'a [Vec2],
+    /// The local to world transformation of the mesh.
+    /// If the transformation cannot be inverted, for example if it has a zero scale, the mesh is not visible and its lightmap is black.
+    pub transformation: Mat4,
+}
+
+///
+/// Precomputes the global illumination of a static scene into lightmaps, which can be used as the [PhysicalMaterial::lightmap_texture]
+/// or [DeferredPhysicalMaterial::lightmap_texture] so that the static lights do not have to be evaluated every frame.
+/// The baking runs entirely on the GPU, so it works with a [HeadlessContext](crate::HeadlessContext) as well as a window context.
+///
+/// Each mesh is rasterized in lightmap uv space to find the world position and normal of each texel.
+/// The direct light at each texel is calculated using the given lights, which means that the light is shadowed if the shadow maps of the lights
+/// have been generated using the static geometry before baking, for example using [DirectionalLight::generate_shadow_map].
+/// Then the indirect light is gathered in a number of bounces by rendering the scene with the lightmaps of the previous bounce into a small cube map, a hemicube, at each texel.
+/// Finally, the indirect light is denoised and the lightmaps are dilated to avoid seams where the lightmap is sampled outside the triangles.
+///
+/// A texel stores the light arriving at the surface divided by PI, which is the color of a white, diffuse surface,
+/// so the lightmaps should be used with [LightmapEncoding::Linear] and without any lights in the scene that are already baked.
+/// Only the diffuse reflection of the [DirectionalLight], [PointLight] and [SpotLight] is baked, while an [AmbientLight] with an environment and [ReflectionProbes]
+/// also contribute with their specular reflection, so it is better to add them when rendering.
+///
+pub struct LightmapBaker {
+    /// The width and height in texels of each lightmap.
+    /// The default is 128.
+    pub lightmap_size: u32,
+    /// The number of times the light bounces off the surfaces. Use 0 to only bake direct light.
+    /// The default is 1.
+    pub bounces: u32,
+    /// The width and height in pixels of each side of the cube map which is rendered at each texel to gather indirect light.
+    /// The default is 16.
+    pub hemicube_size: u32,
+    /// The distance along the surface normal from a texel to the position where the indirect light is gathered, which avoids that a surface occludes itself.
+    /// The default is 0.01.
+    pub bias: f32,
+    /// Whether or not to smooth the indirect light with the neighbouring texels on the same surface.
+    /// The default is true.
+    pub denoise: bool,
+    /// The number of texels that the lightmaps are expanded with outside the triangles.
+    /// The default is 2.
+    pub dilation: u32,
+    context: Context,
+}
+
+impl LightmapBaker {
+    ///
+    /// Creates a new lightmap baker with default settings.
+    ///
+    pub fn new(context: &Context) -> Self {
+        Self {
+            lightmap_size: 128,
+            bounces: 1,
+            hemicube_size: 16,
+            bias: 0.01,
+            denoise: true,
+            dilation: 2,
+            context: context.clone(),
+        }
+    }
+
+    ///
+    /// Bakes the light from the given lights into a lightmap for each of the given meshes.
+    /// The lightmaps are returned in the same order as the meshes and contain linear RGB values, where the first texel is the top left texel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of lightmap uv coordinates of a mesh does not match the number of vertices in the mesh.
+    ///
+    pub fn bake(&self, meshes: &[LightmapMesh], lights: &[&dyn Light]) -> Vec<CpuTexture> {
+        let size = self.lightmap_size;
+        let geometry_program = Program::from_source(
+            &self.context,
+            "in vec3 position;
+            in vec3 normal;
+            in vec2 lightmap_uv;
+            out vec3 pos;
+            out vec3 nor;
+            void main()
+            {
+                pos = position;
+                nor = normal;
+                gl_Position = vec4(lightmap_uv * 2.0 - 1.0, 0.0, 1.0);
+            }",
+            "in vec3 pos;
+            in vec3 nor;
+            layout (location = 0) out vec4 outPosition;
+            layout (location = 1) out vec4 outNormal;
+            void main()
+            {
+                outPosition = vec4(pos, 1.0);
+                outNormal = vec4(normalize(nor), 1.0);
+            }",
+        )
+        .expect("Failed compiling shader");
+
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        let mut texels = Vec::new();
+        let mut objects = Vec::new();
+        let mut direct_light = Vec::new();
+        for mesh in meshes {
+            if mesh.lightmap_uvs.len() != mesh.cpu_mesh.vertex_count() {
+                panic!("Failed baking lightmap: The number of lightmap uv coordinates {} does not match the number of vertices {} in the mesh.", mesh.lightmap_uvs.len(), mesh.cpu_mesh.vertex_count())
+            }
+            let mut cpu_mesh = mesh.cpu_mesh.clone();
+            if cpu_mesh.normals.is_none() {
+                cpu_mesh.compute_normals();
+            }
+            let mut mesh_aabb = cpu_mesh.compute_aabb();
+            mesh_aabb.transform(&mesh.transformation);
+            aabb.expand_with_aabb(&mesh_aabb);
+
+            if let Some(mut geometry_texture) = self.rasterize(&geometry_program, &cpu_mesh, mesh) {
+                direct_light.push(self.direct_light(&geometry_texture, lights));
+                let positions = geometry_texture
+                    .as_color_target(&[0], None)
+                    .read::<[f32; 4]>();
+                let normals = geometry_texture
+                    .as_color_target(&[1], None)
+                    .read::<[f32; 4]>();
+                texels.push(
+                    positions
+                        .into_iter()
+                        .zip(normals)
+                        .map(|(p, n)| {
+                            (p[3] > 0.5).then(|| (vec3(p[0], p[1], p[2]), vec3(n[0], n[1], n[2])))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+            } else {
+                // The mesh is flattened by the transformation, so it is not visible and does not receive any light
+                direct_light.push(vec![[0.0; 4]; (size * size) as usize]);
+                texels.push(vec![None; (size * size) as usize]);
+            }
+
+            let mut gpu_mesh = Mesh::new(&self.context, &cpu_mesh);
+            gpu_mesh.set_transformation(mesh.transformation);
+            gpu_mesh.update_uvs2(mesh.lightmap_uvs);
+            let cpu_material = CpuMaterial {
+                normal_texture: None,
+                ..mesh.cpu_material.clone()
+            };
+            objects.push(Gm::new(
+                gpu_mesh,
+                PhysicalMaterial::new_opaque(&self.context, &cpu_material),
+            ));
+        }
+
+        let mut light = direct_light.clone();
+        if self.bounces > 0 {
+            let mut capture = CubeMapCapture::new(&self.context, self.hemicube_size);
+            capture.z_near = 0.5 * self.bias;
+            capture.z_far = 2.0 * aabb.size().magnitude() + self.bias;
+            for _ in 0..self.bounces {
+                for (object, light) in objects.iter_mut().zip(light.iter()) {
+                    let lightmap = lightmap_texture(&dilate(light, size, self.dilation), size);
+                    object.material.lightmap_texture =
+                        Some(Texture2DRef::from_cpu_texture(&self.context, &lightmap));
+                }
+                for ((light, direct_light), texels) in
+                    light.iter_mut().zip(direct_light.iter()).zip(texels.iter())
+                {
+                    let mut indirect_light =
+                        self.indirect_light(&mut capture, texels, objects.iter());
+                    if self.denoise {
+                        indirect_light = denoise(&indirect_light, texels, size);
+                    }
+                    for ((l, direct), indirect) in
+                        light.iter_mut().zip(direct_light).zip(indirect_light)
+                    {
+                        *l = [
+                            direct[0] + indirect[0],
+                            direct[1] + indirect[1],
+                            direct[2] + indirect[2],
+                            direct[3],
+                        ];
+                    }
+                }
+            }
+        }
+        light
+            .iter()
+            .map(|light| lightmap_texture(&dilate(light, size, self.dilation), size))
+            .collect()
+    }
+
+    ///
+    /// Rasterizes the mesh in lightmap uv space into a texture array with the world position in the first layer and the world normal in the second layer.
+    /// The alpha value is 1 for texels covered by the mesh and 0 otherwise.
+    /// Returns [None] if the transformation of the mesh cannot be inverted.
+    ///
+    fn rasterize(
+        &self,
+        program: &Program,
+        cpu_mesh: &CpuMesh,
+        mesh: &LightmapMesh,
+    ) -> Option<Texture2DArray> {
+        let normal_matrix = mesh.transformation.invert()?.transpose();
+        let vertex_positions = cpu_mesh.positions.to_f32();
+        let vertex_normals = cpu_mesh.normals.as_ref().unwrap();
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        cpu_mesh.for_each_triangle(|i0, i1, i2| {
+            for i in [i0, i1, i2] {
+                positions.push((mesh.transformation * vertex_positions[i].extend(1.0)).truncate());
+                normals.push((normal_matrix * vertex_normals[i].extend(0.0)).truncate());
+                // Flip v, since the first row of a lightmap is the top row while the first row of a texture is the bottom row
+                let uv = mesh.lightmap_uvs[i];
+                uvs.push(vec2(uv.x, 1.0 - uv.y));
+            }
+        });
+
+        let mut geometry_texture = Texture2DArray::new_empty::<[f32; 4]>(
+            &self.context,
+            self.lightmap_size,
+            self.lightmap_size,
+            2,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let position_buffer = VertexBuffer::new_with_data(&self.context, &positions);
+        let normal_buffer = VertexBuffer::new_with_data(&self.context, &normals);
+        let uv_buffer = VertexBuffer::new_with_data(&self.context, &uvs);
+        geometry_texture
+            .as_color_target(&[0, 1], None)
+            .clear(ClearState::color(0.0, 0.0, 0.0, 0.0))
+            .write::<RendererError>(|| {
+                program.use_vertex_attribute("position", &position_buffer);
+                program.use_vertex_attribute("normal", &normal_buffer);
+                program.use_vertex_attribute("lightmap_uv", &uv_buffer);
+                program.draw_arrays(
+                    RenderStates {
+                        depth_test: DepthTest::Always,
+                        ..Default::default()
+                    },
+                    Viewport::new_at_origo(self.lightmap_size, self.lightmap_size),
+                    positions.len() as u32,
+                );
+                Ok(())
+            })
+            .unwrap();
+        Some(geometry_texture)
+    }
+
+    ///
+    /// Returns the direct light from the given lights at each texel, where the first texel is the top left texel.
+    ///
+    fn direct_light(
+        &self,
+        geometry_texture: &Texture2DArray,
+        lights: &[&dyn Light],
+    ) -> Vec<[f32; 4]> {
+        let mut texture = self.new_light_texture();
+        let camera = self.camera();
+        texture
+            .as_color_target(None)
+            .clear(ClearState::color(0.0, 0.0, 0.0, 0.0))
+            .apply_screen_material(&DirectLightMaterial { geometry_texture }, &camera, lights)
+            .read()
+    }
+
+    ///
+    /// Returns the indirect light at each of the given texels, found by capturing the given objects from each texel and integrating the captured light over the hemisphere around the normal.
+    ///
+    fn indirect_light(
+        &self,
+        capture: &mut CubeMapCapture,
+        texels: &[Option<(Vec3, Vec3)>],
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+    ) -> Vec<[f32; 4]> {
+        let size = self.lightmap_size;
+        let mut texture = self.new_light_texture();
+        let camera = self.camera();
+        let target = texture.as_color_target(None);
+        target.clear(ClearState::color(0.0, 0.0, 0.0, 0.0));
+        for (index, texel) in texels.iter().enumerate() {
+            if let Some((position, normal)) = texel {
+                capture.render(
+                    position + normal * self.bias,
+                    ClearState::color_and_depth(0.0, 0.0, 0.0, 1.0, 1.0),
+                    objects.clone(),
+                    &[],
+                );
+                let x = index as u32 % size;
+                let y = size - 1 - index as u32 / size;
+                target.apply_screen_material_partially(
+                    ScissorBox {
+                        x: x as i32,
+                        y: y as i32,
+                        width: 1,
+                        height: 1,
+                    },
+                    &HemicubeMaterial {
+                        texture: capture.texture(),
+                        normal: *normal,
+                    },
+                    &camera,
+                    &[],
+                );
+            }
+        }
+        target.read()
+    }
+
+    fn new_light_texture(&self) -> Texture2D {
+        Texture2D::new_empty::<[f32; 4]>(
+            &self.context,
+            self.lightmap_size,
+            self.lightmap_size,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        )
+    }
+
+    fn camera(&self) -> Camera {
+        let mut camera = Camera::new_2d(Viewport::new_at_origo(
+            self.lightmap_size,
+            self.lightmap_size,
+        ));
+        camera.disable_tone_and_color_mapping();
+        camera
+    }
+}
+
+///
+/// Averages the light of each covered texel with the light of the neighbouring covered texels with a similar normal.
+///
+fn denoise(light: &[[f32; 4]], texels: &[Option<(Vec3, Vec3)>], size: u32) -> Vec<[f32; 4]> {
+    let size = size as i32;
+    (0..size * size)
+        .map(|index| {
+            let Some((_, normal)) = texels[index as usize] else {
+                return light[index as usize];
+            };
+            let (x, y) = (index % size, index / size);
+            let mut sum = [0.0; 3];
+            let mut weight = 0.0;
+            for (nx, ny) in (y - 1..=y + 1).flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny))) {
+                if nx < 0 || ny < 0 || nx >= size || ny >= size {
+                    continue;
+                }
+                let neighbour = (ny * size + nx) as usize;
+                if let Some((_, n)) = texels[neighbour] {
+                    if n.dot(normal) > 0.9 {
+                        let w = if neighbour == index as usize {
+                            2.0
+                        } else {
+                            1.0
+                        };
+                        for c in 0..3 {
+                            sum[c] += w * light[neighbour][c];
+                        }
+                        weight += w;
+                    }
+                }
+            }
+            [sum[0] / weight, sum[1] / weight, sum[2] / weight, 1.0]
+        })
+        .collect()
+}
+
+///
+/// Expands the covered texels, which have an alpha value of 1, into the neighbouring texels that are not covered the given number of times.
+///
+fn dilate(light: &[[f32; 4]], size: u32, iterations: u32) -> Vec<[f32; 4]> {
+    let size = size as i32;
+    let mut light = light.to_vec();
+    for _ in 0..iterations {
+        let previous = light.clone();
+        for index in 0..size * size {
+            if previous[index as usize][3] > 0.5 {
+                continue;
+            }
+            let (x, y) = (index % size, index / size);
+            let mut sum = [0.0; 3];
+            let mut count = 0.0;
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx < 0 || ny < 0 || nx >= size || ny >= size {
+                    continue;
+                }
+                let neighbour = previous[(ny * size + nx) as usize];
+                if neighbour[3] > 0.5 {
+                    for c in 0..3 {
+                        sum[c] += neighbour[c];
+                    }
+                    count += 1.0;
+                }
+            }
+            if count > 0.0 {
+                light[index as usize] = [sum[0] / count, sum[1] / count, sum[2] / count, 1.0];
+            }
+        }
+    }
+    light
+}
+
+fn lightmap_texture(light: &[[f32; 4]], size: u32) -> CpuTexture {
+    CpuTexture {
+        data: TextureData::RgbF32(light.iter().map(|l| [l[0], l[1], l[2]]).collect()),
+        width: size,
+        height: size,
+        min_filter: Interpolation::Linear,
+        mag_filter: Interpolation::Linear,
+        mip_map_filter: None,
+        wrap_s: Wrapping::ClampToEdge,
+        wrap_t: Wrapping::ClampToEdge,
+        ..Default::default()
+    }
+}
+
+struct DirectLightMaterial<'a> {
+    geometry_texture: &'a Texture2DArray,
+}
+
+impl Material for DirectLightMaterial<'_> {
+    fn fragment_shader_source(&self, lights: &[&dyn Light]) -> String {
+        // The specular reflection depends on the view direction, so it is left out of the baked light by replacing the reflection model used by the lights with a diffuse reflection
+        let mut source = lighting_model_shader(LightingModel::Blinn).to_string();
+        source.push_str(include_str!("../core/shared.frag"));
+        source.push_str(include_str!("light/shaders/light_shared.frag"));
+        source.push_str(
+            "
+            vec3 calculate_diffuse_light(vec3 light_color, vec3 L, vec3 surface_color, vec3 V, vec3 N, float metallic, float roughness)
+            {
+                return mix(surface_color, vec3(0.0), metallic) / PI * light_color * max(0.0, dot(N, L));
+            }
+            #define calculate_light calculate_diffuse_light
+            ",
+        );
+        let mut lighting = String::new();
+        for (i, light) in lights.iter().enumerate() {
+            source.push_str(&light.shader_source(i as u32));
+            lighting.push_str(&format!("light += calculate_lighting{}(vec3(1.0), position.xyz, normal, normal, 0.0, 1.0, 1.0);\n", i));
+        }
+        source.push_str(&format!(
+            "
+            uniform sampler2DArray geometryTexture;
+
+            layout (location = 0) out vec4 outColor;
+
+            void main()
+            {{
+                ivec2 texel = ivec2(gl_FragCoord.xy);
+                vec4 position = texelFetch(geometryTexture, ivec3(texel, 0), 0);
+                if (position.a < 0.5) {{
+                    outColor = vec4(0.0);
+                    return;
+                }}
+                vec3 normal = normalize(texelFetch(geometryTexture, ivec3(texel, 1), 0).xyz);
+                // The diffuse reflection from a white surface is the light arriving at the surface divided by PI
+                vec3 light = vec3(0.0);
+                {lighting}
+                outColor = vec4(light, 1.0);
+            }}
+            ",
+        ));
+        source
+    }
+
+    fn id(&self) -> u16 {
+        0b1u16 << 15 | 0b1u16 << 7 | 0b1u16
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes::NONE
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, lights: &[&dyn Light]) {
+        for (i, light) in lights.iter().enumerate() {
+            light.use_uniforms(program, i as u32);
+        }
+        program.use_texture_array("geometryTexture", self.geometry_texture);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            depth_test: DepthTest::Always,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+struct HemicubeMaterial<'a> {
+    texture: &'a TextureCubeMap,
+    normal: Vec3,
+}
+
+impl Material for HemicubeMaterial<'_> {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        "
+        uniform samplerCube hemicube;
+        uniform int size;
+        uniform vec3 normal;
+        uniform vec3 directions[6];
+        uniform vec3 ups[6];
+
+        layout (location = 0) out vec4 outColor;
+
+        void main()
+        {
+            vec3 irradiance = vec3(0.0);
+            float texel_size = 2.0 / float(size);
+            for (int side = 0; side < 6; side++) {
+                vec3 right = cross(directions[side], ups[side]);
+                for (int y = 0; y < size; y++) {
+                    for (int x = 0; x < size; x++) {
+                        vec3 direction = directions[side]
+                            + right * ((float(x) + 0.5) * texel_size - 1.0)
+                            + ups[side] * ((float(y) + 0.5) * texel_size - 1.0);
+                        float length_squared = dot(direction, direction);
+                        float cos_theta = dot(direction, normal) / sqrt(length_squared);
+                        if (cos_theta > 0.0) {
+                            // The solid angle of a texel on the cube map side decreases with the distance from the center of the side
+                            float solid_angle = texel_size * texel_size / (length_squared * sqrt(length_squared));
+                            irradiance += textureLod(hemicube, direction, 0.0).rgb * cos_theta * solid_angle;
+                        }
+                    }
+                }
+            }
+            outColor = vec4(irradiance / 3.14159265359, 1.0);
+        }
+        "
+        .to_owned()
+    }
+
+    fn id(&self) -> u16 {
+        0b1u16 << 15 | 0b1u16 << 7 | 0b10u16
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes::NONE
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_texture_cube("hemicube", self.texture);
+        program.use_uniform("size", self.texture.width() as i32);
+        program.use_uniform("normal", self.normal);
+        program.use_uniform_array(
+            "directions",
+            &CubeMapSide::iter()
+                .map(|s| s.direction())
+                .collect::<Vec<_>>(),
+        );
+        program.use_uniform_array(
+            "ups",
+            &CubeMapSide::iter().map(|s| s.up()).collect::<Vec<_>>(),
+        );
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            depth_test: DepthTest::Always,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
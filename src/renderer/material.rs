@@ -142,6 +142,28 @@ pub enum MaterialType {
     Deferred,
 }
 
+///
+/// Specifies how the colors in a lightmap, for example [PhysicalMaterial::lightmap_texture], are encoded.
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightmapEncoding {
+    /// The colors are stored directly, which supports high dynamic range if the texture contains floating point data (`RgbF16`, `RgbaF32` etc.).
+    /// Colors stored as `u8` are assumed to be in linear color space.
+    Linear,
+    /// The colors are stored as RGBM, ie. the color is the RGB value multiplied by the alpha value and the given range,
+    /// which makes it possible to store high dynamic range colors in a `RgbaU8` texture.
+    Rgbm(f32),
+}
+
+impl LightmapEncoding {
+    fn rgbm_range(&self) -> f32 {
+        match self {
+            Self::Linear => 0.0,
+            Self::Rgbm(range) => *range,
+        }
+    }
+}
+
 ///
 /// Describes the set of attributes provided by a [geometry] and consumed by a [Material], ie. calculated in the vertex shader and then sent to the fragment shader.
 /// To use an attribute for a material, add the relevant shader code to the fragment shader source (documented for each attribute) and return this struct from [Material::fragment_attributes] with the relevant attribute set to true.
@@ -156,8 +178,12 @@ pub struct FragmentAttributes {
     pub tangents: bool,
     /// UV coordinates: `in vec2 uvs;`
     pub uv: bool,
+    /// A second set of UV coordinates, for example used for lightmaps: `in vec2 uvs2;`
+    pub uv2: bool,
     /// Color: `in vec4 col;`
     pub color: bool,
+    /// The index of the instance, for example in the [Instances] of an [InstancedMesh], which is zero if the geometry is not instanced: `flat in int instanceId;`
+    pub instance_id: bool,
 }
 
 impl FragmentAttributes {
@@ -167,7 +193,9 @@ impl FragmentAttributes {
         normal: true,
         tangents: true,
         uv: true,
+        uv2: true,
         color: true,
+        instance_id: true,
     };
     /// No attributes
     pub const NONE: Self = Self {
@@ -175,7 +203,9 @@ impl FragmentAttributes {
         normal: false,
         tangents: false,
         uv: false,
+        uv2: false,
         color: false,
+        instance_id: false,
     };
 }
 
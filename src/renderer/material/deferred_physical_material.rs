@@ -43,6 +43,16 @@ pub struct DeferredPhysicalMaterial {
     /// Texture with color of light shining from an object.
     /// The colors are assumed to be in linear sRGB (`RgbU8`), linear sRGB with an alpha channel (`RgbaU8`) or HDR color space.
     pub emissive_texture: Option<Texture2DRef>,
+    /// Texture with light that is baked into the surface, for example global illumination computed offline, which replaces or complements the light from the lights.
+    /// The colors are the light arriving at the surface, ie. the color of a white diffuse surface lit by the light, and are decoded using the [Self::lightmap_encoding].
+    /// The texture is sampled using the second set of uv coordinates (see [FragmentAttributes::uv2]), which can be specified using [Mesh::update_uvs2].
+    /// A [CpuMaterial] does not contain a lightmap texture, so it is not loaded from for example glTF files and must be set after constructing the material.
+    /// The baked light is stored together with the emissive light in the geometry pass, so it is limited to the range of the geometry pass color target.
+    pub lightmap_texture: Option<Texture2DRef>,
+    /// A scalar multiplier applied to the light from the [Self::lightmap_texture].
+    pub lightmap_intensity: f32,
+    /// Specifies how the colors in the [Self::lightmap_texture] are encoded.
+    pub lightmap_encoding: LightmapEncoding,
     /// A threshold on the alpha value of the color as a workaround for transparency.
     /// If the alpha value of a pixel touched by an object with this material is less than the threshold, then that object is not contributing to the color of that pixel.
     /// On the other hand, if the alpha value is more than the threshold, then it is contributing fully to that pixel and thereby blocks out everything behind.
@@ -116,6 +126,9 @@ impl DeferredPhysicalMaterial {
             alpha_cutout: cpu_material.alpha_cutout,
             emissive: cpu_material.emissive,
             emissive_texture,
+            lightmap_texture: None,
+            lightmap_intensity: 1.0,
+            lightmap_encoding: LightmapEncoding::Linear,
         }
     }
 
@@ -141,6 +154,9 @@ impl DeferredPhysicalMaterial {
             },
             emissive: physical_material.emissive,
             emissive_texture: physical_material.emissive_texture.clone(),
+            lightmap_texture: physical_material.lightmap_texture.clone(),
+            lightmap_intensity: physical_material.lightmap_intensity,
+            lightmap_encoding: physical_material.lightmap_encoding,
             alpha_cutout: if physical_material.is_transparent {
                 Some(0.5)
             } else {
@@ -195,6 +211,9 @@ impl Material for DeferredPhysicalMaterial {
         if self.emissive_texture.is_some() {
             id |= 0b1u16 << 4;
         }
+        if self.lightmap_texture.is_some() {
+            id |= 0b1u16 << 11;
+        }
         if self.alpha_cutout.is_some() {
             id |= 0b1u16 << 5;
         }
@@ -236,6 +255,9 @@ impl Material for DeferredPhysicalMaterial {
                 );
             }
         }
+        if self.lightmap_texture.is_some() {
+            output.push_str("in vec2 uvs2;\n#define USE_LIGHTMAP_TEXTURE;\n");
+        }
         output.push_str(include_str!("shaders/deferred_physical_material.frag"));
         output
     }
@@ -251,7 +273,9 @@ impl Material for DeferredPhysicalMaterial {
                 || self.occlusion_texture.is_some()
                 || self.emissive_texture.is_some()
                 || self.alpha_cutout.is_some(),
+            uv2: self.lightmap_texture.is_some(),
             tangents: self.normal_texture.is_some(),
+            instance_id: false,
         }
     }
 
@@ -284,6 +308,12 @@ impl Material for DeferredPhysicalMaterial {
                 program.use_texture("emissiveTexture", texture);
             }
         }
+        if let Some(ref texture) = self.lightmap_texture {
+            program.use_uniform("lightmapTexTransform", texture.transformation);
+            program.use_uniform("lightmapIntensity", self.lightmap_intensity);
+            program.use_uniform("lightmapRgbmRange", self.lightmap_encoding.rgbm_range());
+            program.use_texture("lightmapTexture", texture);
+        }
     }
 
     fn render_states(&self) -> RenderStates {
@@ -312,6 +342,9 @@ impl Default for DeferredPhysicalMaterial {
             alpha_cutout: None,
             emissive: Srgba::BLACK,
             emissive_texture: None,
+            lightmap_texture: None,
+            lightmap_intensity: 1.0,
+            lightmap_encoding: LightmapEncoding::Linear,
         }
     }
 }
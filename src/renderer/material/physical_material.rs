@@ -39,6 +39,15 @@ pub struct PhysicalMaterial {
     /// Texture with color of light shining from an object.
     /// The colors are assumed to be in linear sRGB (`RgbU8`), linear sRGB with an alpha channel (`RgbaU8`) or HDR color space.
     pub emissive_texture: Option<Texture2DRef>,
+    /// Texture with light that is baked into the surface, for example global illumination computed offline, which replaces or complements the light from the lights.
+    /// The colors are the light arriving at the surface, ie. the color of a white diffuse surface lit by the light, and are decoded using the [Self::lightmap_encoding].
+    /// The texture is sampled using the second set of uv coordinates (see [FragmentAttributes::uv2]), which can be specified using [Mesh::update_uvs2].
+    /// A [CpuMaterial] does not contain a lightmap texture, so it is not loaded from for example glTF files and must be set after constructing the material.
+    pub lightmap_texture: Option<Texture2DRef>,
+    /// A scalar multiplier applied to the light from the [Self::lightmap_texture].
+    pub lightmap_intensity: f32,
+    /// Specifies how the colors in the [Self::lightmap_texture] are encoded.
+    pub lightmap_encoding: LightmapEncoding,
     /// The lighting model used when rendering this material
     pub lighting_model: LightingModel,
 }
@@ -138,6 +147,9 @@ impl PhysicalMaterial {
             is_transparent,
             emissive: cpu_material.emissive,
             emissive_texture,
+            lightmap_texture: None,
+            lightmap_intensity: 1.0,
+            lightmap_encoding: LightmapEncoding::Linear,
             lighting_model: cpu_material.lighting_model,
         }
     }
@@ -167,6 +179,9 @@ impl Material for PhysicalMaterial {
         if self.emissive_texture.is_some() {
             id |= 0b1u16 << 4;
         }
+        if self.lightmap_texture.is_some() {
+            id |= 0b1u16 << 11;
+        }
         id
     }
 
@@ -195,6 +210,9 @@ impl Material for PhysicalMaterial {
                 output.push_str("#define USE_EMISSIVE_TEXTURE;\n");
             }
         }
+        if self.lightmap_texture.is_some() {
+            output.push_str("in vec2 uvs2;\n#define USE_LIGHTMAP_TEXTURE;\n");
+        }
         output.push_str(ToneMapping::fragment_shader_source());
         output.push_str(ColorMapping::fragment_shader_source());
         output.push_str(include_str!("shaders/physical_material.frag"));
@@ -211,7 +229,9 @@ impl Material for PhysicalMaterial {
                 || self.normal_texture.is_some()
                 || self.occlusion_texture.is_some()
                 || self.emissive_texture.is_some(),
+            uv2: self.lightmap_texture.is_some(),
             tangents: self.normal_texture.is_some(),
+            instance_id: false,
         }
     }
 
@@ -223,7 +243,9 @@ impl Material for PhysicalMaterial {
             for (i, light) in lights.iter().enumerate() {
                 light.use_uniforms(program, i as u32);
             }
-            program.use_uniform("metallic", self.metallic);
+        }
+        if !lights.is_empty() || self.lightmap_texture.is_some() {
+            program.use_uniform_if_required("metallic", self.metallic);
             program.use_uniform_if_required("roughness", self.roughness);
             if program.requires_uniform("albedoTexture") {
                 if let Some(ref texture) = self.albedo_texture {
@@ -260,6 +282,12 @@ impl Material for PhysicalMaterial {
                 program.use_texture("emissiveTexture", texture);
             }
         }
+        if let Some(ref texture) = self.lightmap_texture {
+            program.use_uniform("lightmapTexTransform", texture.transformation);
+            program.use_uniform("lightmapIntensity", self.lightmap_intensity);
+            program.use_uniform("lightmapRgbmRange", self.lightmap_encoding.rgbm_range());
+            program.use_texture("lightmapTexture", texture);
+        }
     }
 
     fn render_states(&self) -> RenderStates {
@@ -291,6 +319,9 @@ impl Default for PhysicalMaterial {
             is_transparent: false,
             emissive: Srgba::BLACK,
             emissive_texture: None,
+            lightmap_texture: None,
+            lightmap_intensity: 1.0,
+            lightmap_encoding: LightmapEncoding::Linear,
             lighting_model: LightingModel::Blinn,
         }
     }
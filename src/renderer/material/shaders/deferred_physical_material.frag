@@ -31,6 +31,13 @@ uniform mat3 normalTexTransform;
 uniform float normalScale;
 #endif
 
+#ifdef USE_LIGHTMAP_TEXTURE
+uniform sampler2D lightmapTexture;
+uniform mat3 lightmapTexTransform;
+uniform float lightmapIntensity;
+uniform float lightmapRgbmRange;
+#endif
+
 in vec3 pos;
 in vec3 nor;
 in vec4 col;
@@ -76,6 +83,13 @@ void main()
     total_emissive *= texture(emissiveTexture, (emissiveTexTransform * vec3(uvs, 1.0)).xy).rgb;
 #endif
 
+    vec3 baked_light = vec3(0.0);
+#ifdef USE_LIGHTMAP_TEXTURE
+    vec4 lightmap = texture(lightmapTexture, (lightmapTexTransform * vec3(uvs2, 1.0)).xy);
+    vec3 lightmap_color = lightmapRgbmRange > 0.0 ? lightmap.rgb * lightmap.a * lightmapRgbmRange : lightmap.rgb;
+    baked_light = lightmapIntensity * lightmap_color * mix(surface_color.rgb, vec3(0.0), metallic_factor) * occlusion;
+#endif
+
     outColor = vec4(surface_color.rgb, metallic_factor);
     int o = int(occlusion * 127.0);
     int nz = 1;
@@ -83,5 +97,6 @@ void main()
         nz = 0;
     }
     outNormal = vec4(0.5 * normal.xy + 0.5, float(o | nz << 7)/255.0, roughness_factor);
-    outEmissive = vec4(total_emissive, 0.0);
+    // The baked light does not depend on the lights, so it is added to the emissive light which is added in the lighting pass
+    outEmissive = vec4(total_emissive + baked_light, 0.0);
 }
\ No newline at end of file
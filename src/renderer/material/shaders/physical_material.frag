@@ -32,6 +32,13 @@ uniform mat3 normalTexTransform;
 uniform float normalScale;
 #endif
 
+#ifdef USE_LIGHTMAP_TEXTURE
+uniform sampler2D lightmapTexture;
+uniform mat3 lightmapTexTransform;
+uniform float lightmapIntensity;
+uniform float lightmapRgbmRange;
+#endif
+
 in vec3 pos;
 in vec3 nor;
 in vec4 col;
@@ -75,7 +82,14 @@ void main()
     total_emissive *= texture(emissiveTexture, (emissiveTexTransform * vec3(uvs, 1.0)).xy).rgb;
 #endif
 
-    outColor.rgb = total_emissive + calculate_lighting(cameraPosition, surface_color.rgb, pos, normal, metallic_factor, roughness_factor, occlusion);
+    vec3 baked_light = vec3(0.0);
+#ifdef USE_LIGHTMAP_TEXTURE
+    vec4 lightmap = texture(lightmapTexture, (lightmapTexTransform * vec3(uvs2, 1.0)).xy);
+    vec3 lightmap_color = lightmapRgbmRange > 0.0 ? lightmap.rgb * lightmap.a * lightmapRgbmRange : lightmap.rgb;
+    baked_light = lightmapIntensity * lightmap_color * mix(surface_color.rgb, vec3(0.0), metallic_factor) * occlusion;
+#endif
+
+    outColor.rgb = total_emissive + baked_light + calculate_lighting(cameraPosition, surface_color.rgb, pos, normal, metallic_factor, roughness_factor, occlusion);
     outColor.rgb = tone_mapping(outColor.rgb);
     outColor.rgb = color_mapping(outColor.rgb);
     outColor.a = surface_color.a;
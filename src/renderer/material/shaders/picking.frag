@@ -0,0 +1,37 @@
+
+in vec3 pos;
+
+#ifdef PICKING_IDS
+
+uniform uint geometryIndex;
+
+flat in int instanceId;
+
+layout (location = 0) out uvec4 outId;
+
+void main()
+{
+    uint instance = uint(instanceId);
+
+    // The primitive id is not available in OpenGL ES 3.0, which is used on web
+#ifdef GL_ES
+    uint triangle = 0u;
+#else
+    uint triangle = uint(gl_PrimitiveID) + 1u;
+#endif
+
+    outId = uvec4(geometryIndex, instance, triangle, 1u);
+}
+
+#else
+
+layout (location = 0) out vec4 outPosition;
+layout (location = 1) out vec4 outNormal;
+
+void main()
+{
+    outPosition = vec4(pos, 1.0);
+    outNormal = vec4(normalize(cross(dFdx(pos), dFdy(pos))), 0.0);
+}
+
+#endif
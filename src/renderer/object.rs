@@ -42,6 +42,14 @@ mod imposters;
 #[doc(inline)]
 pub use imposters::*;
 
+mod octahedral_imposters;
+#[doc(inline)]
+pub use octahedral_imposters::*;
+
+mod shapes_2d;
+#[doc(inline)]
+pub use shapes_2d::*;
+
 mod terrain;
 #[doc(inline)]
 pub use terrain::*;
@@ -54,6 +62,18 @@ mod axes;
 #[doc(inline)]
 pub use axes::*;
 
+mod debug_draw;
+#[doc(inline)]
+pub use debug_draw::*;
+
+mod transform_gizmo;
+#[doc(inline)]
+pub use transform_gizmo::*;
+
+mod scene_graph;
+#[doc(inline)]
+pub use scene_graph::*;
+
 use crate::core::*;
 use crate::renderer::*;
 
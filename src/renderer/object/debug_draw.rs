@@ -0,0 +1,279 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// An immediate mode collector of debug visualizations, for example lines, bounding boxes, spheres and coordinate axes.
+/// Add the primitives every frame and call [DebugDraw::render] to render all of them with a few draw calls,
+/// after which the primitives are cleared, ready for the next frame.
+///
+pub struct DebugDraw {
+    /// Whether or not to render the primitives on top of the scene, ie. without depth testing.
+    pub ignore_depth: bool,
+    #[cfg(feature = "text")]
+    /// The size of the text in pixels.
+    pub text_size: f32,
+    #[cfg(feature = "text")]
+    context: Context,
+    positions: Vec<Vec3>,
+    colors: Vec<Srgba>,
+    lines: Lines3D,
+    #[cfg(feature = "text")]
+    font: Option<Font>,
+    #[cfg(feature = "text")]
+    texts: Vec<(Vec3, String, Srgba)>,
+    #[cfg(feature = "text")]
+    text_geometry: Option<TextGeometry>,
+}
+
+impl DebugDraw {
+    ///
+    /// Creates a new debug draw collector which draws lines with the given width.
+    ///
+    pub fn new(context: &Context, line_width: LineWidth) -> Self {
+        Self {
+            ignore_depth: false,
+            #[cfg(feature = "text")]
+            text_size: 16.0,
+            #[cfg(feature = "text")]
+            context: context.clone(),
+            positions: Vec::new(),
+            colors: Vec::new(),
+            lines: Lines3D::new(context, &CpuLines::default(), line_width),
+            #[cfg(feature = "text")]
+            font: None,
+            #[cfg(feature = "text")]
+            texts: Vec::new(),
+            #[cfg(feature = "text")]
+            text_geometry: None,
+        }
+    }
+
+    ///
+    /// Set the width of the lines.
+    ///
+    pub fn set_line_width(&mut self, line_width: LineWidth) {
+        self.lines.set_width(line_width);
+    }
+
+    ///
+    /// Set the font used for rendering text added with [DebugDraw::text].
+    ///
+    #[cfg(feature = "text")]
+    pub fn set_font(&mut self, font: Font) {
+        self.font = Some(font);
+    }
+
+    ///
+    /// Adds a line from the start to the end position.
+    ///
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Srgba) {
+        self.positions.extend([start, end]);
+        self.colors.extend([color, color]);
+    }
+
+    ///
+    /// Adds the edges of the given axis aligned bounding box.
+    ///
+    pub fn aabb(&mut self, aabb: &AxisAlignedBoundingBox, color: Srgba) {
+        if aabb.is_empty() || aabb.is_infinite() {
+            return;
+        }
+        let (min, max) = (aabb.min(), aabb.max());
+        let corner = |i: usize| {
+            vec3(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        self.box_edges(corner, color);
+    }
+
+    ///
+    /// Adds three circles in the coordinate planes with the given center and radius.
+    ///
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Srgba) {
+        const SUBDIVISIONS: usize = 32;
+        let point = |i: usize| {
+            let angle = 2.0 * std::f32::consts::PI * i as f32 / SUBDIVISIONS as f32;
+            radius * vec2(angle.cos(), angle.sin())
+        };
+        for i in 0..SUBDIVISIONS {
+            let (a, b) = (point(i), point(i + 1));
+            self.line(
+                center + vec3(a.x, a.y, 0.0),
+                center + vec3(b.x, b.y, 0.0),
+                color,
+            );
+            self.line(
+                center + vec3(a.x, 0.0, a.y),
+                center + vec3(b.x, 0.0, b.y),
+                color,
+            );
+            self.line(
+                center + vec3(0.0, a.x, a.y),
+                center + vec3(0.0, b.x, b.y),
+                color,
+            );
+        }
+    }
+
+    ///
+    /// Adds an arrow from the start position pointing to the end position.
+    ///
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Srgba) {
+        self.line(start, end, color);
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length < 1e-6 {
+            return;
+        }
+        let direction = direction / length;
+        let t = if direction.x.abs() < 0.9 {
+            vec3(1.0, 0.0, 0.0)
+        } else {
+            vec3(0.0, 1.0, 0.0)
+        };
+        let u = direction.cross(t).normalize();
+        let v = direction.cross(u);
+        let base = end - 0.2 * length * direction;
+        let radius = 0.07 * length;
+        for offset in [u, v, -u, -v] {
+            self.line(end, base + radius * offset, color);
+        }
+    }
+
+    ///
+    /// Adds the edges of the view frustum of the given camera.
+    ///
+    pub fn frustum(&mut self, camera: &Camera, color: Srgba) {
+        let Some(inverse) = (camera.projection() * camera.view()).invert() else {
+            return;
+        };
+        let corner = |i: usize| {
+            let p = inverse
+                * vec4(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -1.0 } else { 1.0 },
+                    if i & 4 == 0 { -1.0 } else { 1.0 },
+                    1.0,
+                );
+            p.truncate() / p.w
+        };
+        self.box_edges(corner, color);
+    }
+
+    ///
+    /// Adds the x (red), y (green) and z (blue) axes of the coordinate system defined by the given transformation,
+    /// ie. the axes have unit length before the transformation.
+    ///
+    pub fn axes(&mut self, transformation: Mat4) {
+        let origin = (transformation * vec4(0.0, 0.0, 0.0, 1.0)).truncate();
+        for (axis, color) in [
+            (transformation.x, Srgba::RED),
+            (transformation.y, Srgba::GREEN),
+            (transformation.z, Srgba::BLUE),
+        ] {
+            self.arrow(origin, origin + axis.truncate(), color);
+        }
+    }
+
+    ///
+    /// Adds a text label at the given position, which always faces the camera and has the size given by [DebugDraw::text_size].
+    /// The text is only rendered if a font is set using [DebugDraw::set_font].
+    ///
+    #[cfg(feature = "text")]
+    pub fn text(&mut self, position: Vec3, text: &str, color: Srgba) {
+        self.texts.push((position, text.to_string(), color));
+    }
+
+    ///
+    /// Removes all of the added primitives without rendering them.
+    ///
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.colors.clear();
+        #[cfg(feature = "text")]
+        self.texts.clear();
+    }
+
+    ///
+    /// Renders all of the added primitives into the given render target using the given camera and then removes them.
+    ///
+    pub fn render(&mut self, camera: &Camera, target: &RenderTarget) {
+        let render_states = RenderStates {
+            depth_test: if self.ignore_depth {
+                DepthTest::Always
+            } else {
+                DepthTest::Less
+            },
+            ..Default::default()
+        };
+        if !self.positions.is_empty() {
+            self.lines.set_lines(&CpuLines {
+                positions: std::mem::take(&mut self.positions),
+                colors: Some(std::mem::take(&mut self.colors)),
+                topology: LineTopology::Segments,
+            });
+            target.render_with_material(
+                &ColorMaterial {
+                    render_states,
+                    ..Default::default()
+                },
+                camera,
+                &self.lines,
+                &[],
+            );
+        }
+
+        #[cfg(feature = "text")]
+        if let Some(font) = self.font.as_ref().filter(|_| !self.texts.is_empty()) {
+            let layout = TextLayout {
+                size: self.text_size,
+                alignment: TextAlignment::Center,
+                vertical_alignment: VerticalAlignment::Bottom,
+                ..Default::default()
+            };
+            let mut material = TextMaterial::new(font);
+            material.render_states.depth_test = render_states.depth_test;
+            material.color = Srgba::WHITE;
+            material.outline_color = Srgba::BLACK;
+            material.outline_width = 0.1;
+            // All of the texts are drawn at once and the text geometry is reused to avoid allocating new buffers every frame
+            let text_geometry = self.text_geometry.get_or_insert_with(|| {
+                TextGeometry::new(
+                    &self.context,
+                    font,
+                    "",
+                    &layout,
+                    TextPlacement::ScreenBillboard,
+                )
+            });
+            text_geometry.set_labels(font, &self.texts, &layout);
+            target.render_with_material(&material, camera, &*text_geometry, &[]);
+        }
+        self.clear();
+    }
+
+    ///
+    /// Adds the 12 edges of a box where the corner with index `i` is on the positive side in x if bit 0 is set, in y if bit 1 is set and in z if bit 2 is set.
+    ///
+    fn box_edges(&mut self, corner: impl Fn(usize) -> Vec3, color: Srgba) {
+        for (i, j) in [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ] {
+            self.line(corner(i), corner(j), color);
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// Specifies which view directions are captured by [OctahedralImposters].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OctahedralViews {
+    /// Only view directions from above the horizon are captured.
+    /// Use this for objects that are never seen from below, for example trees.
+    Hemisphere,
+    /// View directions from all around the objects are captured.
+    Sphere,
+}
+
+///
+/// A level-of-detail technique to replace rendering of high-poly meshes, similar to [Imposters].
+/// The difference is that the objects are rendered from a set of view directions distributed on an octahedron,
+/// either covering a hemisphere or the full sphere (see [OctahedralViews]), which means that the imposters also look correct when seen from above.
+/// Furthermore, the albedo, normal and depth of the objects are stored, so the imposters are lit by the lights given in the render call
+/// and correctly intersect with other objects in the scene.
+///
+pub struct OctahedralImposters {
+    context: Context,
+    geometry: OctahedralImpostersGeometry,
+    material: OctahedralImpostersMaterial,
+}
+
+impl OctahedralImposters {
+    ///
+    /// Constructs new [OctahedralImposters] and render the imposter textures from the given objects.
+    /// The imposters are placed at the given positions.
+    ///
+    /// The objects are rendered from `frames_per_side * frames_per_side` view directions into textures of size `frame_size * frame_size`,
+    /// so the number of frames per side must be in the range `[2..16]`.
+    ///
+    pub fn new(
+        context: &Context,
+        positions: &[Vec3],
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        views: OctahedralViews,
+        frames_per_side: u32,
+        frame_size: u32,
+    ) -> Self {
+        let mut s = Self {
+            context: context.clone(),
+            geometry: OctahedralImpostersGeometry {
+                context: context.clone(),
+                position_buffer: VertexBuffer::new_with_data(
+                    context,
+                    &[
+                        vec3(-1.0, -1.0, 0.0),
+                        vec3(1.0, -1.0, 0.0),
+                        vec3(1.0, 1.0, 0.0),
+                        vec3(1.0, 1.0, 0.0),
+                        vec3(-1.0, 1.0, 0.0),
+                        vec3(-1.0, -1.0, 0.0),
+                    ],
+                ),
+                center_buffer: InstanceBuffer::new(context),
+                positions: positions.to_vec(),
+                aabb: AxisAlignedBoundingBox::EMPTY,
+            },
+            material: OctahedralImpostersMaterial {
+                views,
+                frames_per_side: 2,
+                center: vec3(0.0, 0.0, 0.0),
+                radius: 0.0,
+                albedo_texture: new_frame_texture::<[f16; 4]>(context, 1, 2),
+                normal_depth_texture: new_frame_texture::<[f16; 4]>(context, 1, 2),
+                metallic: 0.0,
+                roughness: 1.0,
+                lighting_model: LightingModel::Blinn,
+            },
+        };
+        s.update_texture(objects, views, frames_per_side, frame_size);
+        s
+    }
+
+    ///
+    /// Set the positions of the imposters.
+    ///
+    pub fn set_positions(&mut self, positions: &[Vec3]) {
+        self.geometry.positions = positions.to_vec();
+        self.geometry.update_centers(self.material.center);
+    }
+
+    ///
+    /// Set the metallic and roughness values used when lighting the imposters.
+    /// These are the same for all imposters, since only the albedo and normal of the objects are captured.
+    ///
+    pub fn set_surface_parameters(&mut self, metallic: f32, roughness: f32) {
+        self.material.metallic = metallic;
+        self.material.roughness = roughness;
+    }
+
+    ///
+    /// Set the [LightingModel] used when lighting the imposters.
+    ///
+    pub fn set_lighting_model(&mut self, lighting_model: LightingModel) {
+        self.material.lighting_model = lighting_model;
+    }
+
+    ///
+    /// Render the imposter textures from the given objects.
+    /// Use this if you want to update the look of the imposters.
+    ///
+    pub fn update_texture(
+        &mut self,
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        views: OctahedralViews,
+        frames_per_side: u32,
+        frame_size: u32,
+    ) {
+        if !(2..=16).contains(&frames_per_side) {
+            panic!(
+                "The number of frames per side of octahedral imposters must be in the range [2..16], but is {}",
+                frames_per_side
+            );
+        }
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        objects
+            .clone()
+            .into_iter()
+            .for_each(|o| aabb.expand_with_aabb(&o.aabb()));
+        self.material.update(
+            &self.context,
+            aabb,
+            objects,
+            views,
+            frames_per_side,
+            frame_size,
+        );
+        self.geometry.aabb = aabb;
+        self.geometry.update_centers(self.material.center);
+    }
+}
+
+impl<'a> IntoIterator for &'a OctahedralImposters {
+    type Item = &'a dyn Object;
+    type IntoIter = std::iter::Once<&'a dyn Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for OctahedralImposters {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        self.geometry
+            .draw(camera, program, render_states, attributes)
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        self.geometry.vertex_shader_source(required_attributes)
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        self.geometry.id(required_attributes)
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, &self.geometry, material, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            &self.geometry,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.geometry.aabb()
+    }
+}
+
+impl Object for OctahedralImposters {
+    fn render(&self, camera: &Camera, lights: &[&dyn Light]) {
+        render_with_material(
+            &self.context,
+            camera,
+            &self.geometry,
+            &self.material,
+            lights,
+        )
+    }
+
+    fn material_type(&self) -> MaterialType {
+        self.material.material_type()
+    }
+}
+
+struct OctahedralImpostersGeometry {
+    context: Context,
+    position_buffer: VertexBuffer,
+    center_buffer: InstanceBuffer,
+    positions: Vec<Vec3>,
+    aabb: AxisAlignedBoundingBox,
+}
+
+impl OctahedralImpostersGeometry {
+    fn update_centers(&mut self, center: Vec3) {
+        self.center_buffer.fill(
+            &self
+                .positions
+                .iter()
+                .map(|p| p + center)
+                .collect::<Vec<_>>(),
+        );
+    }
+}
+
+impl Geometry for OctahedralImpostersGeometry {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        if attributes.normal || attributes.tangents || attributes.color {
+            panic!("octahedral imposters only provide position and uv coordinates")
+        }
+        program.use_uniform("eye", camera.position());
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform_if_required("radius", 0.5 * self.aabb.size().magnitude());
+        program.use_vertex_attribute("position", &self.position_buffer);
+        program.use_instance_attribute("center", &self.center_buffer);
+        program.draw_arrays_instanced(
+            render_states,
+            camera.viewport(),
+            6,
+            self.center_buffer.instance_count(),
+        )
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "{}{}",
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("shaders/octahedral_imposter.vert")
+        )
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15 | 0b110u16;
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, self, material, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        if self.aabb.is_empty() {
+            return AxisAlignedBoundingBox::EMPTY;
+        }
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        let (min, max) = (self.aabb.min(), self.aabb.max());
+        self.positions.iter().for_each(|p| {
+            aabb.expand(&[p + min, p + max]);
+        });
+        aabb
+    }
+}
+
+struct OctahedralImpostersMaterial {
+    views: OctahedralViews,
+    frames_per_side: u32,
+    center: Vec3,
+    radius: f32,
+    albedo_texture: Texture2DArray,
+    normal_depth_texture: Texture2DArray,
+    metallic: f32,
+    roughness: f32,
+    lighting_model: LightingModel,
+}
+
+impl OctahedralImpostersMaterial {
+    fn update(
+        &mut self,
+        context: &Context,
+        aabb: AxisAlignedBoundingBox,
+        objects: impl IntoIterator<Item = impl Object> + Clone,
+        views: OctahedralViews,
+        frames_per_side: u32,
+        frame_size: u32,
+    ) {
+        self.views = views;
+        self.frames_per_side = frames_per_side;
+        if aabb.is_empty() {
+            return;
+        }
+        self.center = aabb.center();
+        self.radius = 0.5 * aabb.size().magnitude();
+        let frame_count = frames_per_side * frames_per_side;
+        self.albedo_texture = new_frame_texture::<[f16; 4]>(context, frame_size, frame_count);
+        self.normal_depth_texture = new_frame_texture::<[f16; 4]>(context, frame_size, frame_count);
+        let mut depth_texture = DepthTexture2D::new::<f32>(
+            context,
+            frame_size,
+            frame_size,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+
+        // The albedo is captured as the color of the objects when lit by a white ambient light with unit intensity.
+        // The directional light does not contribute, but makes sure that the materials use the normals of the objects.
+        let ambient = AmbientLight::new(context, 1.0, Srgba::WHITE);
+        let directional = DirectionalLight::new(context, 0.0, Srgba::WHITE, &vec3(0.0, -1.0, 0.0));
+        let mut camera = Camera::new_orthographic(
+            Viewport::new_at_origo(frame_size, frame_size),
+            self.center + vec3(0.0, 0.0, 2.0 * self.radius),
+            self.center,
+            vec3(0.0, 1.0, 0.0),
+            2.0 * self.radius,
+            0.0,
+            4.0 * self.radius,
+        );
+        camera.disable_tone_and_color_mapping();
+        for j in 0..frames_per_side {
+            for i in 0..frames_per_side {
+                let layers = [j * frames_per_side + i];
+                let uv = vec2(i as f32, j as f32) / (frames_per_side - 1) as f32;
+                let direction = octahedral_decode(views, uv);
+                let (_, up) = frame_basis(direction);
+                camera.set_view(self.center + 2.0 * self.radius * direction, self.center, up);
+                RenderTarget::new(
+                    self.albedo_texture.as_color_target(&layers, None),
+                    depth_texture.as_depth_target(),
+                )
+                .clear(ClearState::color_and_depth(0.0, 0.0, 0.0, 0.0, 1.0))
+                .render(&camera, objects.clone(), &[&ambient, &directional]);
+
+                let capture_material = OctahedralImposterCaptureMaterial {
+                    center: self.center,
+                    radius: self.radius,
+                    direction,
+                };
+                RenderTarget::new(
+                    self.normal_depth_texture.as_color_target(&layers, None),
+                    depth_texture.as_depth_target(),
+                )
+                .clear(ClearState::color_and_depth(0.5, 0.5, 1.0, 0.5, 1.0))
+                .render_with_material(
+                    &capture_material,
+                    &camera,
+                    objects.clone(),
+                    &[],
+                );
+            }
+        }
+    }
+}
+
+impl Material for OctahedralImpostersMaterial {
+    fn id(&self) -> u16 {
+        match self.views {
+            OctahedralViews::Hemisphere => 0b1u16 << 15 | 0b1000u16,
+            OctahedralViews::Sphere => 0b1u16 << 15 | 0b1001u16,
+        }
+    }
+
+    fn fragment_shader_source(&self, lights: &[&dyn Light]) -> String {
+        let mut output = lights_shader_source(lights, self.lighting_model);
+        if self.views == OctahedralViews::Hemisphere {
+            output.push_str("#define HEMISPHERE\n");
+        }
+        output.push_str(ToneMapping::fragment_shader_source());
+        output.push_str(ColorMapping::fragment_shader_source());
+        output.push_str(include_str!("shaders/octahedral_imposter.frag"));
+        output
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, camera: &Camera, lights: &[&dyn Light]) {
+        camera.tone_mapping.use_uniforms(program);
+        camera.color_mapping.use_uniforms(program);
+        for (i, light) in lights.iter().enumerate() {
+            light.use_uniforms(program, i as u32);
+        }
+        program.use_uniform("cameraPosition", camera.position());
+        program.use_uniform_if_required("metallic", self.metallic);
+        program.use_uniform_if_required("roughness", self.roughness);
+        program.use_uniform("framesPerSide", self.frames_per_side as i32);
+        program.use_uniform("imposterRadius", self.radius);
+        program.use_texture_array("albedoFrames", &self.albedo_texture);
+        program.use_texture_array("normalDepthFrames", &self.normal_depth_texture);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            cull: Cull::Back,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+///
+/// Used for capturing the world space normal and the depth relative to the center of the objects.
+///
+struct OctahedralImposterCaptureMaterial {
+    center: Vec3,
+    radius: f32,
+    direction: Vec3,
+}
+
+impl Material for OctahedralImposterCaptureMaterial {
+    fn id(&self) -> u16 {
+        0b1u16 << 15 | 0b1010u16
+    }
+
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        include_str!("shaders/octahedral_imposter_capture.frag").to_owned()
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            normal: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform("frameCenter", self.center);
+        program.use_uniform("frameRadius", self.radius);
+        program.use_uniform("frameDirection", self.direction);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates::default()
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+fn new_frame_texture<T: TextureDataType>(
+    context: &Context,
+    frame_size: u32,
+    frame_count: u32,
+) -> Texture2DArray {
+    Texture2DArray::new_empty::<T>(
+        context,
+        frame_size,
+        frame_size,
+        frame_count,
+        Interpolation::Linear,
+        Interpolation::Linear,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    )
+}
+
+///
+/// Returns the view direction (pointing from the objects towards the viewer) for the given octahedral uv coordinates.
+/// Must be kept in sync with `octahedral_decode` in the imposter fragment shader.
+///
+fn octahedral_decode(views: OctahedralViews, uv: Vec2) -> Vec3 {
+    let p = 2.0 * uv - vec2(1.0, 1.0);
+    match views {
+        OctahedralViews::Hemisphere => {
+            let x = 0.5 * (p.x + p.y);
+            let z = 0.5 * (p.x - p.y);
+            vec3(x, 1.0 - x.abs() - z.abs(), z).normalize()
+        }
+        OctahedralViews::Sphere => {
+            let mut d = vec3(p.x, 1.0 - p.x.abs() - p.y.abs(), p.y);
+            if d.y < 0.0 {
+                let (x, z) = (d.x, d.z);
+                d.x = (1.0 - z.abs()) * if x >= 0.0 { 1.0 } else { -1.0 };
+                d.z = (1.0 - x.abs()) * if z >= 0.0 { 1.0 } else { -1.0 };
+            }
+            d.normalize()
+        }
+    }
+}
+
+///
+/// Returns the right and up direction of the frame captured from the given view direction.
+/// Must be kept in sync with `frame_basis` in the imposter fragment shader.
+///
+fn frame_basis(direction: Vec3) -> (Vec3, Vec3) {
+    let mut right = vec3(0.0, 1.0, 0.0).cross(direction);
+    if right.magnitude2() < 1.0e-6 {
+        right = vec3(1.0, 0.0, 0.0);
+    }
+    let right = right.normalize();
+    (right, direction.cross(right))
+}
@@ -0,0 +1,640 @@
+use crate::core::*;
+use crate::renderer::*;
+pub use three_d_asset::{Node as CpuNode, Scene as CpuScene};
+
+///
+/// Represents something with a local to world transformation which can be set, for example a [Mesh] or a [Gm] with a [Mesh] as geometry.
+/// This is needed to attach an object to a node in a [SceneGraph].
+///
+pub trait Transformable {
+    ///
+    /// Set the local to world transformation.
+    ///
+    fn set_transformation(&mut self, transformation: Mat4);
+}
+
+impl<T: Transformable + ?Sized> Transformable for &mut T {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        T::set_transformation(self, transformation)
+    }
+}
+
+impl<T: Transformable + ?Sized> Transformable for Box<T> {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        T::set_transformation(self, transformation)
+    }
+}
+
+impl Transformable for Mesh {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        Mesh::set_transformation(self, transformation)
+    }
+}
+
+impl Transformable for InstancedMesh {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        InstancedMesh::set_transformation(self, transformation)
+    }
+}
+
+impl Transformable for Sprites {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        Sprites::set_transformation(self, transformation)
+    }
+}
+
+impl Transformable for Lines3D {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        Lines3D::set_transformation(self, transformation)
+    }
+}
+
+impl Transformable for ParticleSystem {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        ParticleSystem::set_transformation(self, transformation)
+    }
+}
+
+#[cfg(feature = "text")]
+impl Transformable for TextGeometry {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        TextGeometry::set_transformation(self, transformation)
+    }
+}
+
+impl<G: Geometry + Transformable, M: Material> Transformable for Gm<G, M> {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        self.geometry.set_transformation(transformation)
+    }
+}
+
+impl<M: Material> Transformable for ModelPart<M> {
+    fn set_transformation(&mut self, transformation: Mat4) {
+        Mesh::set_transformation(self, transformation)
+    }
+}
+
+///
+/// An [Object] which can be attached to a node in a [SceneGraph], ie. an object which is also [Transformable].
+/// This is implemented for all types that implement both [Object] and [Transformable].
+///
+pub trait SceneObject: Object + Transformable {}
+
+impl<T: Object + Transformable + ?Sized> SceneObject for T {}
+
+///
+/// A [Light] which can be attached to a node in a [SceneGraph], ie. a light with a position and/or a direction that can be transformed.
+///
+pub trait SceneLight: Light {
+    ///
+    /// Returns the position and direction of the light. Lights without a position or direction can return any value for that.
+    ///
+    fn position_and_direction(&self) -> (Vec3, Vec3);
+
+    ///
+    /// Set the position and direction of the light. Lights without a position or direction should ignore that value.
+    ///
+    fn set_position_and_direction(&mut self, position: Vec3, direction: Vec3);
+}
+
+impl SceneLight for AmbientLight {
+    fn position_and_direction(&self) -> (Vec3, Vec3) {
+        (Vec3::zero(), Vec3::zero())
+    }
+
+    fn set_position_and_direction(&mut self, _position: Vec3, _direction: Vec3) {}
+}
+
+impl SceneLight for DirectionalLight {
+    fn position_and_direction(&self) -> (Vec3, Vec3) {
+        (Vec3::zero(), self.direction)
+    }
+
+    fn set_position_and_direction(&mut self, _position: Vec3, direction: Vec3) {
+        self.direction = direction;
+    }
+}
+
+impl SceneLight for PointLight {
+    fn position_and_direction(&self) -> (Vec3, Vec3) {
+        (self.position, Vec3::zero())
+    }
+
+    fn set_position_and_direction(&mut self, position: Vec3, _direction: Vec3) {
+        self.position = position;
+    }
+}
+
+impl SceneLight for SpotLight {
+    fn position_and_direction(&self) -> (Vec3, Vec3) {
+        (self.position, self.direction)
+    }
+
+    fn set_position_and_direction(&mut self, position: Vec3, direction: Vec3) {
+        self.position = position;
+        self.direction = direction;
+    }
+}
+
+///
+/// A reference to a node in a [SceneGraph].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+struct AttachedLight {
+    light: Box<dyn SceneLight>,
+    position: Vec3,
+    direction: Vec3,
+}
+
+struct AttachedCamera {
+    camera: Camera,
+    position: Vec3,
+    target: Vec3,
+    up: Vec3,
+}
+
+struct SceneNode {
+    name: String,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    transformation: Mat4,
+    animation_transformation: Mat4,
+    world_transformation: Mat4,
+    animations: Vec<(Option<String>, KeyFrames)>,
+    objects: Vec<Box<dyn SceneObject>>,
+    lights: Vec<AttachedLight>,
+    cameras: Vec<AttachedCamera>,
+}
+
+///
+/// A hierarchy of nodes where each node has a transformation relative to its parent node and
+/// can have [SceneObject]s, [SceneLight]s and [Camera]s attached.
+/// The objects, lights and cameras are defined in the local space of the node they are attached to,
+/// so transforming a node also transforms everything attached to that node and all of its descendants.
+///
+/// A reference to a scene graph can be used in a render call, for example [RenderTarget::render], which renders all of the attached objects.
+///
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Option<SceneNode>>,
+    roots: Vec<NodeId>,
+    animation: Option<Option<String>>,
+}
+
+impl SceneGraph {
+    ///
+    /// Creates a new empty scene graph.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Constructs a scene graph with the same node hierarchy as the given [CpuScene], for example loaded from a glTF file.
+    /// A [Gm] with a [Mesh] as geometry and a [material] type specified by the generic parameter is attached to each node that contains a [CpuMesh].
+    /// If the scene contains animations, the first animation is chosen, see [SceneGraph::choose_animation].
+    ///
+    pub fn from_cpu_scene<M: Material + FromCpuMaterial + Clone + Default + 'static>(
+        context: &Context,
+        cpu_scene: &CpuScene,
+    ) -> Result<Self, RendererError> {
+        let materials = cpu_scene
+            .materials
+            .iter()
+            .map(|m| M::from_cpu_material(context, m))
+            .collect::<Vec<_>>();
+        let mut scene_graph = Self::new();
+        let mut stack = cpu_scene
+            .children
+            .iter()
+            .rev()
+            .map(|child| (None, child))
+            .collect::<Vec<_>>();
+        while let Some((parent, cpu_node)) = stack.pop() {
+            let node = scene_graph.add_node(parent, &cpu_node.name, cpu_node.transformation);
+            scene_graph.node_mut(node).animations = cpu_node.animations.clone();
+            if let Some(CpuGeometry::Triangles(geometry)) = &cpu_node.geometry {
+                let material = if let Some(material_index) = cpu_node.material_index {
+                    materials
+                        .get(material_index)
+                        .ok_or_else(|| {
+                            RendererError::MissingMaterial(
+                                material_index.to_string(),
+                                cpu_node.name.clone(),
+                            )
+                        })?
+                        .clone()
+                } else {
+                    M::default()
+                };
+                scene_graph.add_object(
+                    node,
+                    Gm {
+                        geometry: Mesh::new(context, geometry),
+                        material,
+                    },
+                );
+            }
+            stack.extend(
+                cpu_node
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|child| (Some(node), child)),
+            );
+        }
+        if let Some(animation_name) = scene_graph.animations().first().cloned() {
+            scene_graph.choose_animation(animation_name.as_deref());
+        }
+        Ok(scene_graph)
+    }
+
+    ///
+    /// Adds a new node with the given name and transformation relative to the parent node and returns a reference to it.
+    /// If no parent is specified, the node is added as a root node, ie. the transformation is relative to world space.
+    ///
+    pub fn add_node(
+        &mut self,
+        parent: Option<NodeId>,
+        name: impl Into<String>,
+        transformation: Mat4,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(SceneNode {
+            name: name.into(),
+            parent: None,
+            children: Vec::new(),
+            transformation,
+            animation_transformation: Mat4::identity(),
+            world_transformation: Mat4::identity(),
+            animations: Vec::new(),
+            objects: Vec::new(),
+            lights: Vec::new(),
+            cameras: Vec::new(),
+        }));
+        self.roots.push(id);
+        self.set_parent(id, parent);
+        id
+    }
+
+    ///
+    /// Removes the given node and all of its descendants together with everything attached to them.
+    ///
+    pub fn remove_node(&mut self, node: NodeId) {
+        self.detach(node);
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            if let Some(n) = self.nodes[id.0].take() {
+                stack.extend(n.children);
+            }
+        }
+    }
+
+    ///
+    /// Moves the given node, together with all of its descendants, to the given parent or makes it a root node if no parent is specified.
+    /// The transformation of the node relative to its parent is kept.
+    ///
+    /// # Panic
+    /// Will panic if the parent is the node itself or one of its descendants.
+    ///
+    pub fn set_parent(&mut self, node: NodeId, parent: Option<NodeId>) {
+        if let Some(parent) = parent {
+            assert!(
+                !self.is_ancestor(node, parent),
+                "a node cannot be moved to one of its descendants"
+            );
+        }
+        self.detach(node);
+        self.node_mut(node).parent = parent;
+        if let Some(parent) = parent {
+            self.node_mut(parent).children.push(node);
+        } else {
+            self.roots.push(node);
+        }
+        self.update_world_transformations(node);
+    }
+
+    ///
+    /// Returns the parent of the given node or `None` if it is a root node.
+    ///
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.node(node).parent
+    }
+
+    ///
+    /// Returns the children of the given node.
+    ///
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.node(node).children
+    }
+
+    ///
+    /// Returns the root nodes, ie. the nodes without a parent.
+    ///
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    ///
+    /// Returns the name of the given node.
+    ///
+    pub fn name(&self, node: NodeId) -> &str {
+        &self.node(node).name
+    }
+
+    ///
+    /// Returns the first node with the given name, if any.
+    ///
+    pub fn find_node(&self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|n| n.as_ref().map(|n| n.name == name).unwrap_or(false))
+            .map(NodeId)
+    }
+
+    ///
+    /// Returns the transformation of the given node relative to its parent.
+    ///
+    pub fn transformation(&self, node: NodeId) -> Mat4 {
+        self.node(node).transformation
+    }
+
+    ///
+    /// Set the transformation of the given node relative to its parent.
+    /// This also updates the world transformation of the node and of all of its descendants as well as everything attached to them.
+    ///
+    pub fn set_transformation(&mut self, node: NodeId, transformation: Mat4) {
+        self.node_mut(node).transformation = transformation;
+        self.update_world_transformations(node);
+    }
+
+    ///
+    /// Returns the local to world transformation of the given node, ie. the transformations of the node and all of its ancestors combined,
+    /// including the current animation transformation, see [SceneGraph::animate].
+    ///
+    pub fn world_transformation(&self, node: NodeId) -> Mat4 {
+        self.node(node).world_transformation
+    }
+
+    ///
+    /// Attaches the object to the given node.
+    /// The object is defined in the local space of the node and its transformation is overwritten whenever the node is transformed.
+    ///
+    pub fn add_object(&mut self, node: NodeId, mut object: impl SceneObject + 'static) {
+        object.set_transformation(self.node(node).world_transformation);
+        self.node_mut(node).objects.push(Box::new(object));
+    }
+
+    ///
+    /// Returns the objects attached to the given node.
+    ///
+    pub fn objects(&self, node: NodeId) -> &[Box<dyn SceneObject>] {
+        &self.node(node).objects
+    }
+
+    ///
+    /// Returns mutable references to the objects attached to the given node, for example to change the material.
+    /// Note that the transformation of the objects is overwritten whenever the node is transformed.
+    ///
+    pub fn objects_mut(&mut self, node: NodeId) -> &mut [Box<dyn SceneObject>] {
+        &mut self.node_mut(node).objects
+    }
+
+    ///
+    /// Attaches the light to the given node.
+    /// The position and direction of the light are defined in the local space of the node.
+    ///
+    pub fn add_light(&mut self, node: NodeId, light: impl SceneLight + 'static) {
+        let (position, direction) = light.position_and_direction();
+        let mut attached = AttachedLight {
+            light: Box::new(light),
+            position,
+            direction,
+        };
+        attached.update(&self.node(node).world_transformation);
+        self.node_mut(node).lights.push(attached);
+    }
+
+    ///
+    /// Returns all of the lights attached to any node in this scene graph in world space. Use these lights in a render call, for example [RenderTarget::render].
+    ///
+    pub fn lights(&self) -> Vec<&dyn Light> {
+        self.nodes
+            .iter()
+            .flatten()
+            .flat_map(|n| n.lights.iter().map(|l| &*l.light as &dyn Light))
+            .collect()
+    }
+
+    ///
+    /// Attaches the camera to the given node.
+    /// The position, target and up direction of the camera are defined in the local space of the node.
+    ///
+    pub fn add_camera(&mut self, node: NodeId, camera: Camera) {
+        let mut attached = AttachedCamera {
+            position: *camera.position(),
+            target: *camera.target(),
+            up: *camera.up(),
+            camera,
+        };
+        attached.update(&self.node(node).world_transformation);
+        self.node_mut(node).cameras.push(attached);
+    }
+
+    ///
+    /// Returns the cameras attached to the given node in world space.
+    ///
+    pub fn cameras(&self, node: NodeId) -> Vec<&Camera> {
+        self.node(node).cameras.iter().map(|c| &c.camera).collect()
+    }
+
+    ///
+    /// Returns mutable references to the cameras attached to the given node, for example to set the viewport.
+    /// Note that the view of the cameras is overwritten whenever the node is transformed.
+    ///
+    pub fn cameras_mut(&mut self, node: NodeId) -> Vec<&mut Camera> {
+        self.node_mut(node)
+            .cameras
+            .iter_mut()
+            .map(|c| &mut c.camera)
+            .collect()
+    }
+
+    ///
+    /// Returns the axis aligned bounding box in world space of everything attached to the given node and all of its descendants.
+    ///
+    pub fn node_aabb(&self, node: NodeId) -> AxisAlignedBoundingBox {
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let n = self.node(id);
+            for object in n.objects.iter() {
+                aabb.expand_with_aabb(&object.aabb());
+            }
+            stack.extend(n.children.iter().copied());
+        }
+        aabb
+    }
+
+    ///
+    /// Returns the axis aligned bounding box in world space of all objects in this scene graph.
+    ///
+    pub fn aabb(&self) -> AxisAlignedBoundingBox {
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+        for object in self {
+            aabb.expand_with_aabb(&object.aabb());
+        }
+        aabb
+    }
+
+    ///
+    /// Returns a list of unique names for the animations in this scene graph. Use these names as input to [Self::choose_animation].
+    ///
+    pub fn animations(&self) -> Vec<Option<String>> {
+        let mut names = Vec::new();
+        for (name, _) in self
+            .nodes
+            .iter()
+            .flatten()
+            .flat_map(|n| n.animations.iter())
+        {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    ///
+    /// Specifies the animation to use when [Self::animate] is called. Use the [Self::animations] method to get a list of possible animations.
+    ///
+    pub fn choose_animation(&mut self, animation_name: Option<&str>) {
+        self.animation = Some(animation_name.map(|n| n.to_string()));
+    }
+
+    ///
+    /// Evaluates the chosen animation, see [Self::choose_animation], at the given time and updates the world transformations accordingly.
+    /// Also calls [Geometry::animate] on all of the attached objects.
+    ///
+    pub fn animate(&mut self, time: f32) {
+        if let Some(animation_name) = &self.animation {
+            for node in self.nodes.iter_mut().flatten() {
+                node.animation_transformation = node
+                    .animations
+                    .iter()
+                    .find(|(name, _)| name == animation_name)
+                    .map(|(_, key_frames)| key_frames.transformation(time))
+                    .unwrap_or(Mat4::identity());
+            }
+            for root in self.roots.clone() {
+                self.update_world_transformations(root);
+            }
+        }
+        for node in self.nodes.iter_mut().flatten() {
+            for object in node.objects.iter_mut() {
+                object.animate(time);
+            }
+        }
+    }
+
+    fn node(&self, node: NodeId) -> &SceneNode {
+        self.nodes[node.0]
+            .as_ref()
+            .expect("the node has been removed from the scene graph")
+    }
+
+    fn node_mut(&mut self, node: NodeId) -> &mut SceneNode {
+        self.nodes[node.0]
+            .as_mut()
+            .expect("the node has been removed from the scene graph")
+    }
+
+    fn is_ancestor(&self, ancestor: NodeId, node: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.node(id).parent;
+        }
+        false
+    }
+
+    ///
+    /// Removes the node from the children of its parent or from the roots.
+    ///
+    fn detach(&mut self, node: NodeId) {
+        if let Some(parent) = self.node(node).parent {
+            self.node_mut(parent).children.retain(|c| *c != node);
+        } else {
+            self.roots.retain(|r| *r != node);
+        }
+    }
+
+    ///
+    /// Computes the world transformation of the given node and its descendants from the parent world transformation
+    /// and applies it to everything attached to them.
+    ///
+    fn update_world_transformations(&mut self, node: NodeId) {
+        let mut stack = vec![node];
+        while let Some(id) = stack.pop() {
+            let parent_transformation = self
+                .node(id)
+                .parent
+                .map(|p| self.node(p).world_transformation)
+                .unwrap_or(Mat4::identity());
+            let n = self.node_mut(id);
+            n.world_transformation =
+                parent_transformation * n.transformation * n.animation_transformation;
+            let world_transformation = n.world_transformation;
+            for object in n.objects.iter_mut() {
+                object.set_transformation(world_transformation);
+            }
+            for light in n.lights.iter_mut() {
+                light.update(&world_transformation);
+            }
+            for camera in n.cameras.iter_mut() {
+                camera.update(&world_transformation);
+            }
+            stack.extend(n.children.iter().copied());
+        }
+    }
+}
+
+impl AttachedLight {
+    fn update(&mut self, transformation: &Mat4) {
+        let position = (transformation * self.position.extend(1.0)).truncate();
+        let direction = (transformation * self.direction.extend(0.0)).truncate();
+        let direction = if direction.magnitude2() > 0.0 {
+            direction.normalize()
+        } else {
+            direction
+        };
+        self.light.set_position_and_direction(position, direction);
+    }
+}
+
+impl AttachedCamera {
+    fn update(&mut self, transformation: &Mat4) {
+        let position = (transformation * self.position.extend(1.0)).truncate();
+        let target = (transformation * self.target.extend(1.0)).truncate();
+        let up = (transformation * self.up.extend(0.0)).truncate();
+        self.camera.set_view(position, target, up);
+    }
+}
+
+impl<'a> IntoIterator for &'a SceneGraph {
+    type Item = &'a dyn Object;
+    type IntoIter = std::vec::IntoIter<&'a dyn Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes
+            .iter()
+            .flatten()
+            .flat_map(|n| n.objects.iter().map(|o| &**o as &dyn Object))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
@@ -0,0 +1,134 @@
+uniform mat4 viewProjection;
+uniform vec3 cameraPosition;
+uniform float metallic;
+uniform float roughness;
+
+uniform int framesPerSide;
+uniform float imposterRadius;
+uniform sampler2DArray albedoFrames;
+uniform sampler2DArray normalDepthFrames;
+
+in vec3 pos;
+flat in vec3 imposter_center;
+
+layout (location = 0) out vec4 outColor;
+
+vec2 octahedral_encode(vec3 dir)
+{
+    dir /= abs(dir.x) + abs(dir.y) + abs(dir.z);
+#ifdef HEMISPHERE
+    return 0.5 + 0.5 * vec2(dir.x + dir.z, dir.x - dir.z);
+#else
+    vec2 p = dir.xz;
+    if (dir.y < 0.0) {
+        p = (1.0 - abs(p.yx)) * vec2(p.x >= 0.0 ? 1.0 : -1.0, p.y >= 0.0 ? 1.0 : -1.0);
+    }
+    return 0.5 + 0.5 * p;
+#endif
+}
+
+vec3 octahedral_decode(vec2 uv)
+{
+    vec2 p = 2.0 * uv - 1.0;
+#ifdef HEMISPHERE
+    vec2 xz = 0.5 * vec2(p.x + p.y, p.x - p.y);
+    return normalize(vec3(xz.x, 1.0 - abs(xz.x) - abs(xz.y), xz.y));
+#else
+    vec3 d = vec3(p.x, 1.0 - abs(p.x) - abs(p.y), p.y);
+    if (d.y < 0.0) {
+        d.xz = (1.0 - abs(d.zx)) * vec2(d.x >= 0.0 ? 1.0 : -1.0, d.z >= 0.0 ? 1.0 : -1.0);
+    }
+    return normalize(d);
+#endif
+}
+
+void frame_basis(vec3 direction, out vec3 right, out vec3 up)
+{
+    right = cross(vec3(0.0, 1.0, 0.0), direction);
+    if (dot(right, right) < 1.0e-6) {
+        right = vec3(1.0, 0.0, 0.0);
+    }
+    right = normalize(right);
+    up = cross(direction, right);
+}
+
+// Finds the point where the view ray hits the surface captured in the given frame and returns the albedo, normal and position at that point.
+void sample_frame(vec2 frame, vec3 view_dir, out vec4 albedo, out vec3 normal, out vec3 position)
+{
+    float layer = frame.y * float(framesPerSide) + frame.x;
+    vec3 direction = octahedral_decode(frame / float(framesPerSide - 1));
+    vec3 right;
+    vec3 up;
+    frame_basis(direction, right, up);
+
+    vec3 origin = cameraPosition - imposter_center;
+    float denominator = min(dot(view_dir, direction), -0.001);
+    float height = 0.0;
+    vec2 uv;
+    vec4 normal_depth;
+    for (int i = 0; i < 3; i++) {
+        float t = (height - dot(origin, direction)) / denominator;
+        vec3 p = origin + t * view_dir;
+        uv = 0.5 + 0.5 * vec2(dot(p, right), dot(p, up)) / imposterRadius;
+        normal_depth = texture(normalDepthFrames, vec3(uv, layer));
+        height = (2.0 * normal_depth.a - 1.0) * imposterRadius;
+    }
+    albedo = texture(albedoFrames, vec3(uv, layer));
+    normal = 2.0 * normal_depth.xyz - 1.0;
+    position = imposter_center + imposterRadius * ((2.0 * uv.x - 1.0) * right + (2.0 * uv.y - 1.0) * up) + height * direction;
+}
+
+void main()
+{
+    vec3 view_dir = normalize(pos - cameraPosition);
+    vec3 to_camera = normalize(cameraPosition - imposter_center);
+#ifdef HEMISPHERE
+    to_camera = normalize(vec3(to_camera.x, max(to_camera.y, 0.0), to_camera.z));
+#endif
+
+    // Find the three nearest frames and their weights
+    vec2 grid = octahedral_encode(to_camera) * float(framesPerSide - 1);
+    vec2 cell = min(floor(grid), vec2(float(framesPerSide - 2)));
+    vec2 f = grid - cell;
+    vec2 frames[3];
+    vec3 weights;
+    if (f.x + f.y < 1.0) {
+        frames[0] = cell;
+        frames[1] = cell + vec2(1.0, 0.0);
+        frames[2] = cell + vec2(0.0, 1.0);
+        weights = vec3(1.0 - f.x - f.y, f.x, f.y);
+    } else {
+        frames[0] = cell + vec2(1.0, 1.0);
+        frames[1] = cell + vec2(0.0, 1.0);
+        frames[2] = cell + vec2(1.0, 0.0);
+        weights = vec3(f.x + f.y - 1.0, 1.0 - f.x, 1.0 - f.y);
+    }
+
+    vec4 albedo = vec4(0.0);
+    vec3 normal = vec3(0.0);
+    vec3 position = vec3(0.0);
+    float position_weight = 0.0;
+    for (int i = 0; i < 3; i++) {
+        vec4 a;
+        vec3 n;
+        vec3 p;
+        sample_frame(frames[i], view_dir, a, n, p);
+        albedo += weights[i] * a;
+        normal += weights[i] * a.a * n;
+        position += weights[i] * a.a * p;
+        position_weight += weights[i] * a.a;
+    }
+    if (albedo.a < 0.5) {
+        discard;
+    }
+    normal = normalize(normal);
+    position /= position_weight;
+
+    vec4 clip_pos = viewProjection * vec4(position, 1.0);
+    gl_FragDepth = 0.5 + 0.5 * clip_pos.z / clip_pos.w;
+
+    outColor.rgb = calculate_lighting(cameraPosition, albedo.rgb / albedo.a, position, normal, metallic, roughness, 1.0);
+    outColor.rgb = tone_mapping(outColor.rgb);
+    outColor.rgb = color_mapping(outColor.rgb);
+    outColor.a = 1.0;
+}
@@ -0,0 +1,33 @@
+uniform mat4 viewProjection;
+uniform vec3 eye;
+uniform float radius;
+
+in vec3 center;
+in vec3 position;
+
+out vec3 pos;
+flat out vec3 imposter_center;
+
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
+void main()
+{
+#ifdef USE_INSTANCE_ID
+    instanceId = gl_InstanceID;
+#endif
+    vec3 z = eye - center;
+    float dist = length(z);
+    z /= dist;
+    vec3 t = abs(z.y) > 0.99 ? vec3(1.0, 0.0, 0.0) : vec3(0.0, 1.0, 0.0);
+    vec3 x = normalize(cross(t, z));
+    vec3 y = cross(z, x);
+
+    // Make sure the quad covers the bounding sphere when seen in perspective
+    float size = radius / sqrt(max(1.0 - radius * radius / (dist * dist), 0.01));
+
+    imposter_center = center;
+    pos = center + size * (position.x * x + position.y * y);
+    gl_Position = viewProjection * vec4(pos, 1.0);
+}
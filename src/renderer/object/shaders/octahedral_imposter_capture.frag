@@ -0,0 +1,15 @@
+uniform vec3 frameCenter;
+uniform float frameRadius;
+uniform vec3 frameDirection;
+
+in vec3 pos;
+in vec3 nor;
+
+layout (location = 0) out vec4 outColor;
+
+void main()
+{
+    vec3 normal = normalize(gl_FrontFacing ? nor : -nor);
+    float depth = dot(pos - frameCenter, frameDirection) / frameRadius;
+    outColor = vec4(0.5 + 0.5 * normal, 0.5 + 0.5 * depth);
+}
@@ -0,0 +1,114 @@
+#ifdef USE_POLYGONS
+uniform sampler2D polygonPoints;
+#endif
+
+in vec2 localPosition;
+flat in vec2 extent;
+flat in vec4 parameters;
+flat in vec4 fillColor;
+flat in vec4 strokeColor;
+flat in float strokeWidth;
+
+layout (location = 0) out vec4 outColor;
+
+float sdRoundedRectangle(vec2 p, vec2 halfSize, float radius)
+{
+    vec2 q = abs(p) - halfSize + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
+float sdEllipse(vec2 p, vec2 radius)
+{
+    // Approximation which is accurate close to the edge
+    float k0 = length(p / radius);
+    float k1 = length(p / (radius * radius));
+    return k1 > 0.0 ? k0 * (k0 - 1.0) / k1 : -min(radius.x, radius.y);
+}
+
+float sdArc(vec2 p, float radius, float halfAperture, float halfThickness)
+{
+    vec2 sc = vec2(sin(halfAperture), cos(halfAperture));
+    p.x = abs(p.x);
+    return (sc.y * p.x > sc.x * p.y ? length(p - sc * radius) : abs(length(p) - radius)) - halfThickness;
+}
+
+float sdCapsule(vec2 p, float halfLength, float radius)
+{
+    p.x = max(abs(p.x) - halfLength, 0.0);
+    return length(p) - radius;
+}
+
+#ifdef USE_POLYGONS
+vec2 polygonPoint(int index)
+{
+    // The rows of the texture are flipped when uploaded
+    ivec2 size = textureSize(polygonPoints, 0);
+    return texelFetch(polygonPoints, ivec2(index % size.x, size.y - 1 - index / size.x), 0).xy;
+}
+
+float sdPolygon(vec2 p, int offset, int count)
+{
+    vec2 v0 = polygonPoint(offset);
+    float d = dot(p - v0, p - v0);
+    float s = 1.0;
+    vec2 vj = polygonPoint(offset + count - 1);
+    for (int i = 0; i < count; i++)
+    {
+        vec2 vi = polygonPoint(offset + i);
+        vec2 e = vj - vi;
+        vec2 w = p - vi;
+        vec2 b = w - e * clamp(dot(w, e) / max(dot(e, e), 1e-10), 0.0, 1.0);
+        d = min(d, dot(b, b));
+        bvec3 c = bvec3(p.y >= vi.y, p.y < vj.y, e.x * w.y > e.y * w.x);
+        if (all(c) || all(not(c))) {
+            s = -s;
+        }
+        vj = vi;
+    }
+    return s * sqrt(d);
+}
+#endif
+
+float signedDistance(vec2 p)
+{
+    int shape = int(parameters.x + 0.5);
+    if (shape == 0) {
+        return sdRoundedRectangle(p, extent, parameters.y);
+    } else if (shape == 1) {
+        return length(p) - parameters.y;
+    } else if (shape == 2) {
+        return sdEllipse(p, extent);
+    } else if (shape == 3) {
+        return sdArc(p, parameters.y, parameters.z, parameters.w);
+    } else if (shape == 4) {
+#ifdef USE_POLYGONS
+        int count = int(parameters.z + 0.5);
+        if (count > 2) {
+            return sdPolygon(p, int(parameters.y + 0.5), count);
+        }
+#endif
+        return 1e10;
+    }
+    return sdCapsule(p, parameters.y, parameters.z);
+}
+
+void main()
+{
+    float d = signedDistance(localPosition);
+
+    // The width of a pixel in the local space of the shape
+    float w = max(fwidth(d), 1e-5);
+
+    float fillCoverage = clamp(0.5 - d / w, 0.0, 1.0);
+    float strokeCoverage = strokeWidth > 0.0 ? clamp(0.5 - (abs(d) - 0.5 * strokeWidth) / w, 0.0, 1.0) : 0.0;
+
+    // Composite the stroke on top of the fill using premultiplied alpha
+    vec4 fill = vec4(fillColor.rgb, 1.0) * fillColor.a * fillCoverage;
+    vec4 stroke = vec4(strokeColor.rgb, 1.0) * strokeColor.a * strokeCoverage;
+    vec4 color = stroke + fill * (1.0 - stroke.a);
+    if (color.a < 0.001) {
+        discard;
+    }
+
+    outColor = vec4(color_mapping(color.rgb / color.a), color.a);
+}
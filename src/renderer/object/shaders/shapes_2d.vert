@@ -0,0 +1,36 @@
+uniform mat4 viewProjection;
+uniform float pixelSize;
+
+in vec2 position;
+
+in vec3 shape_transform_row1;
+in vec3 shape_transform_row2;
+in vec2 shape_extent;
+in vec4 shape_parameters;
+in vec4 fill_color;
+in vec4 stroke_color;
+in float stroke_width;
+
+out vec2 localPosition;
+flat out vec2 extent;
+flat out vec4 parameters;
+flat out vec4 fillColor;
+flat out vec4 strokeColor;
+flat out float strokeWidth;
+
+void main()
+{
+    // Make room for the stroke and the anti-aliasing
+    vec2 margin = vec2(0.5 * stroke_width + 2.0 * pixelSize);
+    localPosition = position * (shape_extent + margin);
+
+    vec3 p = vec3(localPosition, 1.0);
+    vec2 worldPosition = vec2(dot(shape_transform_row1, p), dot(shape_transform_row2, p));
+
+    extent = shape_extent;
+    parameters = shape_parameters;
+    fillColor = fill_color;
+    strokeColor = stroke_color;
+    strokeWidth = stroke_width;
+    gl_Position = viewProjection * vec4(worldPosition, 0.0, 1.0);
+}
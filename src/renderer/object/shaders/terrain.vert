@@ -6,6 +6,10 @@ out vec3 pos;
 out vec2 uvs;
 out vec4 col;
 
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
 #ifdef USE_NORMALS
 
 in vec3 normal;
@@ -17,6 +21,9 @@ out vec3 bitang;
 
 void main()
 {
+#ifdef USE_INSTANCE_ID
+    instanceId = 0;
+#endif
     vec4 worldPos = vec4(position, 1.);
     pos = worldPos.xyz;
     uvs = worldPos.xz;
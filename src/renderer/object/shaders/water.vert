@@ -15,8 +15,15 @@ out vec3 nor;
 out vec3 pos;
 out vec4 col;
 
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
 void main()
 {
+#ifdef USE_INSTANCE_ID
+    instanceId = 0;
+#endif
     pos = position + offset;
     nor = vec3(0., 1., 0.);
     
@@ -0,0 +1,542 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// A 2D shape which is rendered as a signed distance field by [Shapes2D].
+/// All positions and sizes are in world space units, which is pixels when using a camera created by [Camera::new_2d].
+///
+#[derive(Clone, Debug)]
+pub enum Shape2D {
+    /// A rectangle with rounded corners. Use a corner radius of zero to get sharp corners.
+    Rectangle {
+        /// The center of the rectangle.
+        center: PhysicalPoint,
+        /// The width of the rectangle.
+        width: f32,
+        /// The height of the rectangle.
+        height: f32,
+        /// The rotation of the rectangle around its center.
+        rotation: Radians,
+        /// The radius of the rounded corners.
+        corner_radius: f32,
+    },
+    /// A circle.
+    Circle {
+        /// The center of the circle.
+        center: PhysicalPoint,
+        /// The radius of the circle.
+        radius: f32,
+    },
+    /// An ellipse.
+    Ellipse {
+        /// The center of the ellipse.
+        center: PhysicalPoint,
+        /// The radius of the ellipse along the (rotated) x-axis.
+        radius_x: f32,
+        /// The radius of the ellipse along the (rotated) y-axis.
+        radius_y: f32,
+        /// The rotation of the ellipse around its center.
+        rotation: Radians,
+    },
+    /// A part of a ring going counterclockwise from the start angle to the end angle, where an angle of zero is along the x-axis.
+    /// If the end angle is smaller than the start angle, the arc wraps around the angle zero, ie. a multiple of 2π is added to the end angle,
+    /// so an arc from 270 to 90 degrees is the right half of the ring. An arc spanning 2π or more is a full ring.
+    Arc {
+        /// The center of the circle that the arc is part of.
+        center: PhysicalPoint,
+        /// The radius of the circle that the arc is part of.
+        radius: f32,
+        /// The angle where the arc starts.
+        start_angle: Radians,
+        /// The angle where the arc ends.
+        end_angle: Radians,
+        /// The thickness of the arc.
+        thickness: f32,
+    },
+    /// A closed polygon defined by its corners. The polygon does not have to be convex.
+    Polygon {
+        /// The corners of the polygon.
+        points: Vec<PhysicalPoint>,
+    },
+    /// A line segment with round ends.
+    Capsule {
+        /// The start point of the line segment.
+        start: PhysicalPoint,
+        /// The end point of the line segment.
+        end: PhysicalPoint,
+        /// The radius of the capsule, ie. half the thickness.
+        radius: f32,
+    },
+}
+
+///
+/// Defines how a [Shape2D] is filled and stroked.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeStyle {
+    /// The color of the inside of the shape. Use a fully transparent color to only draw the outline.
+    pub fill_color: Srgba,
+    /// The color of the outline of the shape.
+    pub stroke_color: Srgba,
+    /// The width of the outline of the shape which is centered on the edge of the shape. Use zero to not draw the outline.
+    pub stroke_width: f32,
+}
+
+impl Default for ShapeStyle {
+    fn default() -> Self {
+        Self {
+            fill_color: Srgba::WHITE,
+            stroke_color: Srgba::BLACK,
+            stroke_width: 0.0,
+        }
+    }
+}
+
+///
+/// A set of 2D shapes, see [Shape2D], which are rendered as signed distance fields, meaning that the edges are anti-aliased
+/// and that each shape can be filled and outlined (see [ShapeStyle]).
+/// All of the shapes are rendered in one draw call in the order they are given, ie. later shapes are drawn on top of earlier shapes.
+/// The shapes are placed in the xy-plane and are meant to be rendered using a camera created by [Camera::new_2d].
+///
+pub struct Shapes2D {
+    context: Context,
+    geometry: Shapes2DGeometry,
+    material: Shapes2DMaterial,
+}
+
+impl Shapes2D {
+    ///
+    /// Constructs a new set of 2D shapes with the given style.
+    ///
+    pub fn new(context: &Context, shapes: &[(Shape2D, ShapeStyle)]) -> Self {
+        let mut shapes_2d = Self {
+            context: context.clone(),
+            geometry: Shapes2DGeometry {
+                context: context.clone(),
+                position_buffer: VertexBuffer::new_with_data(
+                    context,
+                    &[
+                        vec2(-1.0, -1.0),
+                        vec2(1.0, -1.0),
+                        vec2(1.0, 1.0),
+                        vec2(1.0, 1.0),
+                        vec2(-1.0, 1.0),
+                        vec2(-1.0, -1.0),
+                    ],
+                ),
+                instance_buffers: Vec::new(),
+                instance_count: 0,
+                aabb: AxisAlignedBoundingBox::EMPTY,
+            },
+            material: Shapes2DMaterial {
+                polygon_texture: None,
+            },
+        };
+        shapes_2d.set_shapes(shapes);
+        shapes_2d
+    }
+
+    ///
+    /// Replaces all of the shapes with the given shapes.
+    ///
+    pub fn set_shapes(&mut self, shapes: &[(Shape2D, ShapeStyle)]) {
+        let mut transform_row1 = Vec::with_capacity(shapes.len());
+        let mut transform_row2 = Vec::with_capacity(shapes.len());
+        let mut extents = Vec::with_capacity(shapes.len());
+        let mut parameters = Vec::with_capacity(shapes.len());
+        let mut fill_colors = Vec::with_capacity(shapes.len());
+        let mut stroke_colors = Vec::with_capacity(shapes.len());
+        let mut stroke_widths = Vec::with_capacity(shapes.len());
+        let mut polygon_points: Vec<Vec2> = Vec::new();
+        let mut aabb = AxisAlignedBoundingBox::EMPTY;
+
+        for (shape, style) in shapes {
+            // The transformation from the local space of the shape to world space, the half size of the shape in local space
+            // and the parameters of the shape, where the first parameter is the type of shape.
+            let (transformation, extent, shape_parameters) = match shape {
+                Shape2D::Rectangle {
+                    center,
+                    width,
+                    height,
+                    rotation,
+                    corner_radius,
+                } => {
+                    let extent = vec2(0.5 * width.abs(), 0.5 * height.abs());
+                    (
+                        local_to_world(*center, *rotation),
+                        extent,
+                        vec4(
+                            0.0,
+                            corner_radius.clamp(0.0, extent.x.min(extent.y)),
+                            0.0,
+                            0.0,
+                        ),
+                    )
+                }
+                Shape2D::Circle { center, radius } => (
+                    local_to_world(*center, radians(0.0)),
+                    vec2(radius.abs(), radius.abs()),
+                    vec4(1.0, radius.abs(), 0.0, 0.0),
+                ),
+                Shape2D::Ellipse {
+                    center,
+                    radius_x,
+                    radius_y,
+                    rotation,
+                } => (
+                    local_to_world(*center, *rotation),
+                    vec2(radius_x.abs(), radius_y.abs()),
+                    vec4(2.0, 0.0, 0.0, 0.0),
+                ),
+                Shape2D::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    end_angle,
+                    thickness,
+                } => {
+                    // The arc wraps around when the end angle is smaller than the start angle
+                    let mut sweep = (*end_angle - *start_angle).0;
+                    if sweep < 0.0 {
+                        sweep = sweep.rem_euclid(2.0 * std::f32::consts::PI);
+                    }
+                    // The arc is placed symmetrically around the local y-axis
+                    let middle_angle = *start_angle + radians(0.5 * sweep);
+                    let half_aperture = (0.5 * sweep).clamp(0.0, std::f32::consts::PI);
+                    let extent = radius.abs() + 0.5 * thickness.abs();
+                    (
+                        local_to_world(*center, middle_angle - radians(0.5 * std::f32::consts::PI)),
+                        vec2(extent, extent),
+                        vec4(3.0, radius.abs(), half_aperture, 0.5 * thickness.abs()),
+                    )
+                }
+                Shape2D::Polygon { points } => {
+                    let (min, max) = points.iter().fold(
+                        (
+                            vec2(f32::INFINITY, f32::INFINITY),
+                            vec2(f32::NEG_INFINITY, f32::NEG_INFINITY),
+                        ),
+                        |(min, max), p| {
+                            (
+                                vec2(min.x.min(p.x), min.y.min(p.y)),
+                                vec2(max.x.max(p.x), max.y.max(p.y)),
+                            )
+                        },
+                    );
+                    let center = if points.is_empty() {
+                        vec2(0.0, 0.0)
+                    } else {
+                        0.5 * (min + max)
+                    };
+                    let offset = polygon_points.len();
+                    polygon_points.extend(points.iter().map(|p| Vec2::from(*p) - center));
+                    (
+                        local_to_world(center.into(), radians(0.0)),
+                        if points.is_empty() {
+                            vec2(0.0, 0.0)
+                        } else {
+                            0.5 * (max - min)
+                        },
+                        vec4(4.0, offset as f32, points.len() as f32, 0.0),
+                    )
+                }
+                Shape2D::Capsule { start, end, radius } => {
+                    let start = Vec2::from(*start);
+                    let end = Vec2::from(*end);
+                    let direction = end - start;
+                    let half_length = 0.5 * direction.magnitude();
+                    (
+                        local_to_world(
+                            (0.5 * (start + end)).into(),
+                            radians(direction.y.atan2(direction.x)),
+                        ),
+                        vec2(half_length + radius.abs(), radius.abs()),
+                        vec4(5.0, half_length, radius.abs(), 0.0),
+                    )
+                }
+            };
+            transform_row1.push(transformation.x);
+            transform_row2.push(transformation.y);
+            extents.push(extent);
+            parameters.push(shape_parameters);
+            fill_colors.push(style.fill_color.to_linear_srgb());
+            stroke_colors.push(style.stroke_color.to_linear_srgb());
+            stroke_widths.push(style.stroke_width.max(0.0));
+
+            let e = extent + vec2(0.5, 0.5) * style.stroke_width.max(0.0);
+            for corner in [
+                vec2(-e.x, -e.y),
+                vec2(e.x, -e.y),
+                vec2(e.x, e.y),
+                vec2(-e.x, e.y),
+            ] {
+                let p = vec2(
+                    transformation.x.dot(corner.extend(1.0)),
+                    transformation.y.dot(corner.extend(1.0)),
+                );
+                aabb.expand(&[p.extend(0.0)]);
+            }
+        }
+
+        let context = &self.context;
+        self.geometry.instance_count = shapes.len() as u32;
+        self.geometry.aabb = aabb;
+        self.geometry.instance_buffers = vec![
+            (
+                "shape_transform_row1",
+                InstanceBuffer::new_with_data(context, &transform_row1),
+            ),
+            (
+                "shape_transform_row2",
+                InstanceBuffer::new_with_data(context, &transform_row2),
+            ),
+            (
+                "shape_extent",
+                InstanceBuffer::new_with_data(context, &extents),
+            ),
+            (
+                "shape_parameters",
+                InstanceBuffer::new_with_data(context, &parameters),
+            ),
+            (
+                "fill_color",
+                InstanceBuffer::new_with_data(context, &fill_colors),
+            ),
+            (
+                "stroke_color",
+                InstanceBuffer::new_with_data(context, &stroke_colors),
+            ),
+            (
+                "stroke_width",
+                InstanceBuffer::new_with_data(context, &stroke_widths),
+            ),
+        ];
+        self.material.polygon_texture = if polygon_points.is_empty() {
+            None
+        } else {
+            // The polygon points are stored in rows of a texture to not exceed the maximum texture size
+            let width = polygon_points.len().min(POLYGON_TEXTURE_WIDTH);
+            let height = polygon_points.len().div_ceil(width);
+            polygon_points.resize(width * height, vec2(0.0, 0.0));
+            let mut texture = Texture2D::new_empty::<[f32; 2]>(
+                context,
+                width as u32,
+                height as u32,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            );
+            texture.fill(
+                &polygon_points
+                    .iter()
+                    .map(|p| [p.x, p.y])
+                    .collect::<Vec<_>>(),
+            );
+            Some(texture)
+        };
+    }
+}
+
+const POLYGON_TEXTURE_WIDTH: usize = 1024;
+
+///
+/// Returns the first two rows of the 2D transformation from the local space of a shape to world space.
+///
+fn local_to_world(center: PhysicalPoint, rotation: Radians) -> Mat3 {
+    (Mat3::from_translation(center.into()) * Mat3::from_angle_z(rotation)).transpose()
+}
+
+impl<'a> IntoIterator for &'a Shapes2D {
+    type Item = &'a dyn Object;
+    type IntoIter = std::iter::Once<&'a dyn Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for Shapes2D {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        self.geometry
+            .draw(camera, program, render_states, attributes)
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        self.geometry.vertex_shader_source(required_attributes)
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        self.geometry.id(required_attributes)
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        self.geometry.render_with_material(material, camera, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        self.geometry
+            .render_with_effect(material, camera, lights, color_texture, depth_texture)
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.geometry.aabb()
+    }
+}
+
+impl Object for Shapes2D {
+    fn render(&self, camera: &Camera, lights: &[&dyn Light]) {
+        render_with_material(
+            &self.context,
+            camera,
+            &self.geometry,
+            &self.material,
+            lights,
+        )
+    }
+
+    fn material_type(&self) -> MaterialType {
+        self.material.material_type()
+    }
+}
+
+struct Shapes2DGeometry {
+    context: Context,
+    position_buffer: VertexBuffer,
+    instance_buffers: Vec<(&'static str, InstanceBuffer)>,
+    instance_count: u32,
+    aabb: AxisAlignedBoundingBox,
+}
+
+impl Geometry for Shapes2DGeometry {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        _attributes: FragmentAttributes,
+    ) {
+        if self.instance_count == 0 {
+            return;
+        }
+        // The size of a pixel in world space which is used to make room for the anti-aliasing.
+        // This assumes an orthographic camera, like the one created by Camera::new_2d.
+        let pixel_size = 2.0 / (camera.projection().y.y * camera.viewport().height as f32);
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform("pixelSize", pixel_size.abs());
+        program.use_vertex_attribute("position", &self.position_buffer);
+        for (name, buffer) in self.instance_buffers.iter() {
+            if program.requires_attribute(name) {
+                program.use_instance_attribute(name, buffer);
+            }
+        }
+        program.draw_arrays_instanced(render_states, camera.viewport(), 6, self.instance_count)
+    }
+
+    fn vertex_shader_source(&self, _required_attributes: FragmentAttributes) -> String {
+        include_str!("shaders/shapes_2d.vert").to_owned()
+    }
+
+    fn id(&self, _required_attributes: FragmentAttributes) -> u16 {
+        0b1u16 << 15 | 0b111u16
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, self, material, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        self.aabb
+    }
+}
+
+struct Shapes2DMaterial {
+    polygon_texture: Option<Texture2D>,
+}
+
+impl Material for Shapes2DMaterial {
+    fn id(&self) -> u16 {
+        if self.polygon_texture.is_some() {
+            0b1u16 << 15 | 0b1u16 << 4 | 0b100u16
+        } else {
+            0b1u16 << 15 | 0b1u16 << 4 | 0b101u16
+        }
+    }
+
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        let mut output = String::new();
+        if self.polygon_texture.is_some() {
+            output.push_str("#define USE_POLYGONS\n");
+        }
+        output.push_str(ColorMapping::fragment_shader_source());
+        output.push_str(include_str!("shaders/shapes_2d.frag"));
+        output
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes::NONE
+    }
+
+    fn use_uniforms(&self, program: &Program, camera: &Camera, _lights: &[&dyn Light]) {
+        camera.color_mapping.use_uniforms(program);
+        if let Some(texture) = &self.polygon_texture {
+            program.use_texture("polygonPoints", texture);
+        }
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            write_mask: WriteMask::COLOR,
+            depth_test: DepthTest::Always,
+            blend: Blend::TRANSPARENCY,
+            cull: Cull::None,
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Transparent
+    }
+}
@@ -338,14 +338,20 @@ impl TerrainPatch {
 
 impl Geometry for TerrainPatch {
     fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
-        if required_attributes.normal || required_attributes.tangents {
-            format!(
-                "#define USE_NORMALS\n{}",
-                include_str!("shaders/terrain.vert")
-            )
-        } else {
-            include_str!("shaders/terrain.vert").to_owned()
-        }
+        format!(
+            "{}{}{}",
+            if required_attributes.normal || required_attributes.tangents {
+                "#define USE_NORMALS\n"
+            } else {
+                ""
+            },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("shaders/terrain.vert")
+        )
     }
 
     fn draw(
@@ -364,11 +370,15 @@ impl Geometry for TerrainPatch {
     }
 
     fn id(&self, required_attributes: FragmentAttributes) -> u16 {
-        if required_attributes.normal || required_attributes.tangents {
+        let mut id = if required_attributes.normal || required_attributes.tangents {
             0b1u16 << 15 | 0b10u16
         } else {
             0b1u16 << 15 | 0b11u16
+        };
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
         }
+        id
     }
 
     fn render_with_material(
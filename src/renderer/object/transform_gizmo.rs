@@ -0,0 +1,654 @@
+use crate::renderer::*;
+
+///
+/// The type of transformation applied by a [TransformGizmo].
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoMode {
+    /// Translate along an axis, in a plane or in the view plane.
+    #[default]
+    Translate,
+    /// Rotate around an axis.
+    Rotate,
+    /// Scale along an axis or uniformly.
+    Scale,
+}
+
+///
+/// The coordinate system in which the handles of a [TransformGizmo] are aligned.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoSpace {
+    /// The handles are aligned with the world axes.
+    #[default]
+    World,
+    /// The handles are aligned with the local axes of the transformation.
+    Local,
+}
+
+///
+/// A handle of a [TransformGizmo], where the index specifies the axis (0 for x, 1 for y and 2 for z).
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoHandle {
+    /// The arrow for translating or the handle for scaling along the given axis.
+    Axis(usize),
+    /// The square for translating in the plane orthogonal to the given axis.
+    Plane(usize),
+    /// The ring for rotating around the given axis.
+    Ring(usize),
+    /// The center handle for translating in the view plane or scaling uniformly.
+    Center,
+}
+
+///
+/// An interactive gizmo for translating, rotating and scaling a transformation using the mouse.
+/// The gizmo is drawn on top of the scene with a constant size on the screen.
+///
+/// Call [TransformGizmo::handle_events] each frame before any other control, for example [OrbitControl],
+/// since the events used by the gizmo are marked as handled.
+/// Render the gizmo using [TransformGizmo::render] after the rest of the scene has been rendered,
+/// which clears the depth inside the viewport of the camera so the gizmo is drawn on top of the scene while the parts of the gizmo still occlude each other correctly.
+///
+pub struct TransformGizmo {
+    /// The type of transformation applied when dragging a handle.
+    pub mode: GizmoMode,
+    /// The coordinate system in which the handles are aligned. Scaling is always done along the local axes.
+    pub space: GizmoSpace,
+    /// The size of the gizmo in pixels.
+    pub size: f32,
+    /// If specified, translations are snapped to multiples of this distance.
+    pub translation_snap: Option<f32>,
+    /// If specified, rotations are snapped to multiples of this angle.
+    pub rotation_snap: Option<Radians>,
+    /// If specified, scale factors are snapped to multiples of this value.
+    pub scale_snap: Option<f32>,
+    transformation: Mat4,
+    frame: Frame,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+    arrows: Gm<InstancedMesh, ColorMaterial>,
+    planes: Gm<InstancedMesh, ColorMaterial>,
+    translate_center: Gm<InstancedMesh, ColorMaterial>,
+    rings: Gm<InstancedMesh, ColorMaterial>,
+    scale_axes: Gm<InstancedMesh, ColorMaterial>,
+    scale_center: Gm<InstancedMesh, ColorMaterial>,
+}
+
+const AXIS_COLORS: [Srgba; 3] = [
+    Srgba::new_opaque(230, 60, 60),
+    Srgba::new_opaque(60, 200, 60),
+    Srgba::new_opaque(60, 90, 230),
+];
+const CENTER_COLOR: Srgba = Srgba::new_opaque(220, 220, 220);
+const HIGHLIGHT_COLOR: Srgba = Srgba::new_opaque(255, 220, 0);
+/// The distance in pixels from a handle at which it is hit.
+const PICK_TOLERANCE: f32 = 8.0;
+/// The extent of the plane handles relative to the size of the gizmo.
+const PLANE_MIN: f32 = 0.25;
+const PLANE_MAX: f32 = 0.45;
+const CENTER_RADIUS: f32 = 0.1;
+
+impl TransformGizmo {
+    ///
+    /// Creates a new transform gizmo which manipulates the given transformation.
+    ///
+    pub fn new(context: &Context, transformation: Mat4) -> Self {
+        let mut arrow = CpuMesh::arrow(0.8, 0.4, 16);
+        arrow
+            .transform(&Mat4::from_nonuniform_scale(1.0, 0.06, 0.06))
+            .unwrap();
+        let mut plane = CpuMesh::square();
+        let half = 0.5 * (PLANE_MAX - PLANE_MIN);
+        plane
+            .transform(
+                &(Mat4::from_translation(vec3(PLANE_MIN + half, PLANE_MIN + half, 0.0))
+                    * Mat4::from_scale(half)),
+            )
+            .unwrap();
+        let mut sphere = CpuMesh::sphere(16);
+        sphere.transform(&Mat4::from_scale(0.08)).unwrap();
+        let mut scale_axis = CpuMesh::cylinder(16);
+        scale_axis
+            .transform(&Mat4::from_nonuniform_scale(0.9, 0.02, 0.02))
+            .unwrap();
+        let mut cube = CpuMesh::cube();
+        cube.transform(&(Mat4::from_translation(vec3(0.93, 0.0, 0.0)) * Mat4::from_scale(0.07)))
+            .unwrap();
+        append_mesh(&mut scale_axis, &cube);
+        let mut center_cube = CpuMesh::cube();
+        center_cube.transform(&Mat4::from_scale(0.08)).unwrap();
+
+        let material = ColorMaterial {
+            render_states: RenderStates {
+                cull: Cull::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let gm = |mesh: &CpuMesh, count: usize| {
+            Gm::new(
+                InstancedMesh::new(
+                    context,
+                    &Instances {
+                        transformations: vec![Mat4::identity(); count],
+                        ..Default::default()
+                    },
+                    mesh,
+                ),
+                material.clone(),
+            )
+        };
+        let mut gizmo = Self {
+            mode: GizmoMode::default(),
+            space: GizmoSpace::default(),
+            size: 100.0,
+            translation_snap: None,
+            rotation_snap: None,
+            scale_snap: None,
+            transformation,
+            frame: Frame::default(),
+            hovered: None,
+            drag: None,
+            arrows: gm(&arrow, 3),
+            planes: gm(&plane, 3),
+            translate_center: gm(&sphere, 1),
+            rings: gm(&torus(1.0, 0.02, 64, 8), 3),
+            scale_axes: gm(&scale_axis, 3),
+            scale_center: gm(&center_cube, 1),
+        };
+        gizmo.update_handles();
+        gizmo
+    }
+
+    ///
+    /// Returns the transformation manipulated by the gizmo.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.transformation
+    }
+
+    ///
+    /// Set the transformation manipulated by the gizmo. Any ongoing drag is cancelled.
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        self.transformation = transformation;
+        self.drag = None;
+    }
+
+    ///
+    /// Returns the handle under the mouse cursor or the handle being dragged, if any.
+    ///
+    pub fn active_handle(&self) -> Option<GizmoHandle> {
+        self.drag.as_ref().map(|d| d.handle).or(self.hovered)
+    }
+
+    ///
+    /// Renders the gizmo into the given render target on top of what is already rendered.
+    /// The depth inside the viewport of the camera is cleared before rendering, so this should be called after the rest of the scene has been rendered.
+    ///
+    pub fn render(&self, camera: &Camera, target: &RenderTarget) {
+        target
+            .clear_partially(camera.viewport().into(), ClearState::depth(1.0))
+            .render(camera, self, &[]);
+    }
+
+    ///
+    /// Returns whether or not a handle is currently being dragged.
+    ///
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    ///
+    /// Handles the events. Must be called each frame and before any other control using the same events.
+    /// The events that start, continue or end a drag of a handle are marked as handled.
+    /// Returns whether or not the transformation has changed.
+    ///
+    pub fn handle_events(&mut self, camera: &Camera, events: &mut [Event]) -> bool {
+        self.frame = self.compute_frame(camera);
+        let mut change = false;
+        for event in events.iter_mut() {
+            match event {
+                Event::MousePress {
+                    button: MouseButton::Left,
+                    position,
+                    handled,
+                    ..
+                } if !*handled => {
+                    if let Some(handle) = self.hit(camera, *position) {
+                        let position = *position;
+                        self.drag = Some(Drag {
+                            handle,
+                            start_transformation: self.transformation,
+                            frame: self.frame,
+                            start_position: position,
+                            start_point: self.drag_point(handle, &self.frame, camera, position),
+                        });
+                        *handled = true;
+                    }
+                }
+                Event::MouseMotion {
+                    position, handled, ..
+                } if !*handled => {
+                    if let Some(drag) = &self.drag {
+                        let transformation = self.drag_transformation(drag, camera, *position);
+                        if transformation != self.transformation {
+                            self.transformation = transformation;
+                            change = true;
+                        }
+                        *handled = true;
+                    } else {
+                        self.hovered = self.hit(camera, *position);
+                    }
+                }
+                Event::MouseRelease {
+                    button: MouseButton::Left,
+                    handled,
+                    ..
+                } if !*handled && self.drag.is_some() => {
+                    self.drag = None;
+                    *handled = true;
+                }
+                Event::MouseLeave => {
+                    self.hovered = None;
+                }
+                _ => {}
+            }
+        }
+        self.frame = self.compute_frame(camera);
+        self.update_handles();
+        change
+    }
+
+    fn compute_frame(&self, camera: &Camera) -> Frame {
+        let origin = self.transformation.w.truncate();
+        let local = self.space == GizmoSpace::Local || self.mode == GizmoMode::Scale;
+        let axes = if local {
+            orthonormal_axes(&self.transformation)
+        } else {
+            Mat3::identity()
+        };
+        // The size of a pixel in world space at the position of the gizmo
+        let clip = camera.projection() * camera.view() * origin.extend(1.0);
+        let pixel_size =
+            2.0 * clip.w.abs() / (camera.viewport().height as f32 * camera.projection().y.y.abs());
+        Frame {
+            origin,
+            axes,
+            scale: self.size * pixel_size,
+        }
+    }
+
+    fn update_handles(&mut self) {
+        let frame = self.frame.transformation();
+        let active = self.active_handle();
+        let color = |handle: GizmoHandle, color: Srgba| {
+            if active == Some(handle) {
+                HIGHLIGHT_COLOR
+            } else {
+                color
+            }
+        };
+        let update = |gm: &mut Gm<InstancedMesh, ColorMaterial>,
+                      handles: &[(GizmoHandle, Mat4, Srgba)]| {
+            gm.set_transformation(frame);
+            gm.set_instances(&Instances {
+                transformations: handles.iter().map(|h| h.1).collect(),
+                colors: Some(handles.iter().map(|h| color(h.0, h.2)).collect()),
+                ..Default::default()
+            });
+        };
+        let axes = (0..3)
+            .map(|i| (GizmoHandle::Axis(i), axis_rotation(i), AXIS_COLORS[i]))
+            .collect::<Vec<_>>();
+        // The plane and ring meshes lie in the xy-plane, so they are rotated such that the z-axis maps to the normal axis
+        let planes = (0..3)
+            .map(|i| {
+                (
+                    GizmoHandle::Plane(i),
+                    axis_rotation((i + 1) % 3),
+                    AXIS_COLORS[i],
+                )
+            })
+            .collect::<Vec<_>>();
+        let rings = (0..3)
+            .map(|i| {
+                (
+                    GizmoHandle::Ring(i),
+                    axis_rotation((i + 1) % 3),
+                    AXIS_COLORS[i],
+                )
+            })
+            .collect::<Vec<_>>();
+        let center = [(GizmoHandle::Center, Mat4::identity(), CENTER_COLOR)];
+        update(&mut self.arrows, &axes);
+        update(&mut self.planes, &planes);
+        update(&mut self.translate_center, &center);
+        update(&mut self.rings, &rings);
+        update(&mut self.scale_axes, &axes);
+        update(&mut self.scale_center, &center);
+    }
+
+    ///
+    /// Returns the closest handle hit by the ray through the given pixel.
+    ///
+    fn hit(&self, camera: &Camera, position: PhysicalPoint) -> Option<GizmoHandle> {
+        // The hit testing is done in the coordinate system of the gizmo where the size of the gizmo is one
+        let frame = &self.frame;
+        let inverse_axes = frame.axes.transpose();
+        let origin =
+            inverse_axes * (camera.position_at_pixel(position) - frame.origin) / frame.scale;
+        let direction = inverse_axes * camera.view_direction_at_pixel(position);
+        let tolerance = PICK_TOLERANCE / self.size;
+
+        let mut closest: Option<(f32, GizmoHandle)> = None;
+        let mut test = |t: f32, handle: GizmoHandle| {
+            if t >= 0.0 && closest.map(|(c, _)| t < c).unwrap_or(true) {
+                closest = Some((t, handle));
+            }
+        };
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                for i in 0..3 {
+                    let axis = Mat3::identity()[i];
+                    if let Some((s, t, distance)) =
+                        closest_points(Vec3::zero(), axis, origin, direction)
+                    {
+                        if distance < tolerance && (CENTER_RADIUS..=1.0).contains(&s) {
+                            test(t, GizmoHandle::Axis(i));
+                        }
+                    }
+                }
+                if self.mode == GizmoMode::Translate {
+                    for i in 0..3 {
+                        if let Some(t) = plane_intersection(origin, direction, i) {
+                            let p = origin + direction * t;
+                            let (u, v) = (p[(i + 1) % 3], p[(i + 2) % 3]);
+                            if (PLANE_MIN..=PLANE_MAX).contains(&u)
+                                && (PLANE_MIN..=PLANE_MAX).contains(&v)
+                            {
+                                test(t, GizmoHandle::Plane(i));
+                            }
+                        }
+                    }
+                }
+                let t = -origin.dot(direction);
+                if (origin + direction * t).magnitude() < CENTER_RADIUS {
+                    test(t, GizmoHandle::Center);
+                }
+            }
+            GizmoMode::Rotate => {
+                for i in 0..3 {
+                    if let Some(t) = plane_intersection(origin, direction, i) {
+                        let p = origin + direction * t;
+                        if (p.magnitude() - 1.0).abs() < tolerance {
+                            test(t, GizmoHandle::Ring(i));
+                        }
+                    }
+                }
+            }
+        }
+        closest.map(|(_, handle)| handle)
+    }
+
+    ///
+    /// Returns the point in world space that is dragged, which depends on the type of handle.
+    ///
+    fn drag_point(
+        &self,
+        handle: GizmoHandle,
+        frame: &Frame,
+        camera: &Camera,
+        position: PhysicalPoint,
+    ) -> Vec3 {
+        let ray_origin = camera.position_at_pixel(position);
+        let ray_direction = camera.view_direction_at_pixel(position);
+        let plane_point = |normal: Vec3| {
+            let denominator = normal.dot(ray_direction);
+            if denominator.abs() < 1e-6 {
+                return frame.origin;
+            }
+            let t = normal.dot(frame.origin - ray_origin) / denominator;
+            ray_origin + ray_direction * t
+        };
+        match handle {
+            GizmoHandle::Axis(i) => {
+                let axis = frame.axes[i];
+                closest_points(frame.origin, axis, ray_origin, ray_direction)
+                    .map(|(s, _, _)| frame.origin + axis * s)
+                    .unwrap_or(frame.origin)
+            }
+            GizmoHandle::Plane(i) | GizmoHandle::Ring(i) => plane_point(frame.axes[i]),
+            GizmoHandle::Center => plane_point(camera.view_direction()),
+        }
+    }
+
+    fn drag_transformation(&self, drag: &Drag, camera: &Camera, position: PhysicalPoint) -> Mat4 {
+        let frame = &drag.frame;
+        let start = drag.start_transformation;
+        let point = self.drag_point(drag.handle, frame, camera, position);
+        match (self.mode, drag.handle) {
+            (GizmoMode::Scale, GizmoHandle::Axis(i)) => {
+                let axis = frame.axes[i];
+                let start_distance = (drag.start_point - frame.origin).dot(axis);
+                if start_distance.abs() < 1e-6 {
+                    return start;
+                }
+                let factor = self.snap_scale((point - frame.origin).dot(axis) / start_distance);
+                let mut scale = vec3(1.0, 1.0, 1.0);
+                scale[i] = factor;
+                start * Mat4::from_nonuniform_scale(scale.x, scale.y, scale.z)
+            }
+            (GizmoMode::Scale, _) => {
+                let center = Vec2::from(camera.pixel_at_position(frame.origin));
+                let start_distance = (Vec2::from(drag.start_position) - center).magnitude();
+                if start_distance < 1e-6 {
+                    return start;
+                }
+                let factor =
+                    self.snap_scale((Vec2::from(position) - center).magnitude() / start_distance);
+                start * Mat4::from_scale(factor)
+            }
+            (GizmoMode::Rotate, GizmoHandle::Ring(i)) => {
+                let axis = frame.axes[i];
+                let from = drag.start_point - frame.origin;
+                let to = point - frame.origin;
+                let mut angle = from.cross(to).dot(axis).atan2(from.dot(to));
+                if let Some(snap) = self.rotation_snap {
+                    if snap.0 > 0.0 {
+                        angle = (angle / snap.0).round() * snap.0;
+                    }
+                }
+                Mat4::from_translation(frame.origin)
+                    * Mat4::from_axis_angle(axis, radians(angle))
+                    * Mat4::from_translation(-frame.origin)
+                    * start
+            }
+            (GizmoMode::Translate, handle) => {
+                let mut delta = point - drag.start_point;
+                if let Some(snap) = self.translation_snap {
+                    if snap > 0.0 {
+                        // Snap the translation along each of the axes of the gizmo
+                        let local = frame.axes.transpose() * delta;
+                        delta = frame.axes
+                            * vec3(
+                                (local.x / snap).round() * snap,
+                                (local.y / snap).round() * snap,
+                                (local.z / snap).round() * snap,
+                            );
+                    }
+                }
+                if let GizmoHandle::Axis(i) = handle {
+                    delta = frame.axes[i] * delta.dot(frame.axes[i]);
+                }
+                Mat4::from_translation(delta) * start
+            }
+            _ => start,
+        }
+    }
+
+    fn snap_scale(&self, factor: f32) -> f32 {
+        let factor = match self.scale_snap {
+            Some(snap) if snap > 0.0 => (factor / snap).round() * snap,
+            _ => factor,
+        };
+        // Avoid a degenerate transformation
+        if factor.abs() < 1e-3 {
+            1e-3
+        } else {
+            factor
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a TransformGizmo {
+    type Item = &'a dyn Object;
+    type IntoIter = std::vec::IntoIter<&'a dyn Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let objects: Vec<&dyn Object> = match self.mode {
+            GizmoMode::Translate => vec![&self.arrows, &self.planes, &self.translate_center],
+            GizmoMode::Rotate => vec![&self.rings],
+            GizmoMode::Scale => vec![&self.scale_axes, &self.scale_center],
+        };
+        objects.into_iter()
+    }
+}
+
+///
+/// The coordinate system of the gizmo in world space.
+///
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    origin: Vec3,
+    axes: Mat3,
+    scale: f32,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::zero(),
+            axes: Mat3::identity(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Frame {
+    fn transformation(&self) -> Mat4 {
+        Mat4::from_translation(self.origin) * Mat4::from(self.axes) * Mat4::from_scale(self.scale)
+    }
+}
+
+struct Drag {
+    handle: GizmoHandle,
+    start_transformation: Mat4,
+    frame: Frame,
+    start_position: PhysicalPoint,
+    start_point: Vec3,
+}
+
+///
+/// Returns the rotation which maps the x-axis to the given axis by cyclically permuting the axes.
+///
+fn axis_rotation(axis: usize) -> Mat4 {
+    let identity = Mat3::identity();
+    Mat4::from(Mat3::from_cols(
+        identity[axis],
+        identity[(axis + 1) % 3],
+        identity[(axis + 2) % 3],
+    ))
+}
+
+fn orthonormal_axes(transformation: &Mat4) -> Mat3 {
+    let x = transformation.x.truncate();
+    let y = transformation.y.truncate();
+    if x.magnitude2() < 1e-12 || y.magnitude2() < 1e-12 {
+        return Mat3::identity();
+    }
+    let x = x.normalize();
+    let y = y - x * x.dot(y);
+    if y.magnitude2() < 1e-12 {
+        return Mat3::identity();
+    }
+    let y = y.normalize();
+    Mat3::from_cols(x, y, x.cross(y))
+}
+
+///
+/// Returns the parameters of the closest points on the line and the ray together with the distance between them,
+/// or `None` if they are parallel. Both directions must be normalized.
+///
+fn closest_points(
+    line_origin: Vec3,
+    line_direction: Vec3,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+) -> Option<(f32, f32, f32)> {
+    let w = line_origin - ray_origin;
+    let b = line_direction.dot(ray_direction);
+    let denominator = 1.0 - b * b;
+    if denominator < 1e-6 {
+        return None;
+    }
+    let s = (b * ray_direction.dot(w) - line_direction.dot(w)) / denominator;
+    let t = ray_direction.dot(w) + s * b;
+    let distance = (line_origin + line_direction * s - ray_origin - ray_direction * t).magnitude();
+    Some((s, t, distance))
+}
+
+///
+/// Returns the ray parameter of the intersection with the plane through the origin orthogonal to the given axis.
+///
+fn plane_intersection(origin: Vec3, direction: Vec3, axis: usize) -> Option<f32> {
+    if direction[axis].abs() < 1e-6 {
+        return None;
+    }
+    Some(-origin[axis] / direction[axis])
+}
+
+fn torus(radius: f32, tube_radius: f32, segments: u32, tube_segments: u32) -> CpuMesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..segments {
+        let angle = 2.0 * std::f32::consts::PI * i as f32 / segments as f32;
+        let (sin, cos) = angle.sin_cos();
+        for j in 0..tube_segments {
+            let tube_angle = 2.0 * std::f32::consts::PI * j as f32 / tube_segments as f32;
+            let r = radius + tube_radius * tube_angle.cos();
+            positions.push(vec3(r * cos, r * sin, tube_radius * tube_angle.sin()));
+            let a = i * tube_segments + j;
+            let b = ((i + 1) % segments) * tube_segments + j;
+            let c = ((i + 1) % segments) * tube_segments + (j + 1) % tube_segments;
+            let d = i * tube_segments + (j + 1) % tube_segments;
+            indices.extend([a, b, c, a, c, d]);
+        }
+    }
+    CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        ..Default::default()
+    }
+}
+
+fn append_mesh(mesh: &mut CpuMesh, other: &CpuMesh) {
+    let offset = mesh.positions.len() as u32;
+    let mut indices = mesh.indices.to_u32().unwrap_or((0..offset).collect());
+    indices.extend(
+        other
+            .indices
+            .to_u32()
+            .unwrap_or((0..other.positions.len() as u32).collect())
+            .into_iter()
+            .map(|i| i + offset),
+    );
+    let mut positions = mesh.positions.to_f32();
+    positions.extend(other.positions.to_f32());
+    *mesh = CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        ..Default::default()
+    };
+}
@@ -41,6 +41,8 @@ impl Default for WaveParameters {
 pub struct Water<M: Material> {
     patches: Vec<WaterPatch>,
     vertex_distance: f32,
+    height: f32,
+    parameters: [WaveParameters; MAX_WAVE_COUNT],
     material: M,
 }
 impl<M: Material> Water<M> {
@@ -85,6 +87,8 @@ impl<M: Material> Water<M> {
         let mut s = Self {
             patches,
             vertex_distance,
+            height,
+            parameters: [WaveParameters::default(); MAX_WAVE_COUNT],
             material,
         };
         s.set_parameters(parameters);
@@ -110,6 +114,7 @@ impl<M: Material> Water<M> {
     /// Set the average height of the water.
     ///
     pub fn set_height(&mut self, height: f32) {
+        self.height = height;
         self.patches.iter_mut().for_each(|p| p.center.y = height);
     }
 
@@ -125,9 +130,75 @@ impl<M: Material> Water<M> {
             .into_iter()
             .enumerate()
             .for_each(|(i, p)| ps[i] = p);
+        self.parameters = ps;
         self.patches.iter_mut().for_each(|p| p.parameters = ps);
     }
 
+    ///
+    /// Returns the height of the water surface at the given position and time.
+    /// The time parameter should be the same as the one given to [Self::animate].
+    ///
+    /// This can for example be used to make objects float on the water surface.
+    ///
+    pub fn height_at(&self, position: Vec2, time: f32) -> f32 {
+        self.surface_at(position, time).0.y
+    }
+
+    ///
+    /// Returns the normal of the water surface at the given position and time.
+    /// The time parameter should be the same as the one given to [Self::animate].
+    ///
+    pub fn normal_at(&self, position: Vec2, time: f32) -> Vec3 {
+        self.surface_at(position, time).1.normalize()
+    }
+
+    ///
+    /// Returns the position and (unnormalized) normal of the water surface point that ends up at the given position in the xz-plane.
+    /// Since the waves also move the surface horizontally, the undisplaced position is found by iteratively subtracting the horizontal displacement.
+    ///
+    fn surface_at(&self, position: Vec2, time: f32) -> (Vec3, Vec3) {
+        let mut rest_position = position;
+        let mut surface = self.displace(rest_position, time);
+        for _ in 0..16 {
+            let error = vec2(surface.0.x, surface.0.z) - position;
+            if error.magnitude2() < 1.0e-10 {
+                break;
+            }
+            rest_position -= error;
+            surface = self.displace(rest_position, time);
+        }
+        surface
+    }
+
+    ///
+    /// Displaces the given position on the undisturbed water surface exactly as done in the water vertex shader.
+    ///
+    fn displace(&self, position: Vec2, time: f32) -> (Vec3, Vec3) {
+        #[allow(clippy::approx_constant)]
+        const PI: f32 = 3.14159; // Same value as in the shader
+        let time = time * 0.001;
+        let mut pos = vec3(position.x, self.height, position.y);
+        let mut nor = vec3(0.0, 1.0, 0.0);
+        for p in self.parameters.iter() {
+            if p.wavelength > 0.001 && p.amplitude > 0.001 {
+                let frequency = 2.0 * PI / p.wavelength;
+                let theta = p.direction.dot(vec2(pos.x, pos.z));
+                let a = theta * frequency + time * p.speed;
+                let b = p.amplitude * a.cos() * p.direction;
+                let c = p.amplitude * a.sin();
+
+                pos.y += c;
+                pos.x += p.steepness * b.x;
+                pos.z += p.steepness * b.y;
+
+                nor.y -= p.steepness * frequency * c;
+                nor.x -= frequency * b.x;
+                nor.z -= frequency * b.y;
+            }
+        }
+        (pos, nor)
+    }
+
     ///
     /// For updating the animation. The time parameter should be some continious time, for example the time since start.
     ///
@@ -249,12 +320,24 @@ impl Geometry for WaterPatch {
         program.draw_elements(render_states, camera.viewport(), &self.index_buffer);
     }
 
-    fn vertex_shader_source(&self, _required_attributes: FragmentAttributes) -> String {
-        include_str!("shaders/water.vert").to_owned()
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "{}{}",
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            include_str!("shaders/water.vert")
+        )
     }
 
-    fn id(&self, _required_attributes: FragmentAttributes) -> u16 {
-        0b1u16 << 15 | 0b101u16
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15 | 0b101u16;
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
     }
 
     fn render_with_material(
@@ -0,0 +1,217 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// Statistics of the last frame rendered using [OcclusionCulling].
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OcclusionCullingStatistics {
+    /// The total number of objects.
+    pub objects: usize,
+    /// The number of objects that were not rendered because they are outside the camera frustum.
+    pub frustum_culled: usize,
+    /// The number of objects inside the camera frustum that were not rendered because they were occluded by other objects.
+    pub occlusion_culled: usize,
+    /// The number of objects that were rendered.
+    pub rendered: usize,
+}
+
+struct OcclusionState {
+    query: Option<OcclusionQuery>,
+    visible: bool,
+}
+
+///
+/// Culls objects which are hidden behind other objects using hardware occlusion queries.
+/// Use it with [RenderTarget::render_with_occlusion_culling] instead of [RenderTarget::render] to avoid rendering objects that are not visible,
+/// which is useful for scenes with many objects hidden behind large occluders, for example a city with buildings.
+///
+/// After rendering the visible objects, the axis aligned bounding box of each object inside the camera frustum is tested against the depth buffer.
+/// The result of the test is used when rendering the next frame to avoid waiting for the GPU, which means that an object which becomes visible is rendered one frame late.
+/// The objects are identified by their position in the list of objects, so the same objects should be given in the same order every frame.
+///
+pub struct OcclusionCulling {
+    context: Context,
+    program: Program,
+    positions: VertexBuffer,
+    states: Vec<OcclusionState>,
+    statistics: OcclusionCullingStatistics,
+}
+
+impl OcclusionCulling {
+    ///
+    /// Creates a new occlusion culling state.
+    ///
+    pub fn new(context: &Context) -> Self {
+        let program = Program::from_source(
+            context,
+            "uniform mat4 transformation;
+            in vec3 position;
+            void main()
+            {
+                gl_Position = transformation * vec4(position, 1.0);
+            }",
+            "layout (location = 0) out vec4 outColor;
+            void main()
+            {
+                outColor = vec4(1.0);
+            }",
+        )
+        .expect("Failed compiling shader");
+        let mut cube = CpuMesh::cube();
+        cube.transform(&(Mat4::from_translation(vec3(0.5, 0.5, 0.5)) * Mat4::from_scale(0.5)))
+            .unwrap();
+        let mut positions = cube.positions.to_f32();
+        if let Some(indices) = cube.indices.to_u32() {
+            positions = indices.into_iter().map(|i| positions[i as usize]).collect();
+        }
+        Self {
+            context: context.clone(),
+            program,
+            positions: VertexBuffer::new_with_data(context, &positions),
+            states: Vec::new(),
+            statistics: OcclusionCullingStatistics::default(),
+        }
+    }
+
+    ///
+    /// Returns the statistics of the last frame rendered using this occlusion culling state.
+    ///
+    pub fn statistics(&self) -> OcclusionCullingStatistics {
+        self.statistics
+    }
+
+    ///
+    /// Forget the results of the previous occlusion tests, so that all objects inside the camera frustum are rendered in the next frame.
+    /// Call this when the scene changes completely, for example after a camera cut.
+    ///
+    pub fn reset(&mut self) {
+        for state in self.states.iter_mut() {
+            state.visible = true;
+        }
+    }
+
+    ///
+    /// Returns whether or not each of the objects should be rendered, based on the camera frustum and the results of the previous occlusion tests.
+    ///
+    pub(crate) fn visibility(&mut self, camera: &Camera, objects: &[impl Object]) -> Vec<bool> {
+        self.states.truncate(objects.len());
+        while self.states.len() < objects.len() {
+            self.states.push(OcclusionState {
+                query: None,
+                visible: true,
+            });
+        }
+        let mut statistics = OcclusionCullingStatistics {
+            objects: objects.len(),
+            ..Default::default()
+        };
+        let near_distance = near_plane_distance(camera);
+        let visibility = objects
+            .iter()
+            .zip(self.states.iter_mut())
+            .map(|(object, state)| {
+                if let Some(visible) = state.query.as_mut().and_then(|q| q.result()) {
+                    state.visible = visible;
+                }
+                let aabb = object.aabb();
+                if !camera.in_frustum(&aabb) {
+                    // Objects entering the frustum are rendered until they are tested again
+                    state.visible = true;
+                    statistics.frustum_culled += 1;
+                    false
+                } else if state.visible || contains_camera(camera, &aabb, near_distance) {
+                    statistics.rendered += 1;
+                    true
+                } else {
+                    statistics.occlusion_culled += 1;
+                    false
+                }
+            })
+            .collect();
+        self.statistics = statistics;
+        visibility
+    }
+
+    ///
+    /// Tests the bounding boxes of the objects inside the camera frustum against the depth buffer.
+    /// Must be called in the callback given as input to a [RenderTarget], [ColorTarget] or [DepthTarget] write method.
+    ///
+    pub(crate) fn test(&mut self, camera: &Camera, objects: &[impl Object]) {
+        let render_states = RenderStates {
+            write_mask: WriteMask::NONE,
+            depth_test: DepthTest::LessOrEqual,
+            cull: Cull::None,
+            ..Default::default()
+        };
+        let view_projection = camera.projection() * camera.view();
+        let near_distance = near_plane_distance(camera);
+        for (object, state) in objects.iter().zip(self.states.iter_mut()) {
+            let aabb = object.aabb();
+            if state
+                .query
+                .as_ref()
+                .map(|q| q.is_pending())
+                .unwrap_or(false)
+                || aabb.is_infinite()
+                || !camera.in_frustum(&aabb)
+                || contains_camera(camera, &aabb, near_distance)
+            {
+                continue;
+            }
+            // The box is enlarged slightly to avoid objects occluding their own bounding box
+            let size = aabb.size();
+            let margin = 0.01 * size + vec3(1e-4, 1e-4, 1e-4);
+            let transformation = view_projection
+                * Mat4::from_translation(aabb.min() - margin)
+                * Mat4::from_nonuniform_scale(
+                    size.x + 2.0 * margin.x,
+                    size.y + 2.0 * margin.y,
+                    size.z + 2.0 * margin.z,
+                );
+            self.program.use_uniform("transformation", transformation);
+            self.program
+                .use_vertex_attribute("position", &self.positions);
+            let query = state
+                .query
+                .get_or_insert_with(|| OcclusionQuery::new(&self.context));
+            query.begin();
+            self.program.draw_arrays(
+                render_states,
+                camera.viewport(),
+                self.positions.vertex_count(),
+            );
+            query.end();
+        }
+    }
+}
+
+///
+/// Returns the largest distance from the camera position to a corner of the near plane.
+///
+fn near_plane_distance(camera: &Camera) -> f32 {
+    let Some(inverse) = (camera.projection() * camera.view()).invert() else {
+        return camera.z_near();
+    };
+    [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .into_iter()
+        .map(|(x, y)| {
+            let p = inverse * vec4(x, y, -1.0, 1.0);
+            (p.truncate() / p.w).distance(*camera.position())
+        })
+        .fold(camera.z_near(), f32::max)
+}
+
+///
+/// Returns whether the bounding box might intersect the near plane of the camera, in which case the occlusion test is unreliable.
+///
+fn contains_camera(camera: &Camera, aabb: &AxisAlignedBoundingBox, near_distance: f32) -> bool {
+    let p = camera.position();
+    let (min, max) = (aabb.min(), aabb.max());
+    p.x > min.x - near_distance
+        && p.y > min.y - near_distance
+        && p.z > min.z - near_distance
+        && p.x < max.x + near_distance
+        && p.y < max.y + near_distance
+        && p.z < max.z + near_distance
+}
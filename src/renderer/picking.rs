@@ -0,0 +1,259 @@
+use crate::renderer::*;
+
+///
+/// The result of picking with [pick_geometry].
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickResult {
+    /// The index of the geometry that was hit in the list of geometries given to the pick function.
+    pub geometry_index: usize,
+    /// The index of the instance that was hit, for example in the [Instances] of an [InstancedMesh] or the [SpriteInstances] of [Sprites].
+    /// Zero if the geometry is not instanced.
+    pub instance: u32,
+    /// The index of the triangle that was hit, ie. the index of the first of the three vertices (or vertex indices) divided by three.
+    /// Is always `None` on web since the primitive id is not available in WebGL2.
+    pub triangle: Option<u32>,
+    /// The position that was hit in world space.
+    pub position: Vec3,
+    /// The normal of the triangle that was hit in world space, which always points towards the camera.
+    pub normal: Vec3,
+    /// The distance from the camera to the position that was hit along the view direction of the camera.
+    pub depth: f32,
+}
+
+///
+/// Finds the closest geometry at the given pixel and returns which geometry, instance and triangle was hit together with the position and normal.
+/// The geometries are rendered into an id buffer using the given camera, so geometries that are oriented towards the camera, like [Sprites], are picked as they are seen.
+/// Returns ```None``` if no geometry was hit between the near and far plane of the camera.
+///
+pub fn pick_geometry(
+    context: &Context,
+    camera: &Camera,
+    pixel: impl Into<PhysicalPoint> + Copy,
+    geometries: impl IntoIterator<Item = impl Geometry>,
+) -> Option<PickResult> {
+    let p = pixel.into();
+    let scissor_box = ScissorBox {
+        x: p.x.floor() as i32,
+        y: p.y.floor() as i32,
+        width: 1,
+        height: 1,
+    };
+    let (mut id_texture, mut texture) = render_ids(
+        context,
+        camera,
+        scissor_box,
+        &geometries.into_iter().collect::<Vec<_>>(),
+        true,
+    );
+    let [geometry, instance, triangle, _] = id_texture.as_color_target(None).read::<[u32; 4]>()[0];
+    if geometry == 0 {
+        return None;
+    }
+    let position = Vec4::from(texture.as_color_target(&[0], None).read::<[f32; 4]>()[0]).truncate();
+    let mut normal = Vec4::from(texture.as_color_target(&[1], None).read::<[f32; 4]>()[0])
+        .truncate()
+        .normalize();
+    if normal.dot(camera.view_direction_at_pixel(pixel)) > 0.0 {
+        normal = -normal;
+    }
+    Some(PickResult {
+        geometry_index: geometry as usize - 1,
+        instance,
+        triangle: triangle.checked_sub(1),
+        position,
+        normal,
+        depth: (position - camera.position()).dot(camera.view_direction()),
+    })
+}
+
+///
+/// Finds all geometries that are visible inside the rectangle on the screen spanned by the two given pixels, for example to implement box selection.
+/// Returns the index of each of the geometries in the list of given geometries together with the instance index, see [PickResult::instance], sorted and without duplicates.
+///
+pub fn pick_region(
+    context: &Context,
+    camera: &Camera,
+    corner0: impl Into<PhysicalPoint>,
+    corner1: impl Into<PhysicalPoint>,
+    geometries: impl IntoIterator<Item = impl Geometry>,
+) -> Vec<(usize, u32)> {
+    let (p0, p1) = (corner0.into(), corner1.into());
+    let (x0, x1) = (p0.x.min(p1.x).floor(), p0.x.max(p1.x).ceil());
+    let (y0, y1) = (p0.y.min(p1.y).floor(), p0.y.max(p1.y).ceil());
+    let scissor_box = ScissorBox {
+        x: x0 as i32,
+        y: y0 as i32,
+        width: ((x1 - x0) as u32).max(1),
+        height: ((y1 - y0) as u32).max(1),
+    };
+    let (mut id_texture, _) = render_ids(
+        context,
+        camera,
+        scissor_box,
+        &geometries.into_iter().collect::<Vec<_>>(),
+        false,
+    );
+    let ids = id_texture
+        .as_color_target(None)
+        .read::<[u32; 4]>()
+        .into_iter()
+        .filter(|id| id[0] > 0)
+        .map(|id| (id[0] as usize - 1, id[1]))
+        .collect::<std::collections::BTreeSet<_>>();
+    ids.into_iter().collect()
+}
+
+///
+/// Renders the ids of the given geometries in the given region of the camera viewport into an unsigned integer texture with the same size as the region.
+/// The ids are the geometry index plus one, the instance index and the triangle index plus one, where zero means that nothing was hit or that the triangle index is not available.
+/// If `positions` is true, the positions and normals of the closest geometries are also rendered into the two layers of a texture array.
+///
+fn render_ids(
+    context: &Context,
+    camera: &Camera,
+    region: ScissorBox,
+    geometries: &[impl Geometry],
+    positions: bool,
+) -> (Texture2D, Texture2DArray) {
+    // Offset the viewport so that the region ends up at the origin of the texture
+    let mut camera = camera.clone();
+    let viewport = camera.viewport();
+    camera.set_viewport(Viewport {
+        x: viewport.x - region.x,
+        y: viewport.y - region.y,
+        ..viewport
+    });
+    let mut id_texture = Texture2D::new_empty::<[u32; 4]>(
+        context,
+        region.width,
+        region.height,
+        Interpolation::Nearest,
+        Interpolation::Nearest,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    );
+    let mut texture = Texture2DArray::new_empty::<[f32; 4]>(
+        context,
+        if positions { region.width } else { 1 },
+        if positions { region.height } else { 1 },
+        2,
+        Interpolation::Nearest,
+        Interpolation::Nearest,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    );
+    let mut depth_texture = DepthTexture2D::new::<f32>(
+        context,
+        region.width,
+        region.height,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    );
+    RenderTarget::new(
+        id_texture.as_color_target(None),
+        depth_texture.as_depth_target(),
+    )
+    .clear(ClearState::depth(1.0))
+    .write::<RendererError>(|| {
+        // Integer color buffers cannot be cleared with a clear color
+        #[allow(unsafe_code)]
+        unsafe {
+            context.clear_buffer_u32_slice(crate::context::COLOR, 0, &[0; 4]);
+        }
+        for (index, geometry) in geometries.iter().enumerate() {
+            geometry.render_with_material(
+                &PickingMaterial {
+                    geometry_index: index as u32 + 1,
+                    ids: true,
+                    render_states: RenderStates::default(),
+                },
+                &camera,
+                &[],
+            );
+        }
+        Ok(())
+    })
+    .unwrap();
+    if positions {
+        // Only the closest surfaces, which are already in the depth texture, pass the depth test
+        RenderTarget::new(
+            texture.as_color_target(&[0, 1], None),
+            depth_texture.as_depth_target(),
+        )
+        .clear(ClearState::color(0.0, 0.0, 0.0, 0.0))
+        .write::<RendererError>(|| {
+            for geometry in geometries.iter() {
+                geometry.render_with_material(
+                    &PickingMaterial {
+                        geometry_index: 0,
+                        ids: false,
+                        render_states: RenderStates {
+                            depth_test: DepthTest::LessOrEqual,
+                            write_mask: WriteMask::COLOR,
+                            ..Default::default()
+                        },
+                    },
+                    &camera,
+                    &[],
+                );
+            }
+            Ok(())
+        })
+        .unwrap();
+    }
+    (id_texture, texture)
+}
+
+///
+/// Renders the ids of a geometry into an unsigned integer color target if `ids` is true, otherwise the positions and normals into two color targets.
+///
+struct PickingMaterial {
+    geometry_index: u32,
+    ids: bool,
+    render_states: RenderStates,
+}
+
+impl Material for PickingMaterial {
+    fn id(&self) -> u16 {
+        if self.ids {
+            0b1u16 << 15 | 0b1u16 << 7 | 0b11u16
+        } else {
+            0b1u16 << 15 | 0b1u16 << 7 | 0b100u16
+        }
+    }
+
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        format!(
+            "{}{}",
+            if self.ids {
+                "#define PICKING_IDS\n"
+            } else {
+                ""
+            },
+            include_str!("material/shaders/picking.frag")
+        )
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            instance_id: self.ids,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform_if_required("geometryIndex", self.geometry_index);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        self.render_states
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
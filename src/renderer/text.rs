@@ -0,0 +1,18 @@
+//!
+//! Text rendering using signed distance field glyph atlases.
+//! A [Font] is created from a TrueType or OpenType font, a [TextGeometry] contains the layout of a string using that font
+//! and a [TextMaterial] renders the glyphs with anti-aliased edges and an optional outline.
+//! The text can be rendered as a 2D overlay using a camera created by [Camera::new_2d] or placed in the 3D scene, see [TextPlacement].
+//!
+
+mod font;
+#[doc(inline)]
+pub use font::*;
+
+mod text_geometry;
+#[doc(inline)]
+pub use text_geometry::*;
+
+mod text_material;
+#[doc(inline)]
+pub use text_material::*;
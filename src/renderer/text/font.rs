@@ -0,0 +1,424 @@
+use crate::renderer::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+///
+/// The characters included in a [Font] created with [Font::new], ie. the printable ASCII and Latin-1 characters.
+///
+pub const DEFAULT_CHARACTERS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~\
+¡¢£¤¥¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞßàáâãäåæçèéêëìíîïðñòóôõö÷øùúûüýþÿ";
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Glyph {
+    pub id: u16,
+    /// The horizontal advance in em units.
+    pub advance: f32,
+    /// The bounds of the glyph quad relative to the pen position on the baseline in em units, if the glyph is visible.
+    pub bounds: Option<(Vec2, Vec2)>,
+    /// The uv coordinates of the glyph in the atlas.
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+///
+/// A font that can be used for rendering text, see [TextGeometry] and [TextMaterial].
+/// The glyphs of the font are rendered into a signed distance field atlas texture when the font is created,
+/// which means that the text can be rendered at any size with sharp edges.
+///
+pub struct Font {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(u16, u16), f32>,
+    texture: Arc<Texture2D>,
+    glyph_size: u32,
+    spread: f32,
+    ascender: f32,
+    descender: f32,
+    line_gap: f32,
+}
+
+impl Font {
+    ///
+    /// Creates a new font from the given TrueType (.ttf) or OpenType (.otf) font data, containing the [DEFAULT_CHARACTERS].
+    /// The glyph size is the size of an em in pixels in the atlas texture, a size of 32-64 pixels is usually enough.
+    ///
+    pub fn new(
+        context: &Context,
+        font_data: &[u8],
+        glyph_size: u32,
+    ) -> Result<Self, RendererError> {
+        Self::new_with_characters(context, font_data, glyph_size, DEFAULT_CHARACTERS)
+    }
+
+    ///
+    /// Creates a new font from the given TrueType (.ttf) or OpenType (.otf) font data, containing the given characters.
+    /// The glyph size is the size of an em in pixels in the atlas texture, a size of 32-64 pixels is usually enough.
+    ///
+    /// The kerning between the characters is read from the pair adjustments of the `kern` feature in the `GPOS` table or, if the font does not have any, from the `kern` table.
+    /// Other OpenType layout features, like ligatures and contextual kerning, are not supported.
+    ///
+    pub fn new_with_characters(
+        context: &Context,
+        font_data: &[u8],
+        glyph_size: u32,
+        characters: &str,
+    ) -> Result<Self, RendererError> {
+        let face = ttf_parser::Face::parse(font_data, 0)
+            .map_err(|e| RendererError::FontParsing(e.to_string()))?;
+        let units_per_em = face.units_per_em() as f32;
+        let glyph_size = glyph_size.max(4);
+        // The scale from font units to atlas pixels
+        let scale = glyph_size as f32 / units_per_em;
+        // The maximum distance in pixels stored in the distance field
+        let spread = (glyph_size as f32 / 8.0).max(2.0);
+        let padding = spread.ceil() as i32 + 1;
+
+        // Find the outline and size of each glyph in the atlas
+        let mut characters = characters.chars().collect::<Vec<_>>();
+        characters.sort();
+        characters.dedup();
+        let mut cells = Vec::new();
+        for c in characters {
+            let Some(id) = face.glyph_index(c) else {
+                continue;
+            };
+            let mut outline = Outline::new();
+            let cell = face.outline_glyph(id, &mut outline).map(|rect| {
+                let x0 = (rect.x_min as f32 * scale).floor() as i32 - padding;
+                let y1 = (rect.y_max as f32 * scale).ceil() as i32 + padding;
+                let width = (rect.x_max as f32 * scale).ceil() as i32 - x0 + padding;
+                let height = y1 - (rect.y_min as f32 * scale).floor() as i32 + padding;
+                (x0, y1, width as u32, height as u32)
+            });
+            cells.push((c, id, outline, cell));
+        }
+
+        // Pack the glyphs into rows in the atlas starting with the tallest glyphs
+        cells.sort_by_key(|(_, _, _, cell)| std::cmp::Reverse(cell.map(|c| c.3).unwrap_or(0)));
+        let area: u32 = cells
+            .iter()
+            .map(|(_, _, _, cell)| cell.map(|c| (c.2 + 1) * (c.3 + 1)).unwrap_or(0))
+            .sum();
+        let max_cell_width = cells
+            .iter()
+            .map(|(_, _, _, cell)| cell.map(|c| c.2 + 1).unwrap_or(0))
+            .max()
+            .unwrap_or(1);
+        let width = ((area as f32).sqrt().ceil() as u32)
+            .max(max_cell_width)
+            .next_power_of_two();
+        let mut positions = Vec::with_capacity(cells.len());
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for (_, _, _, cell) in cells.iter() {
+            if let Some((_, _, w, h)) = *cell {
+                if x + w > width {
+                    x = 0;
+                    y += row_height + 1;
+                    row_height = 0;
+                }
+                positions.push((x, y));
+                x += w + 1;
+                row_height = row_height.max(h);
+            } else {
+                positions.push((0, 0));
+            }
+        }
+        let height = (y + row_height).max(1);
+
+        // Compute the signed distance field of each glyph where the first row is the top of the atlas
+        let mut data = vec![0u8; (width * height) as usize];
+        let mut glyphs = HashMap::new();
+        for ((c, id, outline, cell), (cx, cy)) in cells.into_iter().zip(positions) {
+            let advance = face.glyph_hor_advance(id).unwrap_or(0) as f32 / units_per_em;
+            let mut glyph = Glyph {
+                id: id.0,
+                advance,
+                bounds: None,
+                uv_min: vec2(0.0, 0.0),
+                uv_max: vec2(0.0, 0.0),
+            };
+            if let Some((x0, y1, w, h)) = cell {
+                let segments = outline
+                    .segments
+                    .iter()
+                    .map(|(a, b)| (a * scale, b * scale))
+                    .collect::<Vec<_>>();
+                for j in 0..h {
+                    for i in 0..w {
+                        let p = vec2(x0 as f32 + i as f32 + 0.5, y1 as f32 - j as f32 - 0.5);
+                        let distance = signed_distance(&segments, p);
+                        data[((cy + j) * width + cx + i) as usize] =
+                            ((0.5 + 0.5 * distance / spread).clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                }
+                glyph.bounds = Some((
+                    vec2(x0 as f32, (y1 - h as i32) as f32) / glyph_size as f32,
+                    vec2((x0 + w as i32) as f32, y1 as f32) / glyph_size as f32,
+                ));
+                glyph.uv_min = vec2(
+                    cx as f32 / width as f32,
+                    1.0 - (cy + h) as f32 / height as f32,
+                );
+                glyph.uv_max = vec2(
+                    (cx + w) as f32 / width as f32,
+                    1.0 - cy as f32 / height as f32,
+                );
+            }
+            glyphs.insert(c, glyph);
+        }
+
+        // The kerning between each pair of glyphs is found once, so the font data does not have to be parsed again when laying out text
+        let kern_lookups = kern_lookups(&face);
+        let mut ids = glyphs.values().map(|g| g.id).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let mut kerning = HashMap::new();
+        for left in ids.iter() {
+            for right in ids.iter() {
+                let (left, right) = (ttf_parser::GlyphId(*left), ttf_parser::GlyphId(*right));
+                let value = gpos_kerning(&face, &kern_lookups, left, right)
+                    .or_else(|| kern_table_kerning(&face, left, right))
+                    .unwrap_or(0);
+                if value != 0 {
+                    kerning.insert((left.0, right.0), value as f32 / units_per_em);
+                }
+            }
+        }
+
+        let texture = Texture2D::new(
+            context,
+            &CpuTexture {
+                name: "font atlas".to_string(),
+                data: TextureData::RU8(data),
+                width,
+                height,
+                min_filter: Interpolation::Linear,
+                mag_filter: Interpolation::Linear,
+                mip_map_filter: None,
+                wrap_s: Wrapping::ClampToEdge,
+                wrap_t: Wrapping::ClampToEdge,
+            },
+        );
+        // The texture is only used on the thread where the OpenGL context is current
+        #[allow(clippy::arc_with_non_send_sync)]
+        let texture = Arc::new(texture);
+        Ok(Self {
+            glyphs,
+            kerning,
+            texture,
+            glyph_size,
+            spread,
+            ascender: face.ascender() as f32 / units_per_em,
+            descender: face.descender() as f32 / units_per_em,
+            line_gap: face.line_gap() as f32 / units_per_em,
+        })
+    }
+
+    ///
+    /// Returns whether or not the given character is included in this font.
+    ///
+    pub fn contains(&self, character: char) -> bool {
+        self.glyphs.contains_key(&character)
+    }
+
+    ///
+    /// Returns the distance from one baseline to the next in em units.
+    ///
+    pub fn line_height(&self) -> f32 {
+        self.ascender - self.descender + self.line_gap
+    }
+
+    ///
+    /// Returns the signed distance field atlas texture.
+    ///
+    pub fn texture(&self) -> &Arc<Texture2D> {
+        &self.texture
+    }
+
+    /// The maximum distance stored in the distance field in em units.
+    pub(super) fn spread(&self) -> f32 {
+        self.spread / self.glyph_size as f32
+    }
+
+    pub(super) fn ascender(&self) -> f32 {
+        self.ascender
+    }
+
+    pub(super) fn descender(&self) -> f32 {
+        self.descender
+    }
+
+    ///
+    /// Returns the glyph for the given character, falling back to a question mark if the character is not included in the font.
+    ///
+    pub(super) fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    ///
+    /// Returns the kerning between the two glyphs in em units.
+    ///
+    pub(super) fn kerning(&self, left: &Glyph, right: &Glyph) -> f32 {
+        self.kerning
+            .get(&(left.id, right.id))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+///
+/// Returns the indices of the lookups used by the `kern` feature in the `GPOS` table.
+///
+fn kern_lookups(face: &ttf_parser::Face) -> Vec<u16> {
+    let Some(gpos) = face.tables().gpos else {
+        return Vec::new();
+    };
+    let mut lookups = (0..gpos.features.len())
+        .filter_map(|i| gpos.features.get(i))
+        .filter(|feature| feature.tag == ttf_parser::Tag::from_bytes(b"kern"))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect::<Vec<_>>();
+    lookups.sort();
+    lookups.dedup();
+    lookups
+}
+
+///
+/// Returns the horizontal advance adjustment in font units of the first pair adjustment in the given `GPOS` lookups which contains the two glyphs.
+///
+fn gpos_kerning(
+    face: &ttf_parser::Face,
+    lookups: &[u16],
+    left: ttf_parser::GlyphId,
+    right: ttf_parser::GlyphId,
+) -> Option<i16> {
+    use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+    let gpos = face.tables().gpos?;
+    lookups
+        .iter()
+        .filter_map(|index| gpos.lookups.get(*index))
+        .flat_map(|lookup| lookup.subtables.into_iter::<PositioningSubtable>())
+        .find_map(|subtable| match subtable {
+            PositioningSubtable::Pair(PairAdjustment::Format1 { coverage, sets }) => coverage
+                .get(left)
+                .and_then(|index| sets.get(index))
+                .and_then(|set| set.get(right)),
+            PositioningSubtable::Pair(PairAdjustment::Format2 {
+                coverage,
+                classes,
+                matrix,
+            }) => coverage
+                .contains(left)
+                .then(|| matrix.get((classes.0.get(left), classes.1.get(right))))
+                .flatten(),
+            _ => None,
+        })
+        .map(|(first, _)| first.x_advance)
+}
+
+///
+/// Returns the kerning in font units between the two glyphs from the `kern` table.
+///
+fn kern_table_kerning(
+    face: &ttf_parser::Face,
+    left: ttf_parser::GlyphId,
+    right: ttf_parser::GlyphId,
+) -> Option<i16> {
+    face.tables().kern.and_then(|kern| {
+        kern.subtables
+            .into_iter()
+            .filter(|s| s.horizontal && !s.variable)
+            .find_map(|s| s.glyphs_kerning(left, right))
+    })
+}
+
+///
+/// Collects the outline of a glyph as line segments in font units.
+///
+struct Outline {
+    segments: Vec<(Vec2, Vec2)>,
+    start: Vec2,
+    current: Vec2,
+}
+
+impl Outline {
+    const CURVE_SUBDIVISIONS: usize = 8;
+
+    fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            start: vec2(0.0, 0.0),
+            current: vec2(0.0, 0.0),
+        }
+    }
+
+    fn line_to_point(&mut self, p: Vec2) {
+        ttf_parser::OutlineBuilder::line_to(self, p.x, p.y);
+    }
+}
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = vec2(x, y);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = vec2(x, y);
+        self.segments.push((self.current, p));
+        self.current = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, c, p) = (self.current, vec2(x1, y1), vec2(x, y));
+        for i in 1..=Self::CURVE_SUBDIVISIONS {
+            let t = i as f32 / Self::CURVE_SUBDIVISIONS as f32;
+            let s = 1.0 - t;
+            self.line_to_point(s * s * p0 + 2.0 * s * t * c + t * t * p);
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, c0, c1, p) = (self.current, vec2(x1, y1), vec2(x2, y2), vec2(x, y));
+        for i in 1..=Self::CURVE_SUBDIVISIONS {
+            let t = i as f32 / Self::CURVE_SUBDIVISIONS as f32;
+            let s = 1.0 - t;
+            self.line_to_point(
+                s * s * s * p0 + 3.0 * s * s * t * c0 + 3.0 * s * t * t * c1 + t * t * t * p,
+            );
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.segments.push((self.current, self.start));
+        }
+        self.current = self.start;
+    }
+}
+
+///
+/// Returns the distance to the closest segment, positive inside the outline and negative outside, using the non-zero winding rule.
+///
+fn signed_distance(segments: &[(Vec2, Vec2)], p: Vec2) -> f32 {
+    let mut distance2 = f32::MAX;
+    let mut winding = 0;
+    for (a, b) in segments {
+        let e = b - a;
+        let w = p - a;
+        let t = (w.dot(e) / e.magnitude2().max(1e-12)).clamp(0.0, 1.0);
+        distance2 = distance2.min((w - e * t).magnitude2());
+        let cross = e.x * w.y - e.y * w.x;
+        if a.y <= p.y && b.y > p.y && cross > 0.0 {
+            winding += 1;
+        } else if b.y <= p.y && a.y > p.y && cross < 0.0 {
+            winding -= 1;
+        }
+    }
+    if winding != 0 {
+        distance2.sqrt()
+    } else {
+        -distance2.sqrt()
+    }
+}
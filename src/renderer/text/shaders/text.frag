@@ -0,0 +1,30 @@
+uniform vec4 textColor;
+uniform vec4 outlineColor;
+uniform float outlineWidth;
+uniform sampler2D sdf;
+
+in vec2 uvs;
+in vec4 col;
+
+layout (location = 0) out vec4 outColor;
+
+void main()
+{
+    // The distance field is 0.5 at the edge of the glyphs and increases towards the inside
+    float distance = texture(sdf, uvs).r;
+    float w = max(fwidth(distance), 1e-5);
+
+    float textCoverage = clamp((distance - 0.5) / w + 0.5, 0.0, 1.0);
+    float outlineCoverage = outlineWidth > 0.0 ? clamp((distance - 0.5 + outlineWidth) / w + 0.5, 0.0, 1.0) : 0.0;
+
+    // Composite the text on top of the outline using premultiplied alpha
+    vec4 color = textColor * col;
+    vec4 text = vec4(color.rgb, 1.0) * color.a * textCoverage;
+    vec4 outline = vec4(outlineColor.rgb, 1.0) * outlineColor.a * outlineCoverage;
+    vec4 result = text + outline * (1.0 - text.a);
+    if (result.a < 0.001) {
+        discard;
+    }
+
+    outColor = vec4(color_mapping(result.rgb / result.a), result.a);
+}
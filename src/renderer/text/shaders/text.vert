@@ -0,0 +1,67 @@
+uniform mat4 viewProjection;
+uniform mat4 transformation;
+
+#ifdef BILLBOARD
+uniform mat4 view;
+#endif
+
+#ifdef SCREEN_BILLBOARD
+uniform vec2 viewportSize;
+#endif
+
+in vec3 position;
+in vec2 uv_coordinate;
+
+#ifdef USE_ANCHORS
+in vec3 anchor;
+#endif
+
+#ifdef USE_VERTEX_COLORS
+in vec4 color;
+#endif
+
+out vec3 pos;
+out vec2 uvs;
+out vec4 col;
+
+#ifdef USE_INSTANCE_ID
+flat out int instanceId;
+#endif
+
+void main()
+{
+#ifdef USE_INSTANCE_ID
+    instanceId = 0;
+#endif
+    uvs = uv_coordinate;
+    col = vec4(1.0);
+#ifdef USE_VERTEX_COLORS
+    col = color;
+#endif
+
+    // The origin of the text, which is offset for each label when the text contains multiple labels
+    vec4 origin = vec4(0.0, 0.0, 0.0, 1.0);
+#ifdef USE_ANCHORS
+    origin.xyz = anchor;
+#endif
+
+#if defined(BILLBOARD)
+    // Place the text in the plane facing the camera, scaled by the scale of the transformation
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    float scale = length(transformation[0].xyz);
+    vec4 center = transformation * origin;
+    pos = center.xyz / center.w + scale * (position.x * right + position.y * up);
+    gl_Position = viewProjection * vec4(pos, 1.0);
+#elif defined(SCREEN_BILLBOARD)
+    // Offset the projected anchor point by the position in pixels
+    vec4 center = transformation * origin;
+    pos = center.xyz / center.w;
+    gl_Position = viewProjection * center;
+    gl_Position.xy += 2.0 * position.xy / viewportSize * gl_Position.w;
+#else
+    vec4 worldPosition = transformation * vec4(origin.xyz + position, 1.0);
+    pos = worldPosition.xyz / worldPosition.w;
+    gl_Position = viewProjection * worldPosition;
+#endif
+}
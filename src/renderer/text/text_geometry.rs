@@ -0,0 +1,471 @@
+use crate::core::*;
+use crate::renderer::*;
+
+use super::Glyph;
+
+///
+/// The horizontal alignment of each line of a text relative to the origin of the text.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlignment {
+    /// The lines start at the origin.
+    Left,
+    /// The lines are centered around the origin.
+    Center,
+    /// The lines end at the origin.
+    Right,
+}
+
+///
+/// The vertical alignment of a text relative to the origin of the text.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    /// The top of the first line is at the origin.
+    Top,
+    /// The text is centered vertically around the origin.
+    Middle,
+    /// The baseline of the first line is at the origin.
+    Baseline,
+    /// The bottom of the last line is at the origin.
+    Bottom,
+}
+
+///
+/// Defines how a text is laid out in a [TextGeometry].
+///
+#[derive(Clone, Copy, Debug)]
+pub struct TextLayout {
+    /// The font size, ie. the size of an em, in the units of the [TextPlacement].
+    pub size: f32,
+    /// The distance between lines relative to the line height of the font.
+    pub line_spacing: f32,
+    /// The horizontal alignment of each line.
+    pub alignment: TextAlignment,
+    /// The vertical alignment of the text.
+    pub vertical_alignment: VerticalAlignment,
+    /// If specified, lines longer than this width are broken at whitespace.
+    /// Lines are always broken at new line characters.
+    pub max_width: Option<f32>,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            size: 1.0,
+            line_spacing: 1.0,
+            alignment: TextAlignment::Left,
+            vertical_alignment: VerticalAlignment::Baseline,
+            max_width: None,
+        }
+    }
+}
+
+///
+/// Defines where a [TextGeometry] is placed.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextPlacement {
+    /// The text is placed in the xy-plane transformed by the transformation of the text geometry, see [TextGeometry::set_transformation].
+    /// The size is in world units, which is pixels when using a camera created by [Camera::new_2d].
+    Plane,
+    /// The text always faces the camera and is placed at the origin transformed by the transformation of the text geometry.
+    /// The size is in world units, scaled by the scale of the transformation, so the text gets smaller when it is further away from the camera.
+    Billboard,
+    /// The text always faces the camera and is placed at the origin transformed by the transformation of the text geometry.
+    /// The size is in pixels, so the text has the same size on the screen independent of the distance to the camera.
+    ScreenBillboard,
+}
+
+///
+/// A geometry containing the glyphs of a text laid out using a [Font], see [TextLayout].
+/// Use it together with a [TextMaterial] created from the same font.
+/// The text is depth tested like any other geometry when placed in the 3D scene.
+///
+pub struct TextGeometry {
+    context: Context,
+    position_buffer: VertexBuffer,
+    uv_buffer: VertexBuffer,
+    labels: Option<(VertexBuffer, VertexBuffer)>,
+    vertex_count: u32,
+    placement: TextPlacement,
+    transformation: Mat4,
+    bounds: (Vec2, Vec2),
+}
+
+impl TextGeometry {
+    ///
+    /// Creates a new text geometry with the given text laid out using the given font and layout.
+    ///
+    pub fn new(
+        context: &Context,
+        font: &Font,
+        text: &str,
+        layout: &TextLayout,
+        placement: TextPlacement,
+    ) -> Self {
+        let mut geometry = Self {
+            context: context.clone(),
+            position_buffer: VertexBuffer::new(context),
+            uv_buffer: VertexBuffer::new(context),
+            labels: None,
+            vertex_count: 0,
+            placement,
+            transformation: Mat4::identity(),
+            bounds: (vec2(0.0, 0.0), vec2(0.0, 0.0)),
+        };
+        geometry.set_text(font, text, layout);
+        geometry
+    }
+
+    ///
+    /// Replaces the text with the given text laid out using the given font and layout.
+    ///
+    pub fn set_text(&mut self, font: &Font, text: &str, layout: &TextLayout) {
+        self.labels = None;
+        self.set_quads(layout_text(font, text, layout));
+    }
+
+    ///
+    /// Replaces the text with a number of labels, each laid out using the given font and layout and placed at the origin offset by the position of the label
+    /// and with the color of the label multiplied by the color of the [TextMaterial].
+    /// All of the labels are drawn in one draw call, since they share the atlas of the font.
+    ///
+    pub(crate) fn set_labels(
+        &mut self,
+        font: &Font,
+        labels: &[(Vec3, String, Srgba)],
+        layout: &TextLayout,
+    ) {
+        let mut quads = Vec::new();
+        let mut anchors = Vec::new();
+        let mut colors = Vec::new();
+        for (position, text, color) in labels {
+            let label_quads = layout_text(font, text, layout);
+            anchors.extend(std::iter::repeat_n(*position, 6 * label_quads.len()));
+            colors.extend(std::iter::repeat_n(
+                color.to_linear_srgb(),
+                6 * label_quads.len(),
+            ));
+            quads.extend(label_quads);
+        }
+        self.set_quads(quads);
+        let (anchor_buffer, color_buffer) = self.labels.get_or_insert_with(|| {
+            (
+                VertexBuffer::new(&self.context),
+                VertexBuffer::new(&self.context),
+            )
+        });
+        anchor_buffer.fill(&anchors);
+        color_buffer.fill(&colors);
+    }
+
+    fn set_quads(&mut self, quads: Vec<(Vec2, Vec2, Vec2, Vec2)>) {
+        let mut positions = Vec::with_capacity(6 * quads.len());
+        let mut uvs = Vec::with_capacity(6 * quads.len());
+        let mut min = vec2(f32::INFINITY, f32::INFINITY);
+        let mut max = vec2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (p0, p1, uv0, uv1) in quads {
+            for (x, y) in [(0, 0), (1, 0), (1, 1), (1, 1), (0, 1), (0, 0)] {
+                let p = vec2(
+                    if x == 0 { p0.x } else { p1.x },
+                    if y == 0 { p0.y } else { p1.y },
+                );
+                positions.push(p.extend(0.0));
+                uvs.push(vec2(
+                    if x == 0 { uv0.x } else { uv1.x },
+                    if y == 0 { uv0.y } else { uv1.y },
+                ));
+            }
+            min = vec2(min.x.min(p0.x), min.y.min(p0.y));
+            max = vec2(max.x.max(p1.x), max.y.max(p1.y));
+        }
+        self.bounds = if positions.is_empty() {
+            (vec2(0.0, 0.0), vec2(0.0, 0.0))
+        } else {
+            (min, max)
+        };
+        self.vertex_count = positions.len() as u32;
+        self.position_buffer.fill(&positions);
+        self.uv_buffer.fill(&uvs);
+    }
+
+    ///
+    /// Returns the local to world transformation applied to the text.
+    ///
+    pub fn transformation(&self) -> Mat4 {
+        self.transformation
+    }
+
+    ///
+    /// Set the local to world transformation applied to the text.
+    /// For billboards, only the translation and the scale of the transformation is used.
+    ///
+    pub fn set_transformation(&mut self, transformation: Mat4) {
+        self.transformation = transformation;
+    }
+
+    ///
+    /// Set the 2D transformation applied to the text when it is placed in the xy-plane using [TextPlacement::Plane].
+    ///
+    pub fn set_transformation_2d(&mut self, transformation: Mat3) {
+        self.set_transformation(Mat4::new(
+            transformation.x.x,
+            transformation.x.y,
+            0.0,
+            transformation.x.z,
+            transformation.y.x,
+            transformation.y.y,
+            0.0,
+            transformation.y.z,
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+            transformation.z.x,
+            transformation.z.y,
+            0.0,
+            transformation.z.z,
+        ));
+    }
+
+    ///
+    /// Returns where the text is placed.
+    ///
+    pub fn placement(&self) -> TextPlacement {
+        self.placement
+    }
+
+    ///
+    /// Set where the text is placed.
+    ///
+    pub fn set_placement(&mut self, placement: TextPlacement) {
+        self.placement = placement;
+    }
+
+    ///
+    /// Returns the minimum and maximum corner of the laid out text in the local coordinate system of the text.
+    ///
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        self.bounds
+    }
+}
+
+impl<'a> IntoIterator for &'a TextGeometry {
+    type Item = &'a dyn Geometry;
+    type IntoIter = std::iter::Once<&'a dyn Geometry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self)
+    }
+}
+
+impl Geometry for TextGeometry {
+    fn draw(
+        &self,
+        camera: &Camera,
+        program: &Program,
+        render_states: RenderStates,
+        attributes: FragmentAttributes,
+    ) {
+        if attributes.normal || attributes.tangents {
+            panic!("text geometry does not provide normals or tangents")
+        }
+        if self.vertex_count == 0 {
+            return;
+        }
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform("transformation", self.transformation);
+        program.use_uniform_if_required("view", camera.view());
+        program.use_uniform_if_required(
+            "viewportSize",
+            vec2(
+                camera.viewport().width as f32,
+                camera.viewport().height as f32,
+            ),
+        );
+        program.use_vertex_attribute("position", &self.position_buffer);
+        if program.requires_attribute("uv_coordinate") {
+            program.use_vertex_attribute("uv_coordinate", &self.uv_buffer);
+        }
+        if let Some((anchor_buffer, color_buffer)) = &self.labels {
+            program.use_vertex_attribute("anchor", anchor_buffer);
+            if program.requires_attribute("color") {
+                program.use_vertex_attribute("color", color_buffer);
+            }
+        }
+        program.draw_arrays(render_states, camera.viewport(), self.vertex_count)
+    }
+
+    fn vertex_shader_source(&self, required_attributes: FragmentAttributes) -> String {
+        format!(
+            "{}{}{}{}",
+            if self.labels.is_some() {
+                "#define USE_ANCHORS\n#define USE_VERTEX_COLORS\n"
+            } else {
+                ""
+            },
+            if required_attributes.instance_id {
+                "#define USE_INSTANCE_ID\n"
+            } else {
+                ""
+            },
+            match self.placement {
+                TextPlacement::Plane => "",
+                TextPlacement::Billboard => "#define BILLBOARD\n",
+                TextPlacement::ScreenBillboard => "#define SCREEN_BILLBOARD\n",
+            },
+            include_str!("shaders/text.vert")
+        )
+    }
+
+    fn id(&self, required_attributes: FragmentAttributes) -> u16 {
+        let mut id = 0b1u16 << 15
+            | match self.placement {
+                TextPlacement::Plane => 0b1000u16,
+                TextPlacement::Billboard => 0b1001u16,
+                TextPlacement::ScreenBillboard => 0b1010u16,
+            };
+        if self.labels.is_some() {
+            id |= 0b1u16 << 9;
+        }
+        if required_attributes.instance_id {
+            id |= 0b1u16 << 13;
+        }
+        id
+    }
+
+    fn render_with_material(
+        &self,
+        material: &dyn Material,
+        camera: &Camera,
+        lights: &[&dyn Light],
+    ) {
+        render_with_material(&self.context, camera, self, material, lights)
+    }
+
+    fn render_with_effect(
+        &self,
+        material: &dyn Effect,
+        camera: &Camera,
+        lights: &[&dyn Light],
+        color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        render_with_effect(
+            &self.context,
+            camera,
+            self,
+            material,
+            lights,
+            color_texture,
+            depth_texture,
+        )
+    }
+
+    fn aabb(&self) -> AxisAlignedBoundingBox {
+        if self.labels.is_some() {
+            return AxisAlignedBoundingBox::INFINITE;
+        }
+        let (min, max) = self.bounds;
+        match self.placement {
+            TextPlacement::Plane => {
+                let mut aabb =
+                    AxisAlignedBoundingBox::new_with_positions(&[min.extend(0.0), max.extend(0.0)]);
+                aabb.transform(&self.transformation);
+                aabb
+            }
+            TextPlacement::Billboard => {
+                let center = self.transformation.w.truncate();
+                let radius = self.transformation.x.truncate().magnitude()
+                    * min.magnitude().max(max.magnitude());
+                AxisAlignedBoundingBox::new_with_positions(&[
+                    center - vec3(radius, radius, radius),
+                    center + vec3(radius, radius, radius),
+                ])
+            }
+            TextPlacement::ScreenBillboard => AxisAlignedBoundingBox::INFINITE,
+        }
+    }
+}
+
+///
+/// Returns the minimum and maximum corner and uv coordinates of each visible glyph.
+///
+fn layout_text(font: &Font, text: &str, layout: &TextLayout) -> Vec<(Vec2, Vec2, Vec2, Vec2)> {
+    let size = layout.size;
+
+    // Break the text into lines of glyphs positioned relative to the start of the line
+    let mut lines: Vec<Vec<(char, Glyph, f32)>> = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<(char, Glyph, f32)> = Vec::new();
+        let mut pen = 0.0;
+        let mut word_start = 0;
+        let mut word_start_pen = 0.0;
+        for c in paragraph.chars() {
+            let Some(glyph) = font.glyph(c).copied() else {
+                continue;
+            };
+            if let Some((_, previous, _)) = line.last() {
+                pen += font.kerning(previous, &glyph) * size;
+            }
+            if c.is_whitespace() {
+                word_start = line.len() + 1;
+                word_start_pen = pen + glyph.advance * size;
+            } else if let Some(max_width) = layout.max_width {
+                if pen + glyph.advance * size > max_width && word_start > 0 {
+                    let word = line.split_off(word_start);
+                    lines.push(line);
+                    line = word
+                        .into_iter()
+                        .map(|(c, g, x)| (c, g, x - word_start_pen))
+                        .collect();
+                    pen -= word_start_pen;
+                    word_start = 0;
+                    word_start_pen = 0.0;
+                }
+            }
+            line.push((c, glyph, pen));
+            pen += glyph.advance * size;
+        }
+        lines.push(line);
+    }
+
+    let line_height = font.line_height() * size * layout.line_spacing;
+    let top = font.ascender() * size;
+    let bottom = -(lines.len() as f32 - 1.0) * line_height + font.descender() * size;
+    let dy = match layout.vertical_alignment {
+        VerticalAlignment::Top => -top,
+        VerticalAlignment::Middle => -0.5 * (top + bottom),
+        VerticalAlignment::Baseline => 0.0,
+        VerticalAlignment::Bottom => -bottom,
+    };
+
+    let mut quads = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        // Trailing whitespace does not count in the width of the line
+        let width = line
+            .iter()
+            .filter(|(c, _, _)| !c.is_whitespace())
+            .map(|(_, g, x)| x + g.advance * size)
+            .fold(0.0, f32::max);
+        let dx = match layout.alignment {
+            TextAlignment::Left => 0.0,
+            TextAlignment::Center => -0.5 * width,
+            TextAlignment::Right => -width,
+        };
+        let baseline = dy - i as f32 * line_height;
+        for (_, glyph, x) in line.iter() {
+            if let Some((min, max)) = glyph.bounds {
+                let origin = vec2(dx + x, baseline);
+                quads.push((
+                    origin + min * size,
+                    origin + max * size,
+                    glyph.uv_min,
+                    glyph.uv_max,
+                ));
+            }
+        }
+    }
+    quads
+}
@@ -0,0 +1,84 @@
+use crate::core::*;
+use crate::renderer::*;
+use std::sync::Arc;
+
+///
+/// A material for rendering a [TextGeometry] using the signed distance field atlas of a [Font].
+/// The glyphs are anti-aliased at any size and can have an outline.
+///
+#[derive(Clone)]
+pub struct TextMaterial {
+    /// The color of the text, which is multiplied by the vertex colors of the geometry if it has any.
+    pub color: Srgba,
+    /// The color of the outline of the glyphs.
+    pub outline_color: Srgba,
+    /// The width of the outline of the glyphs relative to the font size. Use zero to not draw an outline.
+    /// The width is limited to the maximum distance stored in the signed distance field of the font, which is an eighth of the font size.
+    pub outline_width: f32,
+    /// Render states.
+    pub render_states: RenderStates,
+    texture: Arc<Texture2D>,
+    spread: f32,
+}
+
+impl TextMaterial {
+    ///
+    /// Creates a new text material for rendering text with the given font.
+    ///
+    pub fn new(font: &Font) -> Self {
+        Self {
+            color: Srgba::BLACK,
+            outline_color: Srgba::WHITE,
+            outline_width: 0.0,
+            render_states: RenderStates {
+                write_mask: WriteMask::COLOR,
+                blend: Blend::TRANSPARENCY,
+                ..Default::default()
+            },
+            texture: font.texture().clone(),
+            spread: font.spread(),
+        }
+    }
+}
+
+impl Material for TextMaterial {
+    fn id(&self) -> u16 {
+        0b1u16 << 15 | 0b1u16 << 4 | 0b110u16
+    }
+
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        format!(
+            "{}{}",
+            ColorMapping::fragment_shader_source(),
+            include_str!("shaders/text.frag")
+        )
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            uv: true,
+            color: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, camera: &Camera, _lights: &[&dyn Light]) {
+        camera.color_mapping.use_uniforms(program);
+        program.use_uniform("textColor", self.color.to_linear_srgb());
+        program.use_uniform("outlineColor", self.outline_color.to_linear_srgb());
+        // The outline width in the range of the distance field where 0.5 is the edge of the glyph
+        program.use_uniform(
+            "outlineWidth",
+            (0.5 * self.outline_width.max(0.0) / self.spread).min(0.5),
+        );
+        program.use_texture("sdf", &self.texture);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        self.render_states
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Transparent
+    }
+}
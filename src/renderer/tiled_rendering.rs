@@ -0,0 +1,194 @@
+use crate::core::*;
+use crate::renderer::*;
+
+///
+/// A part of an image rendered by a [TiledRenderer].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderTile {
+    /// The column of the tile, where the left-most column is 0.
+    pub column: u32,
+    /// The row of the tile, where the top row is 0.
+    pub row: u32,
+    /// The distance in pixels from the left edge of the image to the left edge of the tile.
+    pub x: u32,
+    /// The distance in pixels from the top edge of the image to the top edge of the tile.
+    pub y: u32,
+    /// The width of the tile in pixels.
+    pub width: u32,
+    /// The height of the tile in pixels.
+    pub height: u32,
+}
+
+///
+/// Renders images which are larger than the maximum texture size, for example print-quality images, by splitting the image into tiles.
+/// Each tile is rendered with a camera created using [Camera::tile], which uses an off-center part of the projection of the original camera,
+/// so the tiles together give the same image as rendering the entire image with the original camera.
+///
+/// The size of the image is the size of the viewport of the camera, so to render a 16k image,
+/// create a camera with the viewport `Viewport::new_at_origo(16384, 16384)`.
+/// Screen effects which sample neighbouring pixels, for example [FxaaEffect], give visible seams between the tiles
+/// unless the [TiledRenderer::guard_band] is at least as wide as the distance the effect samples.
+///
+pub struct TiledRenderer {
+    /// The maximum width and height in pixels of each tile, not including the guard band.
+    /// The default is 2048 or the maximum texture size if that is smaller.
+    pub tile_size: u32,
+    /// The number of pixels which are rendered on each side of a tile in addition to the tile itself and then discarded.
+    /// The default is 0.
+    pub guard_band: u32,
+    context: Context,
+    max_texture_size: u32,
+}
+
+impl TiledRenderer {
+    ///
+    /// Creates a new tiled renderer.
+    ///
+    pub fn new(context: &Context) -> Self {
+        #[allow(unsafe_code)]
+        let max_texture_size =
+            unsafe { context.get_parameter_i32(crate::context::MAX_TEXTURE_SIZE) } as u32;
+        Self {
+            tile_size: max_texture_size.min(2048),
+            guard_band: 0,
+            context: context.clone(),
+            max_texture_size,
+        }
+    }
+
+    ///
+    /// Returns the tiles of an image with the given size, ordered from left to right and then from top to bottom.
+    ///
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<RenderTile> {
+        let tile_size = self.clamped_tile_size();
+        let mut tiles = Vec::new();
+        for (row, y) in (0..height).step_by(tile_size as usize).enumerate() {
+            for (column, x) in (0..width).step_by(tile_size as usize).enumerate() {
+                tiles.push(RenderTile {
+                    column: column as u32,
+                    row: row as u32,
+                    x,
+                    y,
+                    width: tile_size.min(width - x),
+                    height: tile_size.min(height - y),
+                });
+            }
+        }
+        tiles
+    }
+
+    ///
+    /// Renders the image seen by the camera and returns it as a [CpuTexture] where the first pixel is the top left pixel.
+    /// The `render` closure is called once for each tile with the camera for that tile and a render target with a color texture with the texture data type `T` and a depth texture.
+    /// The render target is not cleared before calling the closure.
+    /// Intermediate render targets, for example for screen effects, should have the size of the viewport of the camera given to the closure.
+    ///
+    /// The entire image is kept in memory, use [TiledRenderer::render_tiles] to process the tiles one at a time instead, for example to save them to disk.
+    ///
+    pub fn render<T: CpuTextureDataType>(
+        &self,
+        camera: &Camera,
+        render: impl FnMut(&Camera, &RenderTarget),
+    ) -> CpuTexture {
+        let viewport = camera.viewport();
+        let mut pixels = Vec::with_capacity(viewport.width as usize * viewport.height as usize);
+        let mut row = Vec::new();
+        self.render_tile_pixels::<T>(camera, render, |tile, tile_pixels| {
+            row.push((tile, tile_pixels));
+            if tile.x + tile.width == viewport.width {
+                // The row of tiles is complete, so the rows of pixels can be appended to the image
+                for y in 0..tile.height as usize {
+                    for (tile, tile_pixels) in row.iter() {
+                        let width = tile.width as usize;
+                        pixels.extend_from_slice(&tile_pixels[y * width..(y + 1) * width]);
+                    }
+                }
+                row.clear();
+            }
+        });
+        cpu_texture_from_pixels(pixels, viewport.width, viewport.height)
+    }
+
+    ///
+    /// Renders the image seen by the camera one tile at a time, see [TiledRenderer::render] for more information.
+    /// The `tile_rendered` closure is called with each tile and the rendered pixels of that tile as a [CpuTexture] where the first pixel is the top left pixel,
+    /// which for example makes it possible to save each tile to disk without keeping the entire image in memory.
+    ///
+    pub fn render_tiles<T: CpuTextureDataType>(
+        &self,
+        camera: &Camera,
+        render: impl FnMut(&Camera, &RenderTarget),
+        mut tile_rendered: impl FnMut(RenderTile, CpuTexture),
+    ) {
+        self.render_tile_pixels::<T>(camera, render, |tile, pixels| {
+            tile_rendered(
+                tile,
+                cpu_texture_from_pixels(pixels, tile.width, tile.height),
+            )
+        });
+    }
+
+    fn render_tile_pixels<T: CpuTextureDataType>(
+        &self,
+        camera: &Camera,
+        mut render: impl FnMut(&Camera, &RenderTarget),
+        mut tile_rendered: impl FnMut(RenderTile, Vec<T>),
+    ) {
+        let viewport = camera.viewport();
+        let tiles = self.tiles(viewport.width, viewport.height);
+        let texture_size = tiles
+            .iter()
+            .map(|tile| tile.width.max(tile.height))
+            .max()
+            .unwrap_or(0)
+            + 2 * self.guard_band;
+        let mut color_texture = Texture2D::new_empty::<T>(
+            &self.context,
+            texture_size,
+            texture_size,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let mut depth_texture = DepthTexture2D::new::<f32>(
+            &self.context,
+            texture_size,
+            texture_size,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let render_target = RenderTarget::new(
+            color_texture.as_color_target(None),
+            depth_texture.as_depth_target(),
+        );
+        let guard_band = self.guard_band as i32;
+        for tile in tiles {
+            let tile_camera = camera.tile(Viewport {
+                x: tile.x as i32 - guard_band,
+                // The viewport is relative to the bottom left corner while the tiles are relative to the top left corner
+                y: (viewport.height - tile.y - tile.height) as i32 - guard_band,
+                width: tile.width + 2 * self.guard_band,
+                height: tile.height + 2 * self.guard_band,
+            });
+            render(&tile_camera, &render_target);
+            tile_rendered(
+                tile,
+                render_target.read_color_partially(ScissorBox {
+                    x: guard_band,
+                    y: guard_band,
+                    width: tile.width,
+                    height: tile.height,
+                }),
+            );
+        }
+    }
+
+    fn clamped_tile_size(&self) -> u32 {
+        self.tile_size
+            .min(self.max_texture_size.saturating_sub(2 * self.guard_band))
+            .max(1)
+    }
+}
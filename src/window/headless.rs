@@ -1,11 +1,12 @@
+use crate::context::HasContext;
 use crate::{Context, CoreError};
-use glutin_029::{
-    dpi::PhysicalSize, event_loop::EventLoop, ContextBuilder, ContextCurrentState, CreationError,
-    NotCurrent, PossiblyCurrent,
-};
 use std::rc::Rc;
 use thiserror::Error;
 
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+mod egl;
+mod hidden_window;
+
 ///
 /// Error associated with a headless context.
 ///
@@ -13,13 +14,81 @@ use thiserror::Error;
 #[allow(missing_docs)]
 pub enum HeadlessError {
     #[error("glutin error")]
-    GlutinCreationError(#[from] glutin_029::CreationError),
-    #[error("glutin error")]
-    GlutinContextError(#[from] glutin_029::ContextError),
+    GlutinError(#[from] glutin::error::Error),
+    #[error("winit error")]
+    WinitError(#[from] winit::error::OsError),
+    #[error("EGL error: {0}")]
+    EglError(String),
+    #[error("the {0:?} backend is not supported on this platform")]
+    BackendNotSupported(HeadlessBackend),
+    #[error("no display server is available")]
+    NoDisplayServer,
+    #[error("the hidden window backend can only create one context per process")]
+    EventLoopAlreadyCreated,
+    #[error("none of the headless backends could create a graphics context:{0}")]
+    NoBackend(String),
     #[error("error in three-d")]
     ThreeDError(#[from] CoreError),
 }
 
+///
+/// The graphics API and version of a [HeadlessContext].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadlessApi {
+    /// OpenGL core profile with the given major and minor version, at least version 3.3 is required.
+    OpenGl(u8, u8),
+    /// OpenGL ES with the given major and minor version, at least version 3.0 is required.
+    OpenGlEs(u8, u8),
+}
+
+///
+/// The backend used to create a [HeadlessContext].
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadlessBackend {
+    /// EGL on the surfaceless platform (`EGL_MESA_platform_surfaceless`) which does not need a display server.
+    /// Only available on Linux and other unix platforms with EGL.
+    EglSurfaceless,
+    /// EGL on the device platform (`EGL_EXT_platform_device`) which does not need a display server.
+    /// Hardware devices are tried before software devices.
+    /// Only available on Linux and other unix platforms with EGL.
+    EglDevice,
+    /// A graphics context associated with a hidden window, which requires a display server.
+    /// Only one context can be created with this backend in each process, since it requires an event loop.
+    HiddenWindow,
+}
+
+///
+/// Settings for creating a [HeadlessContext].
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeadlessSettings {
+    /// The graphics API and version of the context.
+    /// The default is OpenGL 3.3.
+    pub api: HeadlessApi,
+    /// The backends to try, in order, until one of them succeeds in creating a context.
+    /// The default is [HeadlessBackend::EglSurfaceless], [HeadlessBackend::EglDevice] and then [HeadlessBackend::HiddenWindow]
+    /// on platforms with EGL and only [HeadlessBackend::HiddenWindow] on other platforms.
+    pub backends: Vec<HeadlessBackend>,
+}
+
+impl Default for HeadlessSettings {
+    fn default() -> Self {
+        Self {
+            api: HeadlessApi::OpenGl(3, 3),
+            #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+            backends: vec![
+                HeadlessBackend::EglSurfaceless,
+                HeadlessBackend::EglDevice,
+                HeadlessBackend::HiddenWindow,
+            ],
+            #[cfg(not(all(unix, not(any(target_os = "macos", target_os = "ios")))))]
+            backends: vec![HeadlessBackend::HiddenWindow],
+        }
+    }
+}
+
 ///
 /// A headless graphics context, ie. a graphics context that is not associated with any window.
 /// For a graphics context associated with a window, see [WindowedContext](crate::WindowedContext).
@@ -28,100 +97,93 @@ pub enum HeadlessError {
 #[derive(Clone)]
 pub struct HeadlessContext {
     context: Context,
-    _glutin_context: Rc<glutin_029::Context<PossiblyCurrent>>,
+    backend: HeadlessBackend,
+    renderer: String,
+    _native_context: Rc<dyn std::any::Any>,
 }
 
 impl HeadlessContext {
     ///
-    /// Creates a new headless graphics context.
+    /// Creates a new headless graphics context with the default [HeadlessSettings].
     ///
-    #[allow(unsafe_code)]
     pub fn new() -> Result<Self, HeadlessError> {
-        let cb = ContextBuilder::new();
-        let glutin_context = build_context(cb)?;
-        let glutin_context = unsafe { glutin_context.make_current().map_err(|(_, e)| e)? };
-        let context = Context::from_gl_context(std::sync::Arc::new(unsafe {
-            crate::context::Context::from_loader_function(|s| {
-                glutin_context.get_proc_address(s) as *const _
-            })
-        }))?;
-        Ok(Self {
-            context,
-            _glutin_context: Rc::new(glutin_context),
-        })
+        Self::new_with_settings(HeadlessSettings::default())
     }
-}
 
-impl std::ops::Deref for HeadlessContext {
-    type Target = Context;
-    fn deref(&self) -> &Self::Target {
-        &self.context
+    ///
+    /// Creates a new headless graphics context using the first of the backends in the settings which succeeds.
+    /// If all of them fails, the returned error contains the reason each of the backends failed.
+    ///
+    #[allow(unsafe_code)]
+    pub fn new_with_settings(settings: HeadlessSettings) -> Result<Self, HeadlessError> {
+        let mut errors = String::new();
+        for backend in settings.backends {
+            let result = match backend {
+                #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+                HeadlessBackend::EglSurfaceless | HeadlessBackend::EglDevice => {
+                    egl::EglContext::new(backend, settings.api).and_then(|c| {
+                        let context = context_from_loader(|s| c.get_proc_address(s))?;
+                        Ok((context, Rc::new(c) as Rc<dyn std::any::Any>))
+                    })
+                }
+                #[cfg(not(all(unix, not(any(target_os = "macos", target_os = "ios")))))]
+                HeadlessBackend::EglSurfaceless | HeadlessBackend::EglDevice => {
+                    Err(HeadlessError::BackendNotSupported(backend))
+                }
+                HeadlessBackend::HiddenWindow => {
+                    hidden_window::HiddenWindowContext::new(settings.api).and_then(|c| {
+                        let context = context_from_loader(|s| c.get_proc_address(s))?;
+                        Ok((context, Rc::new(c) as Rc<dyn std::any::Any>))
+                    })
+                }
+            };
+            match result {
+                Ok((context, native_context)) => {
+                    let renderer =
+                        unsafe { context.get_parameter_string(crate::context::RENDERER) };
+                    return Ok(Self {
+                        context,
+                        backend,
+                        renderer,
+                        _native_context: native_context,
+                    });
+                }
+                Err(e) => errors.push_str(&format!("\n{:?}: {}", backend, e)),
+            }
+        }
+        Err(HeadlessError::NoBackend(errors))
     }
-}
-
-/*#[cfg(target_os = "linux")]
-fn build_context_surfaceless<T1: ContextCurrentState>(
-    cb: ContextBuilder<T1>,
-    el: &EventLoop<()>,
-) -> Result<glutin_029::Context<NotCurrent>, CreationError> {
-    use glutin_029::platform::unix::HeadlessContextExt;
-    cb.build_surfaceless(&el)
-}*/
 
-fn build_context_headless<T1: ContextCurrentState>(
-    cb: ContextBuilder<T1>,
-    el: &EventLoop<()>,
-) -> Result<glutin_029::Context<NotCurrent>, CreationError> {
-    let size_one = PhysicalSize::new(1, 1);
-    cb.build_headless(&el, size_one)
-}
+    ///
+    /// Returns the backend that was used to create this context.
+    ///
+    pub fn backend(&self) -> HeadlessBackend {
+        self.backend
+    }
 
-#[cfg(target_os = "linux")]
-fn build_context_osmesa<T1: ContextCurrentState>(
-    cb: ContextBuilder<T1>,
-) -> Result<glutin_029::Context<NotCurrent>, CreationError> {
-    use glutin_029::platform::unix::HeadlessContextExt;
-    let size_one = PhysicalSize::new(1, 1);
-    cb.build_osmesa(size_one)
+    ///
+    /// Returns the renderer string reported by the graphics driver (`GL_RENDERER`), which usually contains the name of the GPU.
+    ///
+    pub fn renderer(&self) -> &str {
+        &self.renderer
+    }
 }
 
-#[cfg(target_os = "linux")]
-fn build_context<T1: ContextCurrentState>(
-    cb: ContextBuilder<T1>,
-) -> Result<glutin_029::Context<NotCurrent>, CreationError> {
-    // On unix operating systems, you should always try for surfaceless first,
-    // and if that does not work, headless (pbuffers), and if that too fails,
-    // finally osmesa.
-    //
-    // If willing, you could attempt to use hidden windows instead of os mesa,
-    // but note that you must handle events for the window that come on the
-    // events loop.
-
-    /*
-    let err1 = match build_context_surfaceless(cb.clone(), &el) {
-        Ok(ctx) => return Ok((ctx, el)),
-        Err(err) => err,
-    };*/
-
-    let _err3 = match build_context_osmesa(cb.clone()) {
-        Ok(ctx) => return Ok(ctx),
-        Err(err) => err,
-    };
-
-    let el = EventLoop::new();
-
-    let err2 = match build_context_headless(cb, &el) {
-        Ok(ctx) => return Ok(ctx),
-        Err(err) => err,
-    };
-
-    Err(err2)
+#[allow(unsafe_code)]
+fn context_from_loader(
+    get_proc_address: impl Fn(&str) -> *const std::ffi::c_void,
+) -> Result<Context, CoreError> {
+    let gl = unsafe { crate::context::Context::from_loader_function(&get_proc_address) };
+    // The OpenGL context is only used on the thread where it is current
+    #[allow(clippy::arc_with_non_send_sync)]
+    let mut context = Context::from_gl_context(std::sync::Arc::new(gl))?;
+    context.load_functions(get_proc_address);
+    Ok(context)
 }
 
-#[cfg(not(target_os = "linux"))]
-fn build_context<T1: ContextCurrentState>(
-    cb: ContextBuilder<T1>,
-) -> Result<glutin_029::Context<NotCurrent>, CreationError> {
-    let el = EventLoop::new();
-    build_context_headless(cb.clone(), &el)
+impl std::ops::Deref for HeadlessContext {
+    type Target = Context;
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
 }
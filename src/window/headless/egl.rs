@@ -0,0 +1,268 @@
+use super::{HeadlessApi, HeadlessBackend, HeadlessError};
+use glutin_egl_sys::egl;
+use glutin_egl_sys::egl::types::{EGLContext, EGLDeviceEXT, EGLDisplay, EGLenum, EGLint};
+use std::collections::HashSet;
+use std::ffi::{c_void, CStr, CString};
+
+// From the EGL_MESA_platform_surfaceless extension which is not part of the generated bindings.
+const PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+///
+/// An EGL context which is current without any surface.
+///
+pub(super) struct EglContext {
+    egl: egl::Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    _library: libloading::Library,
+}
+
+impl EglContext {
+    #[allow(unsafe_code)]
+    pub fn new(backend: HeadlessBackend, api: HeadlessApi) -> Result<Self, HeadlessError> {
+        let library = ["libEGL.so.1", "libEGL.so"]
+            .into_iter()
+            .find_map(|path| unsafe { libloading::Library::new(path) }.ok())
+            .ok_or_else(|| HeadlessError::EglError("unable to load libEGL".to_owned()))?;
+        let get_proc_address = unsafe {
+            library
+                .get::<unsafe extern "C" fn(*const std::os::raw::c_char) -> *const c_void>(
+                    b"eglGetProcAddress\0",
+                )
+                .map_err(|e| HeadlessError::EglError(e.to_string()))?
+        };
+        // Extension functions are not necessarily exported by the library, so those are found using eglGetProcAddress
+        let egl = egl::Egl::load_with(|name| {
+            let name = CString::new(name).unwrap();
+            unsafe {
+                library
+                    .get::<*const c_void>(name.as_bytes_with_nul())
+                    .map(|symbol| *symbol)
+                    .unwrap_or_else(|_| get_proc_address(name.as_ptr()))
+            }
+        });
+        if !egl.GetPlatformDisplayEXT.is_loaded() {
+            Err(HeadlessError::EglError(
+                "eglGetPlatformDisplayEXT is not supported".to_owned(),
+            ))?;
+        }
+        let client_extensions = extensions(&egl, egl::NO_DISPLAY);
+
+        let (display, context) = match backend {
+            HeadlessBackend::EglSurfaceless => {
+                require(&client_extensions, "EGL_MESA_platform_surfaceless")?;
+                let display = unsafe {
+                    egl.GetPlatformDisplayEXT(
+                        PLATFORM_SURFACELESS_MESA,
+                        egl::DEFAULT_DISPLAY as *mut _,
+                        [egl::NONE as EGLint].as_ptr(),
+                    )
+                };
+                (display, create_context(&egl, display, api)?)
+            }
+            HeadlessBackend::EglDevice => {
+                require(&client_extensions, "EGL_EXT_platform_device")?;
+                let mut result = Err(HeadlessError::EglError("no devices found".to_owned()));
+                for device in devices(&egl, &client_extensions)? {
+                    let display = unsafe {
+                        egl.GetPlatformDisplayEXT(
+                            egl::PLATFORM_DEVICE_EXT,
+                            device as *mut _,
+                            [egl::NONE as EGLint].as_ptr(),
+                        )
+                    };
+                    result = create_context(&egl, display, api).map(|context| (display, context));
+                    if result.is_ok() {
+                        break;
+                    }
+                }
+                result?
+            }
+            HeadlessBackend::HiddenWindow => Err(HeadlessError::BackendNotSupported(backend))?,
+        };
+        Ok(Self {
+            egl,
+            display,
+            context,
+            _library: library,
+        })
+    }
+
+    #[allow(unsafe_code)]
+    pub fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        unsafe { self.egl.GetProcAddress(name.as_ptr()) as *const _ }
+    }
+}
+
+impl Drop for EglContext {
+    #[allow(unsafe_code)]
+    fn drop(&mut self) {
+        // The display is not terminated, since it is shared with other contexts created on the same display or device
+        unsafe {
+            self.egl.MakeCurrent(
+                self.display,
+                egl::NO_SURFACE,
+                egl::NO_SURFACE,
+                egl::NO_CONTEXT,
+            );
+            self.egl.DestroyContext(self.display, self.context);
+        }
+    }
+}
+
+///
+/// Initializes the display and creates a context with the given API and version which is made current without a surface.
+///
+#[allow(unsafe_code)]
+fn create_context(
+    egl: &egl::Egl,
+    display: EGLDisplay,
+    api: HeadlessApi,
+) -> Result<EGLContext, HeadlessError> {
+    if display == egl::NO_DISPLAY {
+        Err(error(egl, "unable to get the display"))?;
+    }
+    if unsafe { egl.Initialize(display, std::ptr::null_mut(), std::ptr::null_mut()) } == egl::FALSE
+    {
+        Err(error(egl, "unable to initialize the display"))?;
+    }
+    require(&extensions(egl, display), "EGL_KHR_surfaceless_context")?;
+
+    let (egl_api, renderable_type, major, minor) = match api {
+        HeadlessApi::OpenGl(major, minor) => (egl::OPENGL_API, egl::OPENGL_BIT, major, minor),
+        HeadlessApi::OpenGlEs(major, minor) => {
+            (egl::OPENGL_ES_API, egl::OPENGL_ES3_BIT, major, minor)
+        }
+    };
+    if unsafe { egl.BindAPI(egl_api) } == egl::FALSE {
+        Err(error(egl, "unable to bind the graphics API"))?;
+    }
+
+    // No specific surface type is required, since the context is never used with a surface
+    let config_attributes = [
+        egl::RENDERABLE_TYPE as EGLint,
+        renderable_type as EGLint,
+        egl::SURFACE_TYPE as EGLint,
+        0,
+        egl::NONE as EGLint,
+    ];
+    let mut config = std::ptr::null();
+    let mut config_count = 0;
+    if unsafe {
+        egl.ChooseConfig(
+            display,
+            config_attributes.as_ptr(),
+            &mut config,
+            1,
+            &mut config_count,
+        )
+    } == egl::FALSE
+        || config_count == 0
+    {
+        Err(error(egl, "no config supports the requested graphics API"))?;
+    }
+
+    let mut context_attributes = vec![
+        egl::CONTEXT_MAJOR_VERSION as EGLint,
+        major as EGLint,
+        egl::CONTEXT_MINOR_VERSION as EGLint,
+        minor as EGLint,
+    ];
+    if let HeadlessApi::OpenGl(..) = api {
+        context_attributes.push(egl::CONTEXT_OPENGL_PROFILE_MASK as EGLint);
+        context_attributes.push(egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as EGLint);
+    }
+    context_attributes.push(egl::NONE as EGLint);
+    let context = unsafe {
+        egl.CreateContext(
+            display,
+            config,
+            egl::NO_CONTEXT,
+            context_attributes.as_ptr(),
+        )
+    };
+    if context == egl::NO_CONTEXT {
+        Err(error(
+            egl,
+            &format!("unable to create a context with {:?}", api),
+        ))?;
+    }
+    if unsafe { egl.MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, context) } == egl::FALSE
+    {
+        let e = error(egl, "unable to make the context current");
+        unsafe { egl.DestroyContext(display, context) };
+        Err(e)?;
+    }
+    Ok(context)
+}
+
+///
+/// Returns the available devices with hardware devices before software devices.
+///
+#[allow(unsafe_code)]
+fn devices(
+    egl: &egl::Egl,
+    client_extensions: &HashSet<String>,
+) -> Result<Vec<EGLDeviceEXT>, HeadlessError> {
+    if !client_extensions.contains("EGL_EXT_device_enumeration")
+        && !client_extensions.contains("EGL_EXT_device_base")
+    {
+        Err(HeadlessError::EglError(
+            "EGL_EXT_device_enumeration is not supported".to_owned(),
+        ))?;
+    }
+    let mut count = 0;
+    if unsafe { egl.QueryDevicesEXT(0, std::ptr::null_mut(), &mut count) } == egl::FALSE {
+        Err(error(egl, "unable to query the devices"))?;
+    }
+    let mut devices = vec![std::ptr::null(); count as usize];
+    if unsafe { egl.QueryDevicesEXT(count, devices.as_mut_ptr(), &mut count) } == egl::FALSE {
+        Err(error(egl, "unable to query the devices"))?;
+    }
+    devices.truncate(count as usize);
+    if egl.QueryDeviceStringEXT.is_loaded() {
+        devices.sort_by_key(|device| {
+            let extensions =
+                unsafe { egl.QueryDeviceStringEXT(*device, egl::EXTENSIONS as EGLint) };
+            !extensions.is_null()
+                && unsafe { CStr::from_ptr(extensions) }
+                    .to_string_lossy()
+                    .split(' ')
+                    .any(|e| e == "EGL_MESA_device_software")
+        });
+    }
+    Ok(devices)
+}
+
+#[allow(unsafe_code)]
+fn extensions(egl: &egl::Egl, display: EGLDisplay) -> HashSet<String> {
+    let extensions = unsafe { egl.QueryString(display, egl::EXTENSIONS as EGLint) };
+    if extensions.is_null() {
+        // Querying the client extensions is not supported, which also means that no platform extensions are supported
+        unsafe { egl.GetError() };
+        return HashSet::new();
+    }
+    unsafe { CStr::from_ptr(extensions) }
+        .to_string_lossy()
+        .split_whitespace()
+        .map(|e| e.to_owned())
+        .collect()
+}
+
+fn require(extensions: &HashSet<String>, extension: &str) -> Result<(), HeadlessError> {
+    if extensions.contains(extension) {
+        Ok(())
+    } else {
+        Err(HeadlessError::EglError(format!(
+            "{} is not supported",
+            extension
+        )))
+    }
+}
+
+#[allow(unsafe_code)]
+fn error(egl: &egl::Egl, message: &str) -> HeadlessError {
+    let code = unsafe { egl.GetError() };
+    HeadlessError::EglError(format!("{} (error code {:#x})", message, code))
+}
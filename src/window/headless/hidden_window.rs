@@ -0,0 +1,132 @@
+use super::{HeadlessApi, HeadlessBackend, HeadlessError};
+use glutin::prelude::*;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use std::ffi::{c_void, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use winit::event_loop::{EventLoop, EventLoopBuilder};
+
+///
+/// A graphics context associated with an invisible window.
+///
+pub(super) struct HiddenWindowContext {
+    display: glutin::display::Display,
+    _context: glutin::context::PossiblyCurrentContext,
+    _surface: glutin::surface::Surface<glutin::surface::WindowSurface>,
+    _window: winit::window::Window,
+    _event_loop: EventLoop<()>,
+}
+
+impl HiddenWindowContext {
+    #[allow(unsafe_code)]
+    pub fn new(api: HeadlessApi) -> Result<Self, HeadlessError> {
+        // Creating an event loop panics when there is no display server
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            Err(HeadlessError::NoDisplayServer)?;
+        }
+        // Creating more than one event loop in a process panics
+        static EVENT_LOOP_CREATED: AtomicBool = AtomicBool::new(false);
+        if EVENT_LOOP_CREATED.swap(true, Ordering::SeqCst) {
+            Err(HeadlessError::EventLoopAlreadyCreated)?;
+        }
+        let mut event_loop_builder = EventLoopBuilder::new();
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(
+            &mut event_loop_builder,
+            true,
+        );
+        #[cfg(target_os = "windows")]
+        winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(
+            &mut event_loop_builder,
+            true,
+        );
+        let event_loop = event_loop_builder.build();
+        let window = winit::window::WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(winit::dpi::PhysicalSize::new(1, 1))
+            .build(&event_loop)?;
+        let raw_display_handle = window.raw_display_handle();
+        let raw_window_handle = window.raw_window_handle();
+
+        #[cfg(target_os = "windows")]
+        let preference = glutin::display::DisplayApiPreference::WglThenEgl(Some(raw_window_handle));
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        let preference = glutin::display::DisplayApiPreference::EglThenGlx(Box::new(
+            winit::platform::x11::register_xlib_error_hook,
+        ));
+        #[cfg(target_os = "macos")]
+        let preference = glutin::display::DisplayApiPreference::Cgl;
+        #[cfg(not(any(
+            target_os = "windows",
+            target_os = "macos",
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )))]
+        let preference = glutin::display::DisplayApiPreference::Egl;
+
+        let display = unsafe { glutin::display::Display::new(raw_display_handle, preference)? };
+        let config_template = glutin::config::ConfigTemplateBuilder::new()
+            .compatible_with_native_window(raw_window_handle)
+            .build();
+        let config = unsafe {
+            display.find_configs(config_template)?.next().ok_or(
+                HeadlessError::BackendNotSupported(HeadlessBackend::HiddenWindow),
+            )?
+        };
+        let context_api = match api {
+            HeadlessApi::OpenGl(major, minor) => glutin::context::ContextApi::OpenGl(Some(
+                glutin::context::Version::new(major, minor),
+            )),
+            HeadlessApi::OpenGlEs(major, minor) => {
+                glutin::context::ContextApi::Gles(Some(glutin::context::Version::new(major, minor)))
+            }
+        };
+        let context_attributes = glutin::context::ContextAttributesBuilder::new()
+            .with_context_api(context_api)
+            .with_profile(glutin::context::GlProfile::Core)
+            .build(Some(raw_window_handle));
+        let surface_attributes =
+            glutin::surface::SurfaceAttributesBuilder::<glutin::surface::WindowSurface>::new()
+                .build(
+                    raw_window_handle,
+                    std::num::NonZeroU32::new(1).unwrap(),
+                    std::num::NonZeroU32::new(1).unwrap(),
+                );
+        let context = unsafe { display.create_context(&config, &context_attributes)? };
+        let surface = unsafe { display.create_window_surface(&config, &surface_attributes)? };
+        let context = context.make_current(&surface)?;
+        Ok(Self {
+            display,
+            _context: context,
+            _surface: surface,
+            _window: window,
+            _event_loop: event_loop,
+        })
+    }
+
+    pub fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        self.display.get_proc_address(&name)
+    }
+}
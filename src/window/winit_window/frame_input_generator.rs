@@ -89,8 +89,9 @@ impl FrameInputGenerator {
         #[cfg(not(target_arch = "wasm32"))]
         if let Some(exit_time) = option_env!("THREE_D_EXIT").map(|v| v.parse::<f64>().unwrap()) {
             if exit_time < frame_input.accumulated_time {
-                #[cfg(feature = "image")]
+                #[cfg(feature = "image-export")]
                 if let Some(path) = option_env!("THREE_D_SCREENSHOT") {
+                    // The screenshot is resized to the logical size of the window, which differs from the size of the screen on high DPI displays
                     let pixels = frame_input.screen().read_color::<[u8; 4]>();
                     let img = image::DynamicImage::ImageRgba8(
                         image::ImageBuffer::from_raw(
@@ -192,15 +192,20 @@ mod inner {
             let gl_context = gl_context.make_current(&gl_surface)?;
             gl_surface.set_swap_interval(&gl_context, swap_interval)?;
 
-            Ok(Self {
-                context: Context::from_gl_context(Arc::new(unsafe {
-                    crate::context::Context::from_loader_function(|s| {
-                        let s = std::ffi::CString::new(s)
-                            .expect("failed to construct C string from string for gl proc address");
+            let get_proc_address = |s: &str| {
+                let s = std::ffi::CString::new(s)
+                    .expect("failed to construct C string from string for gl proc address");
 
-                        gl_display.get_proc_address(&s)
-                    })
-                }))?,
+                gl_display.get_proc_address(&s)
+            };
+            // The OpenGL context is only used on the thread where it is current
+            #[allow(clippy::arc_with_non_send_sync)]
+            let mut context = Context::from_gl_context(Arc::new(unsafe {
+                crate::context::Context::from_loader_function(get_proc_address)
+            }))?;
+            context.load_functions(get_proc_address);
+            Ok(Self {
+                context,
                 glutin_context: gl_context,
                 surface: gl_surface,
             })
@@ -0,0 +1,549 @@
+//!
+//! Golden image tests of the built-in materials, lights and effects.
+//! Run with `THREE_D_BLESS=1 cargo test --features golden-test --test golden` to update the reference images in `tests/golden`.
+//!
+
+use three_d::golden::*;
+use three_d::*;
+
+fn golden() -> GoldenImageTest {
+    GoldenImageTest::new(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"),
+        128,
+        128,
+    )
+    .unwrap()
+}
+
+fn camera(test: &GoldenImageTest) -> Camera {
+    Camera::new_perspective(
+        test.viewport(),
+        vec3(3.0, 2.5, 4.0),
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        degrees(45.0),
+        0.1,
+        100.0,
+    )
+}
+
+fn sphere(context: &Context) -> Mesh {
+    Mesh::new(context, &CpuMesh::sphere(32))
+}
+
+fn ground(context: &Context) -> Mesh {
+    let mut ground = Mesh::new(context, &CpuMesh::square());
+    ground.set_transformation(
+        Mat4::from_translation(vec3(0.0, -1.0, 0.0))
+            * Mat4::from_scale(4.0)
+            * Mat4::from_angle_x(degrees(-90.0)),
+    );
+    ground
+}
+
+fn material(albedo: Srgba) -> CpuMaterial {
+    CpuMaterial {
+        albedo,
+        roughness: 0.4,
+        metallic: 0.2,
+        ..Default::default()
+    }
+}
+
+fn render_with_lights(name: &str, lights: impl FnOnce(&Context, &[&Mesh]) -> Vec<Box<dyn Light>>) {
+    let test = golden();
+    let context = test.context();
+    let sphere = Gm::new(
+        sphere(context),
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(200, 60, 40))),
+    );
+    let ground = Gm::new(
+        ground(context),
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(180, 180, 180))),
+    );
+    let lights = lights(context, &[&sphere.geometry, &ground.geometry]);
+    let lights = lights.iter().map(|l| l.as_ref()).collect::<Vec<_>>();
+    test.render(name, |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), sphere.into_iter().chain(&ground), &lights);
+    })
+    .unwrap();
+}
+
+fn render_with_material<M: Material>(name: &str, material: impl FnOnce(&Context) -> M) {
+    let test = golden();
+    let context = test.context();
+    let sphere = Gm::new(sphere(context), material(context));
+    test.render(name, |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), &sphere, &[]);
+    })
+    .unwrap();
+}
+
+fn environment_map(context: &Context) -> Skybox {
+    let side = |color: [u8; 4]| CpuTexture {
+        data: TextureData::RgbaU8(vec![color; 16 * 16]),
+        width: 16,
+        height: 16,
+        ..Default::default()
+    };
+    Skybox::new(
+        context,
+        &side([200, 80, 80, 255]),
+        &side([80, 200, 80, 255]),
+        &side([220, 220, 255, 255]),
+        &side([60, 50, 40, 255]),
+        &side([80, 80, 200, 255]),
+        &side([200, 200, 80, 255]),
+    )
+}
+
+#[test]
+fn ambient_light() {
+    render_with_lights("ambient_light", |context, _| {
+        vec![
+            Box::new(AmbientLight::new(context, 0.5, Srgba::WHITE)),
+            Box::new(DirectionalLight::new(
+                context,
+                0.5,
+                Srgba::WHITE,
+                &vec3(-1.0, -1.0, -1.0),
+            )),
+        ]
+    });
+}
+
+#[test]
+fn ambient_light_with_environment() {
+    render_with_lights("ambient_light_with_environment", |context, _| {
+        let skybox = environment_map(context);
+        vec![Box::new(AmbientLight::new_with_environment(
+            context,
+            1.0,
+            Srgba::WHITE,
+            skybox.texture(),
+        ))]
+    });
+}
+
+#[test]
+fn ambient_light_with_loaded_environment() {
+    render_with_lights("ambient_light_with_loaded_environment", |context, _| {
+        let skybox = environment_map(context);
+        let environment = Environment::new_with_settings(
+            context,
+            skybox.texture(),
+            EnvironmentSettings {
+                irradiance_size: 16,
+                spherical_harmonics_irradiance: true,
+                prefilter_size: 32,
+                prefilter_sample_count: 64,
+                brdf_size: 64,
+                brdf_sample_count: 64,
+                format: EnvironmentFormat::F32,
+                ..Default::default()
+            },
+        );
+        let bytes = environment.to_bytes();
+        assert!(Environment::from_bytes(context, &bytes[..bytes.len() - 1]).is_err());
+        let mut corrupted = bytes.clone();
+        corrupted[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Environment::from_bytes(context, &corrupted).is_err());
+
+        let loaded = Environment::from_bytes(context, &bytes).unwrap();
+        assert!(loaded.to_bytes() == bytes);
+        vec![Box::new(AmbientLight {
+            intensity: 1.0,
+            color: Srgba::WHITE,
+            environment: Some(loaded),
+        })]
+    });
+}
+
+#[test]
+fn directional_light_with_shadow() {
+    render_with_lights("directional_light_with_shadow", |context, meshes| {
+        let mut light = DirectionalLight::new(context, 2.0, Srgba::WHITE, &vec3(-1.0, -2.0, -0.5));
+        light.generate_shadow_map(512, meshes.iter().copied());
+        vec![
+            Box::new(AmbientLight::new(context, 0.1, Srgba::WHITE)),
+            Box::new(light),
+        ]
+    });
+}
+
+#[test]
+fn point_light() {
+    render_with_lights("point_light", |context, _| {
+        vec![
+            Box::new(AmbientLight::new(context, 0.1, Srgba::WHITE)),
+            Box::new(PointLight::new(
+                context,
+                3.0,
+                Srgba::new_opaque(255, 220, 180),
+                &vec3(1.5, 1.5, 1.5),
+                Attenuation {
+                    constant: 1.0,
+                    linear: 0.2,
+                    quadratic: 0.1,
+                },
+            )),
+        ]
+    });
+}
+
+#[test]
+fn spot_light_with_shadow() {
+    render_with_lights("spot_light_with_shadow", |context, meshes| {
+        let mut light = SpotLight::new(
+            context,
+            3.0,
+            Srgba::WHITE,
+            &vec3(2.0, 3.0, 1.0),
+            &vec3(-2.0, -3.0, -1.0),
+            degrees(30.0),
+            Attenuation::default(),
+        );
+        light.generate_shadow_map(512, meshes.iter().copied());
+        vec![
+            Box::new(AmbientLight::new(context, 0.1, Srgba::WHITE)),
+            Box::new(light),
+        ]
+    });
+}
+
+#[test]
+fn color_material() {
+    render_with_material("color_material", |context| {
+        ColorMaterial::new_opaque(context, &material(Srgba::new_opaque(40, 160, 220)))
+    });
+}
+
+#[test]
+fn transparent_physical_material() {
+    let test = golden();
+    let context = test.context();
+    let ground = Gm::new(
+        ground(context),
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(180, 180, 180))),
+    );
+    let sphere = Gm::new(
+        sphere(context),
+        PhysicalMaterial::new_transparent(context, &material(Srgba::new(40, 200, 80, 128))),
+    );
+    let light = DirectionalLight::new(context, 2.0, Srgba::WHITE, &vec3(-1.0, -2.0, -0.5));
+    test.render("transparent_physical_material", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), ground.into_iter().chain(&sphere), &[&light]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn deferred_physical_material() {
+    let test = golden();
+    let context = test.context();
+    let sphere = Gm::new(
+        sphere(context),
+        DeferredPhysicalMaterial::new(context, &material(Srgba::new_opaque(200, 60, 40))),
+    );
+    let ground = Gm::new(
+        ground(context),
+        DeferredPhysicalMaterial::new(context, &material(Srgba::new_opaque(180, 180, 180))),
+    );
+    let ambient = AmbientLight::new(context, 0.1, Srgba::WHITE);
+    let light = DirectionalLight::new(context, 2.0, Srgba::WHITE, &vec3(-1.0, -2.0, -0.5));
+    test.render("deferred_physical_material", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(
+                &camera(&test),
+                sphere.into_iter().chain(&ground),
+                &[&ambient, &light],
+            );
+    })
+    .unwrap();
+}
+
+#[test]
+fn physical_material_with_lightmap() {
+    let test = golden();
+    let context = test.context();
+    let mut ground = ground(context);
+    ground.update_uvs2(&[
+        vec2(0.0, 0.0),
+        vec2(1.0, 0.0),
+        vec2(1.0, 1.0),
+        vec2(0.0, 1.0),
+    ]);
+    let lightmap = CpuTexture {
+        data: TextureData::RgbF32(vec![
+            [2.0, 1.6, 1.2],
+            [0.2, 0.2, 0.3],
+            [0.1, 0.1, 0.2],
+            [0.8, 0.8, 1.0],
+        ]),
+        width: 2,
+        height: 2,
+        ..Default::default()
+    };
+    let mut material =
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(180, 180, 180)));
+    material.lightmap_texture = Some(Texture2DRef::from_cpu_texture(context, &lightmap));
+    let ground = Gm::new(ground, material);
+    test.render("physical_material_with_lightmap", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), &ground, &[]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn lightmap_baker() {
+    let test = golden();
+    let context = test.context();
+    let square = CpuMesh::square();
+    let uvs = square.uvs.clone().unwrap();
+    let ground_material = material(Srgba::new_opaque(180, 180, 180));
+    let wall_material = material(Srgba::new_opaque(200, 60, 40));
+    let ground = ground(context);
+    let mut wall = Mesh::new(context, &square);
+    wall.set_transformation(
+        Mat4::from_translation(vec3(-1.0, 0.0, 0.0)) * Mat4::from_angle_y(degrees(90.0)),
+    );
+    let mut light = DirectionalLight::new(context, 2.0, Srgba::WHITE, &vec3(-1.0, -2.0, -0.5));
+    light.generate_shadow_map(512, [&ground, &wall]);
+    let meshes = [(&ground, &ground_material), (&wall, &wall_material)];
+    let mut baker = LightmapBaker::new(context);
+    baker.lightmap_size = 16;
+    baker.hemicube_size = 8;
+    let lightmaps = baker.bake(
+        &meshes
+            .iter()
+            .map(|(mesh, material)| LightmapMesh {
+                cpu_mesh: &square,
+                cpu_material: material,
+                lightmap_uvs: &uvs,
+                transformation: mesh.transformation(),
+            })
+            .collect::<Vec<_>>(),
+        &[&light, &AmbientLight::new(context, 0.1, Srgba::WHITE)],
+    );
+    let objects = meshes
+        .iter()
+        .zip(lightmaps.iter())
+        .map(|((mesh, material), lightmap)| {
+            let mut lightmapped = Mesh::new(context, &square);
+            lightmapped.set_transformation(mesh.transformation());
+            lightmapped.update_uvs2(&uvs);
+            let mut material = PhysicalMaterial::new_opaque(context, material);
+            material.lightmap_texture = Some(Texture2DRef::from_cpu_texture(context, lightmap));
+            Gm::new(lightmapped, material)
+        })
+        .collect::<Vec<_>>();
+    test.render("lightmap_baker", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), &objects, &[]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn normal_material() {
+    render_with_material("normal_material", |context| {
+        NormalMaterial::new(context, &CpuMaterial::default())
+    });
+}
+
+#[test]
+fn depth_material() {
+    render_with_material("depth_material", |_| DepthMaterial {
+        min_distance: Some(3.0),
+        max_distance: Some(7.0),
+        ..Default::default()
+    });
+}
+
+#[test]
+fn position_material() {
+    render_with_material("position_material", |_| PositionMaterial::default());
+}
+
+#[test]
+fn uv_material() {
+    let test = golden();
+    let cube = Gm::new(
+        Mesh::new(test.context(), &CpuMesh::cube()),
+        UVMaterial::default(),
+    );
+    test.render("uv_material", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(&camera(&test), &cube, &[]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn orm_material() {
+    render_with_material("orm_material", |context| {
+        ORMMaterial::new(context, &material(Srgba::WHITE))
+    });
+}
+
+#[test]
+fn skybox() {
+    let test = golden();
+    let skybox = environment_map(test.context());
+    test.render("skybox", |target| {
+        target
+            .clear(ClearState::depth(1.0))
+            .render(&camera(&test), &skybox, &[]);
+    })
+    .unwrap();
+}
+
+#[test]
+fn path_stroke() {
+    let test = golden();
+    let context = test.context();
+    let path = Path::new()
+        .move_to(vec2(16.0, 16.0))
+        .line_to(vec2(112.0, 16.0))
+        .line_to(vec2(112.0, 112.0))
+        .close();
+    // A dash pattern with an odd number of lengths is repeated twice
+    let dashed = Gm::new(
+        Mesh::new(
+            context,
+            &path.stroke(
+                &StrokeStyle {
+                    width: 6.0,
+                    dash_pattern: vec![12.0],
+                    ..Default::default()
+                },
+                0.5,
+            ),
+        ),
+        ColorMaterial {
+            color: Srgba::new_opaque(220, 220, 80),
+            ..Default::default()
+        },
+    );
+    // A translucent stroke is only blended once when the depth test rejects the overlapping triangles
+    let mut translucent = Gm::new(
+        Mesh::new(
+            context,
+            &Path::new()
+                .move_to(vec2(24.0, 40.0))
+                .line_to(vec2(64.0, 100.0))
+                .line_to(vec2(104.0, 40.0))
+                .stroke(
+                    &StrokeStyle {
+                        width: 16.0,
+                        join: LineJoin::Round,
+                        cap: LineCap::Round,
+                        ..Default::default()
+                    },
+                    0.5,
+                ),
+        ),
+        ColorMaterial {
+            color: Srgba::new(80, 160, 255, 128),
+            ..Default::default()
+        },
+    );
+    translucent.material.render_states.depth_test = DepthTest::Less;
+    translucent.material.render_states.write_mask = WriteMask::COLOR_AND_DEPTH;
+    test.render("path_stroke", |target| {
+        target
+            .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+            .render(
+                &Camera::new_2d(test.viewport()),
+                dashed.into_iter().chain(&translucent),
+                &[],
+            );
+    })
+    .unwrap();
+}
+
+///
+/// Renders the scene into a color and depth texture and applies the given effect to the screen using those textures.
+///
+fn render_with_effect(name: &str, effect: impl Effect) {
+    let test = golden();
+    let context = test.context();
+    let camera = camera(&test);
+    let sphere = Gm::new(
+        sphere(context),
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(200, 60, 40))),
+    );
+    let ground = Gm::new(
+        ground(context),
+        PhysicalMaterial::new_opaque(context, &material(Srgba::new_opaque(180, 180, 180))),
+    );
+    let light = DirectionalLight::new(context, 2.0, Srgba::WHITE, &vec3(-1.0, -2.0, -0.5));
+    let viewport = test.viewport();
+    let mut color_texture = Texture2D::new_empty::<[u8; 4]>(
+        context,
+        viewport.width,
+        viewport.height,
+        Interpolation::Nearest,
+        Interpolation::Nearest,
+        None,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    );
+    let mut depth_texture = DepthTexture2D::new::<f32>(
+        context,
+        viewport.width,
+        viewport.height,
+        Wrapping::ClampToEdge,
+        Wrapping::ClampToEdge,
+    );
+    RenderTarget::new(
+        color_texture.as_color_target(None),
+        depth_texture.as_depth_target(),
+    )
+    .clear(ClearState::color_and_depth(0.1, 0.1, 0.15, 1.0, 1.0))
+    .render(&camera, sphere.into_iter().chain(&ground), &[&light]);
+    test.render(name, |target| {
+        target.clear(ClearState::default()).apply_screen_effect(
+            &effect,
+            &camera,
+            &[],
+            Some(ColorTexture::Single(&color_texture)),
+            Some(DepthTexture::Single(&depth_texture)),
+        );
+    })
+    .unwrap();
+}
+
+#[test]
+fn copy_effect() {
+    render_with_effect("copy_effect", CopyEffect::default());
+}
+
+#[test]
+fn fxaa_effect() {
+    render_with_effect("fxaa_effect", FxaaEffect::default());
+}
+
+#[test]
+fn fog_effect() {
+    render_with_effect(
+        "fog_effect",
+        FogEffect {
+            color: Srgba::new_opaque(200, 200, 220),
+            density: 0.2,
+            animation: 0.0,
+            time: 0.0,
+        },
+    );
+}